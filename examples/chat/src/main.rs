@@ -69,6 +69,7 @@ struct Message {
     username: String,
     message: String,
     #[serde(skip)]
+    #[sql(auto_now_add)]
     date_added: DateTime<Utc>,
 }
 
@@ -88,8 +89,7 @@ fn chat(req: &mut Request) -> IronResult<Response> {
                 // Insert a new message.
                 let _ = sql!(Message.insert(
                             username = username,
-                            message = message,
-                            date_added = Utc::now()
+                            message = message
                         ));
             }
         }
@@ -116,7 +116,7 @@ fn main() {
         let connection = pool.get().unwrap();
 
         // Create the Message table.
-        let _ = sql!(Message.create());
+        sql!(Message.create_if_not_exists()).unwrap();
     }
 
     let mut chain = Chain::new(chat);
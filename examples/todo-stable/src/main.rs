@@ -44,8 +44,7 @@ struct TodoItem {
 
 fn add_todo_item(connection: Connection, text: String) {
     // Insert the new item.
-    let date_added = Utc::now(); // FIXME: remove the variable when function-like proc-macro works on stable.
-    let result = sql!(connection, TodoItem.insert(text = text, date_added = date_added, done = false));
+    let result = sql!(connection, TodoItem.insert(text = text, date_added = Utc::now(), done = false));
     if let Err(err) = result {
         println!("Failed to add the item ({})", err);
     }
@@ -120,7 +119,7 @@ fn main() {
     let connection = get_connection();
 
     // Create the table.
-    let _ = sql!(connection, TodoItem.create());
+    sql!(connection, TodoItem.create_if_not_exists()).unwrap();
 
     let mut args = env::args();
     args.next();
@@ -121,7 +121,7 @@ fn main() {
     let connection = get_connection();
 
     // Create the table.
-    let _ = sql!(TodoItem.create());
+    sql!(TodoItem.create_if_not_exists()).unwrap();
 
     let mut args = env::args();
     args.next();
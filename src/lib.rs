@@ -23,20 +23,47 @@
 //!
 //! It type check your expression at compile time and converts it to SQL.
 
+#[cfg(feature = "bigdecimal")]
+extern crate bigdecimal;
 #[cfg(feature = "chrono")]
 extern crate chrono;
 #[cfg(feature = "postgres")]
 extern crate postgres;
+#[cfg(feature = "regex")]
+extern crate regex;
 #[cfg(feature = "rusqlite")]
 extern crate rusqlite;
+#[cfg(feature = "rust_decimal")]
+extern crate rust_decimal;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde_json")]
+extern crate serde_json;
+extern crate tql_snapshot;
+#[cfg(feature = "uuid")]
+extern crate uuid;
+
+use std::error;
+use std::fmt::{self, Display, Formatter};
+#[cfg(feature = "postgres")]
+use std::io;
 
 pub mod aggregates;
 mod methods;
+pub mod migration;
+#[cfg(all(feature = "regex", feature = "rusqlite"))]
+pub mod sqlite;
 mod types;
+mod value;
 
+pub use methods::{Interval, Now, days, hours, minutes, months, now, seconds, years};
+pub use migration::{check_all, compare_schema};
 pub use types::{Date, DateTime, Time, ToTqlType};
 use types::StdI32;
 pub use types::numbers::{i16, i32, i64, i8, u16, u32, u64, u8};
+pub use value::{ColumnType, EnumType, EnumValue, SqlType, SqlValue};
+#[cfg(feature = "json")]
+pub use value::Json;
 
 /// The `ForeignKey` is optional.
 ///
@@ -46,6 +73,62 @@ pub type ForeignKey<T> = Option<T>;
 /// A `PrimaryKey` is a 4-byte integer.
 pub type PrimaryKey = StdI32;
 
+/// The error returned by `update()` on a table with a `#[sql(version)]` field when the row does
+/// not have the expected version: it was updated or deleted since it was read.
+#[derive(Debug)]
+pub struct StaleObject;
+
+impl Display for StaleObject {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "the row was updated or deleted since it was read")
+    }
+}
+
+impl error::Error for StaleObject {
+    fn description(&self) -> &str {
+        "stale object"
+    }
+}
+
+/// Check if the `error` was returned by `update()` because the row was updated or deleted since it
+/// was read.
+#[cfg(feature = "postgres")]
+pub fn is_stale_object(error: &::postgres::Error) -> bool {
+    error.as_io()
+        .and_then(|error| error.get_ref())
+        .map_or(false, |error| error.is::<StaleObject>())
+}
+
+/// Check if the `error` was returned by `update()` because the row was updated or deleted since it
+/// was read.
+#[cfg(feature = "rusqlite")]
+pub fn is_stale_object(error: &::rusqlite::Error) -> bool {
+    if let ::rusqlite::Error::UserFunctionError(ref error) = *error {
+        return error.is::<StaleObject>();
+    }
+    false
+}
+
+#[cfg(feature = "postgres")]
+#[doc(hidden)]
+// Used to return an error when no row was updated because none has the expected version.
+pub fn check_version<T: Default + PartialEq>(updated_count: T) -> ::postgres::Result<T> {
+    if updated_count == T::default() {
+        return Err(io::Error::new(io::ErrorKind::Other, StaleObject).into());
+    }
+    Ok(updated_count)
+}
+
+#[cfg(feature = "rusqlite")]
+#[doc(hidden)]
+// Used to return an error when no row was updated because none has the expected version.
+pub fn check_version<T: Default + PartialEq>(updated_count: T) -> ::rusqlite::Result<T> {
+    if updated_count == T::default() {
+        return Err(::rusqlite::Error::UserFunctionError(Box::new(StaleObject)));
+    }
+    Ok(updated_count)
+}
+
 #[doc(hidden)]
 // Marker trait used for error reporting:
 // when a struct is used in a ForeignKey, but it is not annotated with #[derive(SqlTable)].
@@ -57,6 +140,11 @@ pub unsafe trait SqlTable {
 
     fn _tql_default() -> Self;
 
+    // The name of the primary key column, used in the schema of the tables referencing this one.
+    fn _tql_primary_key() -> &'static str;
+
+    fn _tql_schema() -> migration::TableSchema;
+
     #[cfg(feature = "postgres")]
     fn from_row(row: &::postgres::rows::Row) -> Self;
 
@@ -70,6 +158,50 @@ pub unsafe trait SqlTable {
     fn from_related_row(row: &::rusqlite::Row, delta: StdI32) -> Self;
 }
 
+#[doc(hidden)]
+// Marker trait used for error reporting:
+// when a struct is used in an embedded field, but it is not annotated with #[derive(SqlEmbed)].
+pub unsafe trait SqlEmbed {
+    #[cfg(feature = "postgres")]
+    fn from_row(row: &::postgres::rows::Row, delta: usize) -> Self;
+
+    #[cfg(feature = "rusqlite")]
+    fn from_row(row: &::rusqlite::Row, delta: StdI32) -> Self;
+}
+
+#[doc(hidden)]
+// Marker trait used to check that a value can be compared to another one in a query, e.g. a field
+// and the column returned by a subquery.
+pub trait ComparableColumn<T> {}
+
+impl<T> ComparableColumn<T> for T {}
+
+impl<T: SqlTable> ComparableColumn<PrimaryKey> for ForeignKey<T> {}
+
+impl<'a> ComparableColumn<&'a str> for String {}
+
+impl<'a> ComparableColumn<&'a String> for String {}
+
+#[doc(hidden)]
+pub fn check_comparison<T: ComparableColumn<U>, U>(_value: &T, _other: &U) {
+}
+
+#[doc(hidden)]
+pub fn check_subquery_column<T: ComparableColumn<U>, U>(_field: &T, _column: &U) {
+}
+
+#[doc(hidden)]
+// Used to type check the call of a user-defined function with a field as argument.
+pub fn field_value<T>(_field: &T) -> T {
+    unreachable!()
+}
+
+#[doc(hidden)]
+// Used to type check the call of a user-defined function through a function pointer with its signature.
+pub fn function_result<T>() -> T {
+    unreachable!()
+}
+
 #[cfg(feature = "postgres")]
 #[doc(hidden)]
 pub fn from_related_row<T: SqlTable>(field: &mut Option<T>, row: &::postgres::rows::Row, delta: usize) -> usize
@@ -104,6 +236,15 @@ macro_rules! check_missing_fields {
 #[cfg(not(unstable))]
 #[macro_export]
 macro_rules! sql {
+    // The information about the tables of the query, given by the tql_<Table>_metadata!() macros.
+    ($(@tql_metadata $table:ident $metadata:tt)+ $connection:ident, $($tt:tt)*) => {{
+        #[derive(StableToSql)]
+        enum __TqlStableToSqlEnum {
+            Input = (stringify!($(@tql_metadata $table $metadata)+ $($tt)*), 0).1,
+        }
+
+        __tql_call_metadata_macro!($connection, $($tt)*)
+    }};
     ($connection:ident, $($tt:tt)*) => {{
         #[derive(StableToSql)]
         enum __TqlStableToSqlEnum {
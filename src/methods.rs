@@ -22,17 +22,45 @@
 //! These methods should not be used directly:
 //! they exist only for type checking.
 
-use types::{Date, DateTime, Time, TqlOption, TqlString};
+use std::ops::{Add, Sub};
+
+#[cfg(feature = "chrono")]
+use chrono::{self, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use ComparableColumn;
+use types::{Date, DateTime, Time, ToTqlType, TqlArray, TqlJson, TqlOption, TqlString};
 
 impl Date {
     pub fn day(&self) -> i32 { 0 }
     pub fn month(&self) -> i32 { 0 }
+    /// Returns 0 for Monday.
+    pub fn weekday(&self) -> i32 { 0 }
     pub fn year(&self) -> i32 { 0 }
 }
 
+impl ToTqlType for Date {
+    type Target = Date;
+    fn to_tql_type(&self) -> Self::Target { Date }
+}
+
+impl ComparableColumn<Now> for Date {}
+
+#[cfg(feature = "chrono")]
+impl ComparableColumn<NaiveDate> for Date {}
+
+#[cfg(feature = "chrono")]
+impl ComparableColumn<chrono::Date<Local>> for Date {}
+
+#[cfg(feature = "chrono")]
+impl ComparableColumn<chrono::Date<Utc>> for Date {}
+
 impl DateTime {
+    pub fn date(&self) -> Date { Date }
+    pub fn date_trunc(&self, _unit: &str) -> DateTime { DateTime }
     pub fn day(&self) -> i32 { 0 }
     pub fn month(&self) -> i32 { 0 }
+    /// Returns 0 for Monday.
+    pub fn weekday(&self) -> i32 { 0 }
     pub fn year(&self) -> i32 { 0 }
 
     pub fn hour(&self) -> i32 { 0 }
@@ -40,19 +68,103 @@ impl DateTime {
     pub fn second(&self) -> i32 { 0 }
 }
 
+impl ToTqlType for DateTime {
+    type Target = DateTime;
+    fn to_tql_type(&self) -> Self::Target { DateTime }
+}
+
+impl ComparableColumn<Now> for DateTime {}
+
+#[cfg(feature = "chrono")]
+impl ComparableColumn<NaiveDateTime> for DateTime {}
+
+#[cfg(feature = "chrono")]
+impl ComparableColumn<chrono::DateTime<Local>> for DateTime {}
+
+#[cfg(feature = "chrono")]
+impl ComparableColumn<chrono::DateTime<Utc>> for DateTime {}
+
 impl Time {
     pub fn hour(&self) -> i32 { 0 }
     pub fn minute(&self) -> i32 { 0 }
     pub fn second(&self) -> i32 { 0 }
 }
 
+/// The current date and time of the database, from `now()`.
+#[doc(hidden)]
+pub struct Now;
+
+/// An interval, from `days(7)` for instance.
+#[doc(hidden)]
+pub struct Interval;
+
+#[doc(hidden)]
+pub fn now() -> Now { Now }
+
+#[doc(hidden)]
+pub fn seconds(_amount: i32) -> Interval { Interval }
+
+#[doc(hidden)]
+pub fn minutes(_amount: i32) -> Interval { Interval }
+
+#[doc(hidden)]
+pub fn hours(_amount: i32) -> Interval { Interval }
+
+#[doc(hidden)]
+pub fn days(_amount: i32) -> Interval { Interval }
+
+#[doc(hidden)]
+pub fn months(_amount: i32) -> Interval { Interval }
+
+#[doc(hidden)]
+pub fn years(_amount: i32) -> Interval { Interval }
+
+impl Add<Interval> for Now {
+    type Output = Now;
+    fn add(self, _interval: Interval) -> Now { Now }
+}
+
+impl Sub<Interval> for Now {
+    type Output = Now;
+    fn sub(self, _interval: Interval) -> Now { Now }
+}
+
+#[cfg(feature = "chrono")]
+impl ComparableColumn<Now> for NaiveDate {}
+
+#[cfg(feature = "chrono")]
+impl ComparableColumn<Now> for NaiveDateTime {}
+
+#[cfg(feature = "chrono")]
+impl<Tz: TimeZone> ComparableColumn<Now> for chrono::DateTime<Tz> {}
+
 impl TqlString {
     pub fn contains(&self, _string: &str) -> bool { false }
     pub fn ends_with(&self, _string: &str) -> bool { false }
+    pub fn eq_ignore_case(&self, _string: &str) -> bool { false }
+    pub fn icontains(&self, _string: &str) -> bool { false }
+    pub fn iends_with(&self, _string: &str) -> bool { false }
     pub fn iregex(&self, _string: &str) -> bool { false }
+    pub fn istarts_with(&self, _string: &str) -> bool { false }
     pub fn len(&self) -> usize { 0 }
     pub fn regex(&self, _string: &str) -> bool { false }
     pub fn starts_with(&self, _string: &str) -> bool { false }
+    /// The `start` index is 1-based, like in SQL.
+    pub fn substring(&self, _start: i32, _length: i32) -> String { String::new() }
+    pub fn to_lowercase(&self) -> String { String::new() }
+    pub fn to_uppercase(&self) -> String { String::new() }
+    pub fn trim(&self) -> String { String::new() }
+}
+
+impl<T> TqlArray<T> {
+    pub fn contains_element<U>(&self, _element: U) -> bool where T: ComparableColumn<U> { false }
+    pub fn len(&self) -> usize { 0 }
+    pub fn overlaps<U>(&self, _elements: &[U]) -> bool where T: ComparableColumn<U> { false }
+}
+
+impl TqlJson {
+    pub fn get_str(&self, _key: &str) -> String { String::new() }
+    pub fn has_key(&self, _key: &str) -> bool { false }
 }
 
 impl TqlOption {
@@ -0,0 +1,1099 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Schema migrations generated from the differences between two snapshots of the models.
+//!
+//! The schema of a table deriving `SqlTable` is obtained with `Schema::table()`. It can be saved
+//! to a snapshot and compared to the previous snapshot to produce the steps of a migration, which
+//! are converted to SQL for a backend and applied to a database with `migrate()`.
+
+use std::env;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::result;
+
+use tql_snapshot::{SNAPSHOT_HEADER, normalize_type, parse_snapshot, type_and_constraint};
+
+use SqlTable;
+
+/// The name of the table storing the names of the applied migrations.
+pub const HISTORY_TABLE: &str = "tql_migrations";
+
+/// The environment variable containing the directory where the tests generated by
+/// `#[derive(SqlTable)]` write the snapshot of their table.
+pub const SNAPSHOT_DIR_VARIABLE: &str = "TQL_SNAPSHOT_DIR";
+
+/// The errors of the migrations: either a database error or an invalid snapshot.
+pub type Result<T> = result::Result<T, Box<error::Error + Send + Sync>>;
+
+/// The database backend for which the SQL of a migration is generated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Postgres,
+    Sqlite,
+}
+
+/// A column of a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: String,
+    /// The SQL type of the column, with its `PRIMARY KEY` or `REFERENCES` constraint.
+    pub sql_type: String,
+    pub nullable: bool,
+    /// The SQL expression of the default value.
+    pub default: Option<String>,
+}
+
+impl Column {
+    /// Get the definition of the column as found in a `CREATE TABLE` query (without the name).
+    pub fn definition(&self) -> String {
+        let mut definition = self.sql_type.clone();
+        if !self.nullable {
+            definition.push_str(" NOT NULL");
+        }
+        if let Some(ref default) = self.default {
+            definition.push_str(" DEFAULT ");
+            definition.push_str(default);
+        }
+        definition
+    }
+
+    /// Get the table referenced by the `REFERENCES` constraint of the column.
+    pub fn referenced_table(&self) -> Option<&str> {
+        let (_, constraint) = self.type_and_constraint();
+        if constraint.starts_with("REFERENCES ") {
+            let table = &constraint["REFERENCES ".len()..];
+            Some(table.split('(').next().unwrap_or(table).trim())
+        }
+        else {
+            None
+        }
+    }
+
+    /// Split the SQL type in the type itself and its constraint.
+    pub fn type_and_constraint(&self) -> (&str, &str) {
+        type_and_constraint(&self.sql_type)
+    }
+}
+
+/// An index on columns of a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Index {
+    pub name: String,
+    pub columns: Vec<String>,
+}
+
+impl Index {
+    /// Get the index on a single column, as created by `#[sql(index)]`.
+    pub fn on_column(table: &str, column: &str) -> Self {
+        Index {
+            name: format!("{}_{}_idx", table, column),
+            columns: vec![column.to_string()],
+        }
+    }
+
+    fn create_sql(&self, table: &str) -> String {
+        format!("CREATE INDEX {} ON {} ({})", self.name, table, self.columns.join(", "))
+    }
+
+    fn drop_sql(&self) -> String {
+        format!("DROP INDEX {}", self.name)
+    }
+}
+
+/// The schema of a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub indexes: Vec<Index>,
+}
+
+impl TableSchema {
+    /// Get the column called `name`.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Get the `CREATE TABLE` query of this table.
+    pub fn create_sql(&self) -> String {
+        self.create_sql_with_name(&self.name)
+    }
+
+    fn create_sql_with_name(&self, name: &str) -> String {
+        let columns = self.columns.iter()
+            .map(|column| format!("{} {}", column.name, column.definition()))
+            .collect::<Vec<_>>();
+        format!("CREATE TABLE {} ({})", name, columns.join(", "))
+    }
+
+    fn column_mut(&mut self, name: &str) -> Option<&mut Column> {
+        self.columns.iter_mut().find(|column| column.name == name)
+    }
+}
+
+/// The schema of a database: the tables in the order they are created.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    pub tables: Vec<TableSchema>,
+}
+
+impl Schema {
+    /// Create an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the schema of the table `T` to this schema.
+    ///
+    /// The tables referenced by a `ForeignKey` must be added before the tables referencing them.
+    pub fn table<T: SqlTable>(mut self) -> Self {
+        self.tables.push(T::_tql_schema());
+        self
+    }
+
+    /// Create a schema from `tables` in any order: the tables referenced by a foreign key are moved
+    /// before the tables referencing them.
+    pub fn from_tables(tables: Vec<TableSchema>) -> Self {
+        Schema {
+            tables: sort_by_references(tables),
+        }
+    }
+
+    /// Get the table called `name`.
+    pub fn get(&self, name: &str) -> Option<&TableSchema> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut TableSchema> {
+        self.tables.iter_mut().find(|table| table.name == name)
+    }
+
+    /// Get the schema of the tables of the `database`, except the history table of the migrations.
+    ///
+    /// The SQL types are written as in the `CREATE TABLE` queries, with their `PRIMARY KEY` or
+    /// `REFERENCES` constraint, and the tables referenced by a foreign key come first.
+    pub fn from_database<D: Database>(database: &D) -> Result<Self> {
+        let tables =
+            match database.backend() {
+                Backend::Postgres => postgres_tables(database)?,
+                Backend::Sqlite => sqlite_tables(database)?,
+            };
+        Ok(Schema {
+            tables: sort_by_references(tables),
+        })
+    }
+
+    /// Parse a snapshot created by `to_snapshot()`.
+    pub fn from_snapshot(snapshot: &str) -> Result<Self> {
+        let tables = parse_snapshot(snapshot).map_err(|error| format!("invalid snapshot: {}", error))?;
+        Ok(Schema {
+            tables: tables.into_iter()
+                .map(|table| TableSchema {
+                    name: table.name.to_string(),
+                    columns: table.columns.into_iter()
+                        .map(|column| Column {
+                            name: column.name.to_string(),
+                            sql_type: column.sql_type.to_string(),
+                            nullable: column.nullable,
+                            default: column.default.map(str::to_string),
+                        })
+                        .collect(),
+                    indexes: table.indexes.into_iter()
+                        .map(|index| Index {
+                            name: index.name.to_string(),
+                            columns: index.columns.into_iter().map(str::to_string).collect(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Convert the schema to a snapshot, a text format with one line per table, column and index.
+    pub fn to_snapshot(&self) -> String {
+        let mut snapshot = format!("{}\n", SNAPSHOT_HEADER);
+        for table in &self.tables {
+            snapshot.push_str(&format!("table\t{}\n", table.name));
+            for column in &table.columns {
+                let nullable = if column.nullable { "NULL" } else { "NOT NULL" };
+                snapshot.push_str(&format!("column\t{}\t{}\t{}", column.name, column.sql_type, nullable));
+                if let Some(ref default) = column.default {
+                    snapshot.push_str(&format!("\t{}", default));
+                }
+                snapshot.push('\n');
+            }
+            for index in &table.indexes {
+                snapshot.push_str(&format!("index\t{}\t{}\n", index.name, index.columns.join(",")));
+            }
+        }
+        snapshot
+    }
+
+    /// Get the steps to migrate from this schema to the `new` one.
+    ///
+    /// The columns removed from a table are dropped and the columns added to a table are created:
+    /// use `diff_with_renames()` to rename columns instead.
+    pub fn diff(&self, new: &Schema) -> Vec<Step> {
+        self.diff_with_renames(new, &[])
+    }
+
+    /// Get the steps to migrate from this schema to the `new` one, where the columns of the
+    /// `renames` are renamed instead of being dropped and created.
+    pub fn diff_with_renames(&self, new: &Schema, renames: &[Rename]) -> Vec<Step> {
+        let mut steps = vec![];
+        for table in &new.tables {
+            if self.get(&table.name).is_none() {
+                steps.push(Step::CreateTable(table.clone()));
+            }
+        }
+        for table in &new.tables {
+            if let Some(old_table) = self.get(&table.name) {
+                diff_tables(old_table, table, renames, &mut steps);
+            }
+        }
+        // NOTE: the tables are dropped in the reverse order to drop the tables referencing another
+        // table first.
+        for table in self.tables.iter().rev() {
+            if new.get(&table.name).is_none() {
+                steps.push(Step::DropTable(table.clone()));
+            }
+        }
+        steps
+    }
+
+    /// Get the columns which might have been renamed from this schema to the `new` one, i.e. the
+    /// columns removed from a table with the same definition as a column added to this table.
+    pub fn rename_candidates(&self, new: &Schema) -> Vec<Rename> {
+        let mut renames = vec![];
+        for table in &new.tables {
+            if let Some(old_table) = self.get(&table.name) {
+                for old_column in old_table.columns.iter().filter(|column| table.column(&column.name).is_none()) {
+                    let added_columns = table.columns.iter()
+                        .filter(|column| old_table.column(&column.name).is_none())
+                        .filter(|column| column.definition() == old_column.definition());
+                    for column in added_columns {
+                        renames.push(Rename {
+                            table: table.name.clone(),
+                            old_name: old_column.name.clone(),
+                            new_name: column.name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        renames
+    }
+
+    /// Update the schema with the change done by `step`.
+    pub fn apply(&mut self, step: &Step) {
+        match *step {
+            Step::CreateTable(ref table) => self.tables.push(table.clone()),
+            Step::DropTable(ref table) => self.tables.retain(|current_table| current_table.name != table.name),
+            Step::AddColumn { ref table, ref column } => {
+                if let Some(table) = self.get_mut(table) {
+                    table.columns.push(column.clone());
+                }
+            },
+            Step::DropColumn { ref table, ref column } => {
+                if let Some(table) = self.get_mut(table) {
+                    table.columns.retain(|current_column| current_column.name != column.name);
+                }
+            },
+            Step::RenameColumn { ref table, ref old_name, ref new_name } => {
+                if let Some(table) = self.get_mut(table) {
+                    if let Some(column) = table.column_mut(old_name) {
+                        column.name = new_name.clone();
+                    }
+                }
+            },
+            Step::ChangeColumn { ref table, ref new, .. } => {
+                if let Some(table) = self.get_mut(table) {
+                    if let Some(column) = table.column_mut(&new.name) {
+                        *column = new.clone();
+                    }
+                }
+            },
+            Step::AddIndex { ref table, ref index } => {
+                if let Some(table) = self.get_mut(table) {
+                    table.indexes.push(index.clone());
+                }
+            },
+            Step::DropIndex { ref table, ref index } => {
+                if let Some(table) = self.get_mut(table) {
+                    table.indexes.retain(|current_index| current_index.name != index.name);
+                }
+            },
+        }
+    }
+}
+
+/// Add the steps to migrate the `old` table to the `new` one.
+fn diff_tables(old: &TableSchema, new: &TableSchema, renames: &[Rename], steps: &mut Vec<Step>) {
+    let table = &new.name;
+    // NOTE: the indexes are dropped first since their columns might be dropped or renamed.
+    for index in &old.indexes {
+        if !new.indexes.contains(index) {
+            steps.push(Step::DropIndex { table: table.clone(), index: index.clone() });
+        }
+    }
+
+    let renamed_columns: Vec<_> = renames.iter()
+        .filter(|rename| rename.table == *table)
+        .filter(|rename| new.column(&rename.old_name).is_none() && old.column(&rename.new_name).is_none())
+        .filter_map(|rename| Some((old.column(&rename.old_name)?, new.column(&rename.new_name)?)))
+        .collect();
+    for &(old_column, column) in &renamed_columns {
+        steps.push(Step::RenameColumn {
+            table: table.clone(),
+            old_name: old_column.name.clone(),
+            new_name: column.name.clone(),
+        });
+        let renamed_column = Column {
+            name: column.name.clone(),
+            ..old_column.clone()
+        };
+        if renamed_column != *column {
+            steps.push(Step::ChangeColumn {
+                table: table.clone(),
+                old: renamed_column,
+                new: column.clone(),
+            });
+        }
+    }
+
+    let dropped_columns: Vec<_> = old.columns.iter()
+        .filter(|column| new.column(&column.name).is_none())
+        .filter(|column| !renamed_columns.iter().any(|&(old_column, _)| old_column.name == column.name))
+        .collect();
+    let added_columns = new.columns.iter()
+        .filter(|column| old.column(&column.name).is_none())
+        .filter(|column| !renamed_columns.iter().any(|&(_, new_column)| new_column.name == column.name));
+    for column in added_columns {
+        steps.push(Step::AddColumn { table: table.clone(), column: column.clone() });
+    }
+
+    for column in &new.columns {
+        if let Some(old_column) = old.column(&column.name) {
+            if old_column != column {
+                steps.push(Step::ChangeColumn {
+                    table: table.clone(),
+                    old: old_column.clone(),
+                    new: column.clone(),
+                });
+            }
+        }
+    }
+
+    for column in dropped_columns {
+        steps.push(Step::DropColumn { table: table.clone(), column: column.clone() });
+    }
+
+    for index in &new.indexes {
+        if !old.indexes.contains(index) {
+            steps.push(Step::AddIndex { table: table.clone(), index: index.clone() });
+        }
+    }
+}
+
+/// A column renamed in a table.
+///
+/// Since a renamed column cannot be told apart from a dropped column and an added column, the
+/// renames are given to `Schema::diff_with_renames()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rename {
+    pub table: String,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+impl Rename {
+    /// Get the rename reverting this one.
+    pub fn reverse(&self) -> Self {
+        Rename {
+            table: self.table.clone(),
+            old_name: self.new_name.clone(),
+            new_name: self.old_name.clone(),
+        }
+    }
+}
+
+/// A change to the schema of the database.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    CreateTable(TableSchema),
+    DropTable(TableSchema),
+    AddColumn {
+        table: String,
+        column: Column,
+    },
+    DropColumn {
+        table: String,
+        column: Column,
+    },
+    RenameColumn {
+        table: String,
+        old_name: String,
+        new_name: String,
+    },
+    /// Change the type, nullability or default value of a column.
+    ChangeColumn {
+        table: String,
+        old: Column,
+        new: Column,
+    },
+    AddIndex {
+        table: String,
+        index: Index,
+    },
+    DropIndex {
+        table: String,
+        index: Index,
+    },
+}
+
+impl Step {
+    /// Convert the step to SQL queries for the `backend`, where `schema` is the schema before the
+    /// step is applied.
+    pub fn to_sql(&self, schema: &Schema, backend: Backend) -> Vec<String> {
+        match *self {
+            Step::CreateTable(ref table) => {
+                let mut queries = vec![table.create_sql()];
+                queries.extend(table.indexes.iter().map(|index| index.create_sql(&table.name)));
+                queries
+            },
+            Step::DropTable(ref table) => vec![format!("DROP TABLE {}", table.name)],
+            Step::AddColumn { ref table, ref column } =>
+                vec![format!("ALTER TABLE {} ADD COLUMN {} {}", table, column.name, column.definition())],
+            Step::DropColumn { ref table, ref column } =>
+                match backend {
+                    Backend::Postgres => vec![format!("ALTER TABLE {} DROP COLUMN {}", table, column.name)],
+                    Backend::Sqlite => rebuild_table(self, schema, table),
+                },
+            Step::RenameColumn { ref table, ref old_name, ref new_name } =>
+                vec![format!("ALTER TABLE {} RENAME COLUMN {} TO {}", table, old_name, new_name)],
+            Step::ChangeColumn { ref table, ref old, ref new } =>
+                match backend {
+                    Backend::Postgres => alter_column(table, old, new),
+                    Backend::Sqlite => rebuild_table(self, schema, table),
+                },
+            Step::AddIndex { ref table, ref index } => vec![index.create_sql(table)],
+            Step::DropIndex { ref index, .. } => vec![index.drop_sql()],
+        }
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            Step::CreateTable(ref table) => write!(formatter, "Create table {}", table.name),
+            Step::DropTable(ref table) => write!(formatter, "Drop table {}", table.name),
+            Step::AddColumn { ref table, ref column } => write!(formatter, "Add column {}.{}", table, column.name),
+            Step::DropColumn { ref table, ref column } => write!(formatter, "Drop column {}.{}", table, column.name),
+            Step::RenameColumn { ref table, ref old_name, ref new_name } =>
+                write!(formatter, "Rename column {}.{} to {}", table, old_name, new_name),
+            Step::ChangeColumn { ref table, ref new, .. } => write!(formatter, "Change column {}.{}", table, new.name),
+            Step::AddIndex { ref table, ref index } => write!(formatter, "Add index {} on {}", index.name, table),
+            Step::DropIndex { ref table, ref index } => write!(formatter, "Drop index {} on {}", index.name, table),
+        }
+    }
+}
+
+/// Get the PostgreSQL queries changing the `old` column to the `new` one.
+fn alter_column(table: &str, old: &Column, new: &Column) -> Vec<String> {
+    let mut queries = vec![];
+    let alter_column = format!("ALTER TABLE {} ALTER COLUMN {}", table, new.name);
+    let (old_type, old_constraint) = old.type_and_constraint();
+    let (new_type, new_constraint) = new.type_and_constraint();
+    if old_type != new_type {
+        queries.push(format!("{} TYPE {} USING {}::{}", alter_column, new_type, new.name, new_type));
+    }
+    if old_constraint != new_constraint {
+        // NOTE: the foreign key constraint has the default name given by PostgreSQL.
+        let constraint_name = format!("{}_{}_fkey", table, new.name);
+        if old_constraint.starts_with("REFERENCES") {
+            queries.push(format!("ALTER TABLE {} DROP CONSTRAINT {}", table, constraint_name));
+        }
+        if new_constraint.starts_with("REFERENCES") {
+            queries.push(format!("ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) {}", table, constraint_name,
+                                 new.name, new_constraint));
+        }
+    }
+    if old.nullable != new.nullable {
+        let action = if new.nullable { "DROP" } else { "SET" };
+        if let Some(ref default) = new.default {
+            if !new.nullable {
+                queries.push(format!("UPDATE {} SET {} = {} WHERE {} IS NULL", table, new.name, default, new.name));
+            }
+        }
+        queries.push(format!("{} {} NOT NULL", alter_column, action));
+    }
+    if old.default != new.default {
+        match new.default {
+            Some(ref default) => queries.push(format!("{} SET DEFAULT {}", alter_column, default)),
+            None => queries.push(format!("{} DROP DEFAULT", alter_column)),
+        }
+    }
+    queries
+}
+
+/// Get the SQLite queries recreating the table with the change done by `step`, since SQLite
+/// cannot alter or drop a column.
+fn rebuild_table(step: &Step, schema: &Schema, table: &str) -> Vec<String> {
+    let old_table =
+        match schema.get(table) {
+            Some(table) => table,
+            None => return vec![],
+        };
+    let mut new_schema = schema.clone();
+    new_schema.apply(step);
+    let new_table = new_schema.get(table).expect("table after step");
+    let temporary_table = format!("tql_new_{}", table);
+    let mut columns = vec![];
+    let mut values = vec![];
+    for column in &new_table.columns {
+        if let Some(old_column) = old_table.column(&column.name) {
+            columns.push(column.name.clone());
+            // NOTE: the NULL values are replaced by the default value when the column becomes NOT NULL.
+            match column.default {
+                Some(ref default) if old_column.nullable && !column.nullable =>
+                    values.push(format!("COALESCE({}, {})", column.name, default)),
+                _ => values.push(column.name.clone()),
+            }
+        }
+    }
+    let mut queries = vec![
+        new_table.create_sql_with_name(&temporary_table),
+        format!("INSERT INTO {} ({}) SELECT {} FROM {}", temporary_table, columns.join(", "), values.join(", "),
+                table),
+        format!("DROP TABLE {}", table),
+        format!("ALTER TABLE {} RENAME TO {}", temporary_table, table),
+    ];
+    queries.extend(new_table.indexes.iter().map(|index| index.create_sql(table)));
+    queries
+}
+
+/// Convert the `steps` to SQL queries for the `backend`, where `schema` is the schema before the
+/// first step.
+pub fn steps_to_sql(steps: &[Step], schema: &Schema, backend: Backend) -> Vec<String> {
+    let mut schema = schema.clone();
+    let mut queries = vec![];
+    for step in steps {
+        queries.extend(step.to_sql(&schema, backend));
+        schema.apply(step);
+    }
+    queries
+}
+
+/// A migration: the queries to apply it and to revert it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Migration {
+    pub name: String,
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+}
+
+impl Migration {
+    /// Create the migration from the `old` schema to the `new` one.
+    pub fn new(name: &str, old: &Schema, new: &Schema, backend: Backend) -> Self {
+        Migration::with_renames(name, old, new, &[], backend)
+    }
+
+    /// Create the migration from the `old` schema to the `new` one, where the columns of the
+    /// `renames` are renamed instead of being dropped and created.
+    pub fn with_renames(name: &str, old: &Schema, new: &Schema, renames: &[Rename], backend: Backend) -> Self {
+        let reversed_renames: Vec<_> = renames.iter().map(Rename::reverse).collect();
+        Migration {
+            name: name.to_string(),
+            up: steps_to_sql(&old.diff_with_renames(new, renames), old, backend),
+            down: steps_to_sql(&new.diff_with_renames(old, &reversed_renames), new, backend),
+        }
+    }
+}
+
+/// A connection on which the migrations can be applied.
+pub trait Database {
+    /// Get the backend of this database.
+    fn backend(&self) -> Backend;
+
+    /// Execute the SQL `query`.
+    fn execute(&self, query: &str) -> Result<()>;
+
+    /// Execute the SQL `query` and get the text of the first column of each row.
+    fn query_strings(&self, query: &str) -> Result<Vec<String>>;
+
+    /// Execute the SQL `query` and get the text of the columns of each row (`None` for `NULL`).
+    fn query_rows(&self, query: &str) -> Result<Vec<Vec<Option<String>>>>;
+}
+
+#[cfg(feature = "postgres")]
+impl Database for ::postgres::Connection {
+    fn backend(&self) -> Backend {
+        Backend::Postgres
+    }
+
+    fn execute(&self, query: &str) -> Result<()> {
+        self.batch_execute(query)?;
+        Ok(())
+    }
+
+    fn query_strings(&self, query: &str) -> Result<Vec<String>> {
+        let rows = self.query(query, &[])?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    fn query_rows(&self, query: &str) -> Result<Vec<Vec<Option<String>>>> {
+        let rows = self.query(query, &[])?;
+        Ok(rows.iter().map(|row| (0..row.len()).map(|index| row.get(index)).collect()).collect())
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl Database for ::rusqlite::Connection {
+    fn backend(&self) -> Backend {
+        Backend::Sqlite
+    }
+
+    fn execute(&self, query: &str) -> Result<()> {
+        self.execute_batch(query)?;
+        Ok(())
+    }
+
+    fn query_strings(&self, query: &str) -> Result<Vec<String>> {
+        let mut statement = self.prepare(query)?;
+        let params: &[&::rusqlite::types::ToSql] = &[];
+        let rows = statement.query_map(params, |row| row.get(0))?;
+        let mut strings = vec![];
+        for row in rows {
+            strings.push(row?);
+        }
+        Ok(strings)
+    }
+
+    fn query_rows(&self, query: &str) -> Result<Vec<Vec<Option<String>>>> {
+        let mut statement = self.prepare(query)?;
+        let count = statement.column_count();
+        let params: &[&::rusqlite::types::ToSql] = &[];
+        let rows = statement.query_map(params, |row| (0..count).map(|index| row.get(index)).collect())?;
+        let mut values = vec![];
+        for row in rows {
+            values.push(row?);
+        }
+        Ok(values)
+    }
+}
+
+/// Get the tables of the current schema of a PostgreSQL `database`.
+fn postgres_tables<D: Database>(database: &D) -> Result<Vec<TableSchema>> {
+    // NOTE: the values are converted to text since the columns of information_schema have
+    // specific types.
+    let columns = database.query_rows(
+        "SELECT c.table_name::text, c.column_name::text, format_type(a.atttypid, a.atttypmod), \
+         c.is_nullable::text, c.column_default::text \
+         FROM information_schema.columns c \
+         JOIN information_schema.tables t ON t.table_schema = c.table_schema AND t.table_name = c.table_name \
+         JOIN pg_attribute a \
+             ON a.attrelid = (quote_ident(c.table_schema::text) || '.' || quote_ident(c.table_name::text))::regclass \
+             AND a.attname = c.column_name \
+         WHERE c.table_schema = current_schema() AND t.table_type = 'BASE TABLE' \
+         ORDER BY c.table_name, c.ordinal_position")?;
+    let constraints = database.query_rows(
+        "SELECT tc.table_name::text, kcu.column_name::text, tc.constraint_type::text, ccu.table_name::text, \
+         ccu.column_name::text \
+         FROM information_schema.table_constraints tc \
+         JOIN information_schema.key_column_usage kcu ON kcu.constraint_schema = tc.constraint_schema \
+             AND kcu.constraint_name = tc.constraint_name \
+         LEFT JOIN information_schema.constraint_column_usage ccu ON ccu.constraint_schema = tc.constraint_schema \
+             AND ccu.constraint_name = tc.constraint_name AND tc.constraint_type = 'FOREIGN KEY' \
+         WHERE tc.table_schema = current_schema() AND tc.constraint_type IN ('FOREIGN KEY', 'PRIMARY KEY')")?;
+    let indexes = database.query_rows(
+        "SELECT t.relname::text, i.relname::text, a.attname::text \
+         FROM pg_index x \
+         JOIN pg_class t ON t.oid = x.indrelid \
+         JOIN pg_class i ON i.oid = x.indexrelid \
+         JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = ANY(x.indkey) \
+         JOIN pg_namespace n ON n.oid = t.relnamespace \
+         WHERE n.nspname = current_schema() AND NOT x.indisprimary AND NOT x.indisunique \
+         ORDER BY t.relname, i.relname, array_position(x.indkey::int2[], a.attnum)")?;
+
+    let mut tables: Vec<TableSchema> = vec![];
+    for row in columns {
+        let table_name = text(&row, 0);
+        if table_name == HISTORY_TABLE {
+            continue;
+        }
+        let name = text(&row, 1);
+        let mut sql_type = postgres_type(&text(&row, 2));
+        let mut default = value(&row, 4);
+        for constraint in constraints.iter().filter(|constraint| text(constraint, 0) == table_name &&
+            text(constraint, 1) == name)
+        {
+            if text(constraint, 2) == "PRIMARY KEY" {
+                // NOTE: a serial column is an integer column whose default value is the next value of
+                // a sequence.
+                if default.as_ref().map_or(false, |default| default.starts_with("nextval(")) {
+                    default = None;
+                    sql_type =
+                        match sql_type.as_str() {
+                            "BIGINT" => "BIGSERIAL".to_string(),
+                            "SMALLINT" => "SMALLSERIAL".to_string(),
+                            _ => "SERIAL".to_string(),
+                        };
+                }
+                sql_type.push_str(" PRIMARY KEY");
+            }
+            else {
+                sql_type.push_str(&format!(" REFERENCES {}({})", text(constraint, 3), text(constraint, 4)));
+            }
+        }
+        let column = Column {
+            name,
+            sql_type,
+            nullable: text(&row, 3) == "YES",
+            default,
+        };
+        if tables.last().map(|table| &table.name) != Some(&table_name) {
+            tables.push(TableSchema {
+                name: table_name,
+                columns: vec![],
+                indexes: vec![],
+            });
+        }
+        tables.last_mut().expect("table").columns.push(column);
+    }
+
+    for row in indexes {
+        if let Some(table) = tables.iter_mut().find(|table| table.name == text(&row, 0)) {
+            let name = text(&row, 1);
+            let column = text(&row, 2);
+            if table.indexes.last().map(|index| &index.name) == Some(&name) {
+                table.indexes.last_mut().expect("index").columns.push(column);
+            }
+            else {
+                table.indexes.push(Index {
+                    name,
+                    columns: vec![column],
+                });
+            }
+        }
+    }
+    Ok(tables)
+}
+
+/// Convert a type given by the PostgreSQL function `format_type()` to the SQL type used in the
+/// `CREATE TABLE` queries.
+fn postgres_type(typ: &str) -> String {
+    typ.to_uppercase()
+        .replace("TIMESTAMP WITHOUT TIME ZONE", "TIMESTAMP")
+        .replace("TIME WITHOUT TIME ZONE", "TIME")
+}
+
+/// Get the tables of a SQLite `database`.
+fn sqlite_tables<D: Database>(database: &D) -> Result<Vec<TableSchema>> {
+    let mut tables = vec![];
+    let names = database.query_strings(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")?;
+    for table_name in names {
+        if table_name == HISTORY_TABLE {
+            continue;
+        }
+        let table = string_literal(&table_name);
+        // NOTE: the integers are converted to text to be read like the other values.
+        let columns = database.query_rows(&format!(
+            "SELECT name, type, CAST(\"notnull\" AS TEXT), dflt_value, CAST(pk AS TEXT) FROM pragma_table_info({}) \
+             ORDER BY cid", table))?;
+        let foreign_keys = database.query_rows(&format!(
+            "SELECT \"from\", \"table\", \"to\" FROM pragma_foreign_key_list({})", table))?;
+        let mut table_schema = TableSchema {
+            name: table_name.clone(),
+            columns: vec![],
+            indexes: vec![],
+        };
+        for row in columns {
+            let name = text(&row, 0);
+            let mut sql_type = text(&row, 1).to_uppercase();
+            if text(&row, 4) != "0" {
+                sql_type.push_str(" PRIMARY KEY");
+            }
+            if let Some(foreign_key) = foreign_keys.iter().find(|foreign_key| text(foreign_key, 0) == name) {
+                sql_type.push_str(&format!(" REFERENCES {}", text(foreign_key, 1)));
+                // NOTE: the referenced column is not specified when it is the primary key.
+                if let Some(column) = value(foreign_key, 2) {
+                    sql_type.push_str(&format!("({})", column));
+                }
+            }
+            table_schema.columns.push(Column {
+                name,
+                sql_type,
+                nullable: text(&row, 2) == "0",
+                default: value(&row, 3),
+            });
+        }
+        // NOTE: the indexes created for the constraints have no SQL.
+        let indexes = database.query_strings(&format!(
+            "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = {} AND sql IS NOT NULL ORDER BY name",
+            table))?;
+        for name in indexes {
+            let columns = database.query_strings(&format!(
+                "SELECT name FROM pragma_index_info({}) ORDER BY seqno", string_literal(&name)))?;
+            table_schema.indexes.push(Index {
+                name,
+                columns,
+            });
+        }
+        tables.push(table_schema);
+    }
+    Ok(tables)
+}
+
+/// Sort the `tables` so that the tables referenced by a foreign key come before the tables
+/// referencing them (the order is kept for the tables referencing each other).
+fn sort_by_references(mut tables: Vec<TableSchema>) -> Vec<TableSchema> {
+    let mut sorted: Vec<TableSchema> = vec![];
+    while !tables.is_empty() {
+        let position = tables.iter()
+            .position(|table| {
+                table.columns.iter()
+                    .filter_map(Column::referenced_table)
+                    .all(|referenced_table| referenced_table == table.name ||
+                         !tables.iter().any(|table| table.name == referenced_table))
+            })
+            .unwrap_or(0);
+        sorted.push(tables.remove(position));
+    }
+    sorted
+}
+
+/// Write the snapshot of the table `T` in the file `T.tql` of the directory in the
+/// `TQL_SNAPSHOT_DIR` environment variable (nothing is written when this variable is not set).
+///
+/// This is called by the test generated by `#[derive(SqlTable)]`, which is run by `tql
+/// makemigrations`.
+#[doc(hidden)]
+pub fn write_snapshot<T: SqlTable>() {
+    if let Some(directory) = env::var_os(SNAPSHOT_DIR_VARIABLE) {
+        let schema = Schema::new().table::<T>();
+        let path = Path::new(&directory).join(format!("{}.tql", schema.tables[0].name));
+        let mut file = File::create(&path)
+            .unwrap_or_else(|error| panic!("cannot create {}: {}", path.display(), error));
+        file.write_all(schema.to_snapshot().as_bytes())
+            .unwrap_or_else(|error| panic!("cannot write {}: {}", path.display(), error));
+    }
+}
+
+/// Get the text of the value at `index` in the `row` (the empty string for `NULL`).
+fn text(row: &[Option<String>], index: usize) -> String {
+    value(row, index).unwrap_or_default()
+}
+
+/// Get the text of the value at `index` in the `row`.
+fn value(row: &[Option<String>], index: usize) -> Option<String> {
+    row.get(index).and_then(Clone::clone)
+}
+
+/// A difference between a table of the models and the table of the database.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mismatch {
+    MissingTable {
+        table: String,
+    },
+    /// The column of a field is not in the table of the database.
+    MissingColumn {
+        table: String,
+        column: String,
+    },
+    /// The column of the table of the database has no field in the model.
+    MissingField {
+        table: String,
+        column: String,
+    },
+    /// The SQL type (or its `PRIMARY KEY` or `REFERENCES` constraint) of the column is not the
+    /// one of the field.
+    Type {
+        table: String,
+        column: String,
+        expected: String,
+        found: String,
+    },
+    /// The column is nullable and the field is not optional, or the reverse.
+    Nullability {
+        table: String,
+        column: String,
+        /// Whether the column of the database is nullable.
+        nullable: bool,
+    },
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            Mismatch::MissingTable { ref table } => write!(formatter, "Missing table {}", table),
+            Mismatch::MissingColumn { ref table, ref column } =>
+                write!(formatter, "Missing column {}.{}", table, column),
+            Mismatch::MissingField { ref table, ref column } =>
+                write!(formatter, "Missing field for the column {}.{}", table, column),
+            Mismatch::Type { ref table, ref column, ref expected, ref found } =>
+                write!(formatter, "Column {}.{} has type {}, expected {}", table, column, found, expected),
+            Mismatch::Nullability { ref table, ref column, nullable: true } =>
+                write!(formatter, "Column {}.{} is nullable, expected NOT NULL", table, column),
+            Mismatch::Nullability { ref table, ref column, nullable: false } =>
+                write!(formatter, "Column {}.{} is NOT NULL, expected nullable", table, column),
+        }
+    }
+}
+
+/// Compare the tables of the `models` to the tables of the `database`.
+///
+/// Only the tables added to the `models` schema (e.g. with `Schema::table()`) are compared: the
+/// other tables of the database are ignored.
+/// The names are compared case-insensitively since PostgreSQL converts the unquoted identifiers
+/// to lowercase, and the types read into the same Rust type (e.g. `TEXT` and `VARCHAR(255)`) are
+/// considered equal.
+pub fn check_all<D: Database>(database: &D, models: &Schema) -> Result<Vec<Mismatch>> {
+    let schema = Schema::from_database(database)?;
+    let mut mismatches = vec![];
+    for table in &models.tables {
+        match schema.tables.iter().find(|database_table| database_table.name.eq_ignore_ascii_case(&table.name)) {
+            Some(database_table) => check_table(table, database_table, &mut mismatches),
+            None => mismatches.push(Mismatch::MissingTable {
+                table: table.name.clone(),
+            }),
+        }
+    }
+    Ok(mismatches)
+}
+
+/// An alias of `check_all()`.
+pub use self::check_all as compare_schema;
+
+/// Compare the columns of the `table` of the models to the columns of the `database_table`.
+fn check_table(table: &TableSchema, database_table: &TableSchema, mismatches: &mut Vec<Mismatch>) {
+    for column in &table.columns {
+        let database_column =
+            match database_table.columns.iter().find(|other| other.name.eq_ignore_ascii_case(&column.name)) {
+                Some(database_column) => database_column,
+                None => {
+                    mismatches.push(Mismatch::MissingColumn {
+                        table: table.name.clone(),
+                        column: column.name.clone(),
+                    });
+                    continue;
+                },
+            };
+        let (typ, constraint) = column.type_and_constraint();
+        let (database_type, database_constraint) = database_column.type_and_constraint();
+        let same_constraint =
+            match column.referenced_table() {
+                Some(referenced_table) => database_column.referenced_table()
+                    .map_or(false, |other| other.eq_ignore_ascii_case(referenced_table)),
+                None => constraint.starts_with("PRIMARY KEY") == database_constraint.starts_with("PRIMARY KEY"),
+            };
+        if !same_constraint || normalize_type(typ) != normalize_type(database_type) {
+            mismatches.push(Mismatch::Type {
+                table: table.name.clone(),
+                column: column.name.clone(),
+                expected: column.sql_type.clone(),
+                found: database_column.sql_type.clone(),
+            });
+        }
+        // NOTE: the nullability of the primary key is not checked since SQLite allows NULL in an
+        // INTEGER PRIMARY KEY column (it is then given a value).
+        else if !constraint.starts_with("PRIMARY KEY") && column.nullable != database_column.nullable {
+            mismatches.push(Mismatch::Nullability {
+                table: table.name.clone(),
+                column: column.name.clone(),
+                nullable: database_column.nullable,
+            });
+        }
+    }
+    for database_column in &database_table.columns {
+        if !table.columns.iter().any(|column| column.name.eq_ignore_ascii_case(&database_column.name)) {
+            mismatches.push(Mismatch::MissingField {
+                table: table.name.clone(),
+                column: database_column.name.clone(),
+            });
+        }
+    }
+}
+
+
+/// Get the names of the migrations applied to the `database`, in the order they were applied.
+///
+/// The history table is created if it does not exist.
+pub fn applied_migrations<D: Database>(database: &D) -> Result<Vec<String>> {
+    database.execute(&format!("CREATE TABLE IF NOT EXISTS {} (id {}, name CHARACTER VARYING NOT NULL UNIQUE, \
+                              applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP)", HISTORY_TABLE,
+                              serial_primary_key(database.backend())))?;
+    database.query_strings(&format!("SELECT name FROM {} ORDER BY id", HISTORY_TABLE))
+}
+
+/// Apply the `migrations` that are not applied yet and get their names.
+///
+/// Each migration is applied in its own transaction.
+pub fn migrate<D: Database>(database: &D, migrations: &[Migration]) -> Result<Vec<String>> {
+    let applied = applied_migrations(database)?;
+    let mut newly_applied = vec![];
+    for migration in migrations {
+        if applied.contains(&migration.name) {
+            continue;
+        }
+        let record = format!("INSERT INTO {} (name) VALUES ({})", HISTORY_TABLE, string_literal(&migration.name));
+        execute_in_transaction(database, &migration.up, &record)?;
+        newly_applied.push(migration.name.clone());
+    }
+    Ok(newly_applied)
+}
+
+/// Revert the `migration`, which must be the last one applied.
+pub fn rollback<D: Database>(database: &D, migration: &Migration) -> Result<()> {
+    let applied = applied_migrations(database)?;
+    if applied.last() != Some(&migration.name) {
+        return Err(format!("cannot rollback {}: it is not the last applied migration", migration.name).into());
+    }
+    let record = format!("DELETE FROM {} WHERE name = {}", HISTORY_TABLE, string_literal(&migration.name));
+    execute_in_transaction(database, &migration.down, &record)
+}
+
+/// Execute the `queries` and the query updating the history in a transaction.
+fn execute_in_transaction<D: Database>(database: &D, queries: &[String], history_query: &str) -> Result<()> {
+    database.execute("BEGIN")?;
+    for query in queries.iter().map(String::as_str).chain(Some(history_query)) {
+        if let Err(error) = database.execute(query) {
+            let _ = database.execute("ROLLBACK");
+            return Err(error);
+        }
+    }
+    database.execute("COMMIT")
+}
+
+fn serial_primary_key(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Postgres => "SERIAL PRIMARY KEY",
+        Backend::Sqlite => "INTEGER PRIMARY KEY",
+    }
+}
+
+fn string_literal(string: &str) -> String {
+    format!("'{}'", string.replace('\'', "''"))
+}
@@ -0,0 +1,52 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Functions to register on a SQLite connection.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry::{Occupied, Vacant};
+
+use regex::Regex;
+use rusqlite::{Connection, Error, Result};
+
+/// Register the `REGEXP` function used by the `regex()` and `iregex()` methods on `connection`.
+///
+/// SQLite does not provide an implementation of the `REGEXP` operator, so this function must be
+/// called before executing a query using these methods.
+pub fn register_regexp(connection: &Connection) -> Result<()> {
+    let mut cached_regexes = HashMap::new();
+    // NOTE: `text REGEXP pattern` calls `regexp(pattern, text)`.
+    connection.create_scalar_function("regexp", 2, true, move |context| {
+        let pattern = context.get::<String>(0)?;
+        let regex =
+            match cached_regexes.entry(pattern.clone()) {
+                Occupied(entry) => entry.into_mut(),
+                Vacant(entry) => {
+                    match Regex::new(&pattern) {
+                        Ok(regex) => entry.insert(regex),
+                        Err(error) => return Err(Error::UserFunctionError(Box::new(error))),
+                    }
+                },
+            };
+        let text = context.get::<String>(1)?;
+        Ok(regex.is_match(&text))
+    })
+}
@@ -24,8 +24,15 @@
 
 #![allow(dead_code, non_camel_case_types)]
 
+use std::marker::PhantomData;
+
 #[cfg(feature = "chrono")]
 use chrono::{self, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+#[cfg(feature = "serde_json")]
+use serde_json;
+
+#[cfg(feature = "json")]
+use value::Json;
 
 type StdI8 = i8;
 
@@ -182,6 +189,29 @@ impl ToTqlType for NaiveTime {
     fn to_tql_type(&self) -> Self::Target { Time }
 }
 
+#[doc(hidden)]
+pub struct TqlArray<T>(PhantomData<T>);
+
+impl<T> ToTqlType for Vec<T> {
+    type Target = TqlArray<T>;
+    fn to_tql_type(&self) -> Self::Target { TqlArray(PhantomData) }
+}
+
+#[doc(hidden)]
+pub struct TqlJson;
+
+#[cfg(feature = "json")]
+impl<T> ToTqlType for Json<T> {
+    type Target = TqlJson;
+    fn to_tql_type(&self) -> Self::Target { TqlJson }
+}
+
+#[cfg(feature = "serde_json")]
+impl ToTqlType for serde_json::Value {
+    type Target = TqlJson;
+    fn to_tql_type(&self) -> Self::Target { TqlJson }
+}
+
 #[doc(hidden)]
 pub struct TqlOption;
 
@@ -0,0 +1,147 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Conversion of the column types that are not supported by the database libraries.
+
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "rusqlite")]
+mod sqlite;
+
+use std::error::Error;
+use std::marker::PhantomData;
+#[cfg(feature = "json")]
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+/// Wrapper used by the generated code to convert a value from and to a column whose type is not
+/// supported (or is not mapped to the right SQL type) by the database library, like the unsigned
+/// integers which are stored in a wider signed column.
+#[derive(Debug)]
+#[doc(hidden)]
+pub struct SqlValue<T>(pub T);
+
+/// A value serialized with `serde` and stored in a JSON column (`JSONB` on PostgreSQL and `TEXT` on
+/// SQLite).
+#[cfg(feature = "json")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "json")]
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T> DerefMut for Json<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// A user-defined type that can be used as the type of a field in a `#[derive(SqlTable)]` struct.
+///
+/// The values of this type are stored in a column of the `Underlying` type, so that a newtype like
+/// `struct Email(String)` is stored, filtered and type checked like a `String` column.
+pub trait SqlType: Sized {
+    /// The type stored in the database.
+    type Underlying: ColumnType;
+
+    /// Convert the value to the type stored in the database.
+    fn to_sql_value(&self) -> Self::Underlying;
+
+    /// Convert a value read from the database.
+    fn from_sql_value(value: Self::Underlying) -> Result<Self, Box<Error + Send + Sync>>;
+}
+
+/// A type supported by the database library that can be used as the underlying type of a
+/// `SqlType`.
+pub trait ColumnType {
+    /// The SQL type of the column.
+    const SQL_TYPE: &'static str;
+}
+
+/// A fieldless enum stored in a native PostgreSQL enum column (implemented by
+/// `#[derive(SqlEnum)]`).
+#[doc(hidden)]
+pub trait EnumType {
+    /// The name of the SQL type.
+    const NAME: &'static str;
+}
+
+/// The name of the variant of an enum stored in a native PostgreSQL enum column.
+#[derive(Debug)]
+#[doc(hidden)]
+pub struct EnumValue<T> {
+    pub name: String,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> EnumValue<T> {
+    pub fn new(name: &str) -> Self {
+        EnumValue {
+            name: name.to_string(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: EnumType> ColumnType for EnumValue<T> {
+    const SQL_TYPE: &'static str = T::NAME;
+}
+
+macro_rules! column_type {
+    ($($(#[$attribute:meta])* $typ:ty => $sql_type:expr),*) => {$(
+        $(#[$attribute])*
+        impl ColumnType for $typ {
+            const SQL_TYPE: &'static str = $sql_type;
+        }
+    )*};
+}
+
+// NOTE: these SQL types must be kept in sync with the ones generated by tql_macros.
+column_type!(
+    bool => "BOOLEAN",
+    f32 => "REAL",
+    f64 => "DOUBLE PRECISION",
+    i8 => "CHARACTER(1)",
+    i16 => "SMALLINT",
+    i32 => "INTEGER",
+    i64 => "BIGINT",
+    String => "CHARACTER VARYING",
+    Vec<u8> => "BYTEA",
+    #[cfg(feature = "chrono")]
+    DateTime<Local> => "TIMESTAMP WITH TIME ZONE",
+    #[cfg(feature = "chrono")]
+    DateTime<Utc> => "TIMESTAMP WITH TIME ZONE",
+    #[cfg(feature = "chrono")]
+    NaiveDate => "DATE",
+    #[cfg(feature = "chrono")]
+    NaiveDateTime => "TIMESTAMP",
+    #[cfg(feature = "chrono")]
+    NaiveTime => "TIME"
+);
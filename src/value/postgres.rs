@@ -0,0 +1,486 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::Debug;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+#[cfg(any(feature = "bigdecimal", feature = "rust_decimal"))]
+use std::str::FromStr;
+
+#[cfg(feature = "bigdecimal")]
+use bigdecimal::BigDecimal;
+#[cfg(feature = "serde_json")]
+use postgres::types::{JSON, JSONB};
+#[cfg(any(feature = "bigdecimal", feature = "rust_decimal"))]
+use postgres::types::NUMERIC;
+use postgres::types::{CIDR, FromSql, INET, IsNull, Kind, ToSql, Type, __to_sql_checked};
+#[cfg(feature = "uuid")]
+use postgres::types::UUID;
+#[cfg(feature = "rust_decimal")]
+use rust_decimal::Decimal;
+#[cfg(feature = "json")]
+use serde::Serialize;
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde_json")]
+use serde_json::{self, Value};
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+#[cfg(feature = "json")]
+use super::Json;
+use super::{EnumType, EnumValue, SqlType, SqlValue};
+
+type BoxError = Box<Error + Sync + Send>;
+
+/// The version of the JSONB binary format.
+#[cfg(feature = "serde_json")]
+const JSONB_VERSION: u8 = 1;
+
+/// The address families of the INET binary format.
+const PGSQL_AF_INET: u8 = 2;
+const PGSQL_AF_INET6: u8 = 3;
+
+/// The signs of the NUMERIC binary format.
+#[cfg(any(feature = "bigdecimal", feature = "rust_decimal"))]
+const NUMERIC_NEGATIVE: u16 = 0x4000;
+#[cfg(any(feature = "bigdecimal", feature = "rust_decimal"))]
+const NUMERIC_NAN: u16 = 0xC000;
+
+macro_rules! to_sql_checked {
+    () => {
+        fn to_sql_checked(&self, ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+            __to_sql_checked(self, ty, out)
+        }
+    };
+}
+
+// The unsigned integers are stored in the smallest signed column that can hold all their values,
+// except for u64 which is stored in a BIGINT: the conversion fails for the values that do not fit.
+macro_rules! unsigned_integer {
+    ($($typ:ty => $column_type:ty),*) => {$(
+        impl<'a> ToSql for SqlValue<&'a $typ> {
+            fn to_sql(&self, ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+                <$column_type>::try_from(*self.0)?.to_sql(ty, out)
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                <$column_type as ToSql>::accepts(ty)
+            }
+
+            to_sql_checked!();
+        }
+
+        impl FromSql for SqlValue<$typ> {
+            fn from_sql(ty: &Type, raw: &[u8]) -> Result<Self, BoxError> {
+                let value = <$column_type as FromSql>::from_sql(ty, raw)?;
+                Ok(SqlValue(<$typ>::try_from(value)?))
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                <$column_type as FromSql>::accepts(ty)
+            }
+        }
+    )*};
+}
+
+unsigned_integer!(u8 => i16, u16 => i32, u32 => i64, u64 => i64);
+
+impl<'a, T: Debug> ToSql for SqlValue<&'a Option<T>>
+where for<'b> SqlValue<&'b T>: ToSql,
+{
+    fn to_sql(&self, ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+        match *self.0 {
+            Some(ref value) => SqlValue(value).to_sql(ty, out),
+            None => Ok(IsNull::Yes),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <SqlValue<&'a T> as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl<T> FromSql for SqlValue<Option<T>>
+where SqlValue<T>: FromSql,
+{
+    fn from_sql(ty: &Type, raw: &[u8]) -> Result<Self, BoxError> {
+        let SqlValue(value) = SqlValue::<T>::from_sql(ty, raw)?;
+        Ok(SqlValue(Some(value)))
+    }
+
+    fn from_sql_null(_ty: &Type) -> Result<Self, BoxError> {
+        Ok(SqlValue(None))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <SqlValue<T> as FromSql>::accepts(ty)
+    }
+}
+
+impl<'a, T: Debug + SqlType> ToSql for SqlValue<&'a T>
+where T::Underlying: ToSql,
+{
+    fn to_sql(&self, ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+        self.0.to_sql_value().to_sql(ty, out)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <T::Underlying as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl<T: SqlType> FromSql for SqlValue<T>
+where T::Underlying: FromSql,
+{
+    fn from_sql(ty: &Type, raw: &[u8]) -> Result<Self, BoxError> {
+        let value = <T::Underlying as FromSql>::from_sql(ty, raw)?;
+        Ok(SqlValue(T::from_sql_value(value)?))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <T::Underlying as FromSql>::accepts(ty)
+    }
+}
+
+impl<T: Debug + EnumType> ToSql for EnumValue<T> {
+    fn to_sql(&self, _ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+        out.extend_from_slice(self.name.as_bytes());
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        is_enum_type::<T>(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl<T: EnumType> FromSql for EnumValue<T> {
+    fn from_sql(_ty: &Type, raw: &[u8]) -> Result<Self, BoxError> {
+        Ok(EnumValue::new(::std::str::from_utf8(raw)?))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        is_enum_type::<T>(ty)
+    }
+}
+
+/// Check that `ty` is the enum type created for `T`.
+fn is_enum_type<T: EnumType>(ty: &Type) -> bool {
+    match *ty.kind() {
+        // NOTE: the type name is not quoted when the type is created, so PostgreSQL converts it to
+        // lowercase.
+        Kind::Enum(_) => ty.name().eq_ignore_ascii_case(T::NAME),
+        _ => false,
+    }
+}
+
+impl<'a> ToSql for SqlValue<&'a IpAddr> {
+    fn to_sql(&self, _ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+        // The header contains the family, the netmask bits, the CIDR flag and the address size.
+        match *self.0 {
+            IpAddr::V4(ref address) => {
+                out.extend_from_slice(&[PGSQL_AF_INET, 32, 0, 4]);
+                out.extend_from_slice(&address.octets());
+            },
+            IpAddr::V6(ref address) => {
+                out.extend_from_slice(&[PGSQL_AF_INET6, 128, 0, 16]);
+                out.extend_from_slice(&address.octets());
+            },
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == INET
+    }
+
+    to_sql_checked!();
+}
+
+impl FromSql for SqlValue<IpAddr> {
+    fn from_sql(_ty: &Type, raw: &[u8]) -> Result<Self, BoxError> {
+        if raw.len() < 4 || raw.len() != 4 + raw[3] as usize {
+            return Err("invalid INET value".into());
+        }
+        let address = &raw[4..];
+        match raw[0] {
+            PGSQL_AF_INET if address.len() == 4 =>
+                Ok(SqlValue(IpAddr::V4(Ipv4Addr::new(address[0], address[1], address[2], address[3])))),
+            PGSQL_AF_INET6 if address.len() == 16 => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(address);
+                Ok(SqlValue(IpAddr::V6(Ipv6Addr::from(octets))))
+            },
+            _ => Err("invalid INET value".into()),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == INET || *ty == CIDR
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl<'a> ToSql for SqlValue<&'a Value> {
+    fn to_sql(&self, ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+        if *ty == JSONB {
+            out.push(JSONB_VERSION);
+        }
+        serde_json::to_writer(&mut *out, self.0)?;
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == JSON || *ty == JSONB
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(feature = "serde_json")]
+impl FromSql for SqlValue<Value> {
+    fn from_sql(ty: &Type, raw: &[u8]) -> Result<Self, BoxError> {
+        Ok(SqlValue(serde_json::from_slice(json_text(ty, raw)?)?))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == JSON || *ty == JSONB
+    }
+}
+
+#[cfg(feature = "json")]
+impl<'a, T: Debug + Serialize> ToSql for SqlValue<&'a Json<T>> {
+    fn to_sql(&self, ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+        if *ty == JSONB {
+            out.push(JSONB_VERSION);
+        }
+        serde_json::to_writer(&mut *out, &(self.0).0)?;
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == JSON || *ty == JSONB
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(feature = "json")]
+impl<T: DeserializeOwned> FromSql for SqlValue<Json<T>> {
+    fn from_sql(ty: &Type, raw: &[u8]) -> Result<Self, BoxError> {
+        Ok(SqlValue(Json(serde_json::from_slice(json_text(ty, raw)?)?)))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == JSON || *ty == JSONB
+    }
+}
+
+/// Get the JSON text of a JSON or JSONB value, without the version of the JSONB binary format.
+#[cfg(feature = "serde_json")]
+fn json_text<'a>(ty: &Type, raw: &'a [u8]) -> Result<&'a [u8], BoxError> {
+    if *ty == JSONB {
+        match raw.split_first() {
+            Some((&JSONB_VERSION, json)) => Ok(json),
+            _ => Err("unsupported JSONB encoding version".into()),
+        }
+    }
+    else {
+        Ok(raw)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl<'a> ToSql for SqlValue<&'a Uuid> {
+    fn to_sql(&self, _ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+        out.extend_from_slice(self.0.as_bytes());
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == UUID
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(feature = "uuid")]
+impl FromSql for SqlValue<Uuid> {
+    fn from_sql(_ty: &Type, raw: &[u8]) -> Result<Self, BoxError> {
+        let uuid = Uuid::from_bytes(raw)
+            .map_err(|error| error.to_string())?;
+        Ok(SqlValue(uuid))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == UUID
+    }
+}
+
+// The decimal types are converted through their string representation.
+macro_rules! decimal {
+    ($($(#[$attribute:meta])* $typ:ty),*) => {$(
+        $(#[$attribute])*
+        impl<'a> ToSql for SqlValue<&'a $typ> {
+            fn to_sql(&self, _ty: &Type, out: &mut Vec<u8>) -> Result<IsNull, BoxError> {
+                numeric_to_sql(&self.0.to_string(), out)?;
+                Ok(IsNull::No)
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                *ty == NUMERIC
+            }
+
+            to_sql_checked!();
+        }
+
+        $(#[$attribute])*
+        impl FromSql for SqlValue<$typ> {
+            fn from_sql(_ty: &Type, raw: &[u8]) -> Result<Self, BoxError> {
+                let number = <$typ>::from_str(&numeric_from_sql(raw)?)
+                    .map_err(|error| error.to_string())?;
+                Ok(SqlValue(number))
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                *ty == NUMERIC
+            }
+        }
+    )*};
+}
+
+decimal!(
+    #[cfg(feature = "bigdecimal")]
+    BigDecimal,
+    #[cfg(feature = "rust_decimal")]
+    Decimal
+);
+
+/// Write the decimal `number` in the NUMERIC binary format: a header followed by the digits in base
+/// 10000.
+#[cfg(any(feature = "bigdecimal", feature = "rust_decimal"))]
+fn numeric_to_sql(number: &str, out: &mut Vec<u8>) -> Result<(), BoxError> {
+    let (negative, absolute_value) =
+        if number.starts_with('-') {
+            (true, &number[1..])
+        }
+        else {
+            (false, number)
+        };
+    let mut parts = absolute_value.splitn(2, '.');
+    let integer = parts.next().unwrap_or("").trim_start_matches('0');
+    let fraction = parts.next().unwrap_or("");
+    if !integer.bytes().chain(fraction.bytes()).all(|byte| byte.is_ascii_digit()) {
+        return Err(format!("cannot convert {} to NUMERIC", number).into());
+    }
+
+    // Align the groups of 4 digits on the decimal point.
+    let integer_padding = (4 - integer.len() % 4) % 4;
+    let fraction_padding = (4 - fraction.len() % 4) % 4;
+    let digits = "0".repeat(integer_padding) + integer + fraction + &"0".repeat(fraction_padding);
+    let mut groups: Vec<i16> = digits.as_bytes().chunks(4)
+        .map(|chunk| chunk.iter().fold(0, |group, digit| group * 10 + (digit - b'0') as i16))
+        .collect();
+    let mut weight = ((integer_padding + integer.len()) / 4) as i16 - 1;
+    while groups.first() == Some(&0) {
+        groups.remove(0);
+        weight -= 1;
+    }
+    while groups.last() == Some(&0) {
+        groups.pop();
+    }
+    if groups.is_empty() {
+        weight = 0;
+    }
+    let sign =
+        if negative && !groups.is_empty() {
+            NUMERIC_NEGATIVE
+        }
+        else {
+            0
+        };
+
+    out.extend_from_slice(&(groups.len() as i16).to_be_bytes());
+    out.extend_from_slice(&weight.to_be_bytes());
+    out.extend_from_slice(&sign.to_be_bytes());
+    out.extend_from_slice(&(fraction.len() as u16).to_be_bytes());
+    for group in groups {
+        out.extend_from_slice(&group.to_be_bytes());
+    }
+    Ok(())
+}
+
+/// Get the string representation of a number in the NUMERIC binary format.
+#[cfg(any(feature = "bigdecimal", feature = "rust_decimal"))]
+fn numeric_from_sql(raw: &[u8]) -> Result<String, BoxError> {
+    let read = |index: usize| ((raw[index] as u16) << 8) | raw[index + 1] as u16;
+    if raw.len() < 8 {
+        return Err("invalid NUMERIC value".into());
+    }
+    let group_count = read(0) as usize;
+    let weight = read(2) as i16 as isize;
+    let sign = read(4);
+    let scale = read(6) as usize;
+    if sign == NUMERIC_NAN {
+        return Err("cannot convert NaN from NUMERIC".into());
+    }
+    if raw.len() != 8 + group_count * 2 {
+        return Err("invalid NUMERIC value".into());
+    }
+    let group = |index: isize|
+        if index >= 0 && (index as usize) < group_count {
+            read(8 + index as usize * 2)
+        }
+        else {
+            0
+        };
+
+    let mut number = String::new();
+    if sign == NUMERIC_NEGATIVE {
+        number.push('-');
+    }
+    if weight < 0 {
+        number.push('0');
+    }
+    else {
+        number += &group(0).to_string();
+        for index in 1..weight + 1 {
+            number += &format!("{:04}", group(index));
+        }
+    }
+    if scale > 0 {
+        let mut fraction = String::new();
+        for index in 1..(scale as isize + 3) / 4 + 1 {
+            fraction += &format!("{:04}", group(weight + index));
+        }
+        fraction.truncate(scale);
+        number.push('.');
+        number += &fraction;
+    }
+    Ok(number)
+}
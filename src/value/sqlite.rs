@@ -0,0 +1,196 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+#[cfg(feature = "bigdecimal")]
+use bigdecimal::BigDecimal;
+use rusqlite::{Error, Result};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+#[cfg(feature = "rust_decimal")]
+use rust_decimal::Decimal;
+#[cfg(feature = "json")]
+use serde::Serialize;
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde_json")]
+use serde_json;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+#[cfg(feature = "json")]
+use super::Json;
+use super::{SqlType, SqlValue};
+
+/// Conversion of a value to its SQLite representation.
+trait ToSqlite {
+    fn to_sqlite(&self) -> Result<ToSqlOutput>;
+}
+
+/// Conversion of a value from its SQLite representation.
+trait FromSqlite: Sized {
+    fn from_sqlite(value: ValueRef) -> FromSqlResult<Self>;
+}
+
+impl<'a, T: ToSqlite> ToSql for SqlValue<&'a T> {
+    fn to_sql(&self) -> Result<ToSqlOutput> {
+        self.0.to_sqlite()
+    }
+}
+
+impl<'a, T: ToSqlite> ToSql for SqlValue<&'a Option<T>> {
+    fn to_sql(&self) -> Result<ToSqlOutput> {
+        match *self.0 {
+            Some(ref value) => value.to_sqlite(),
+            None => Ok(ToSqlOutput::Owned(Value::Null)),
+        }
+    }
+}
+
+impl<T: FromSqlite> FromSql for SqlValue<T> {
+    fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+        T::from_sqlite(value).map(SqlValue)
+    }
+}
+
+impl<T: FromSqlite> FromSql for SqlValue<Option<T>> {
+    fn column_result(value: ValueRef) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Null => Ok(SqlValue(None)),
+            _ => T::from_sqlite(value).map(|value| SqlValue(Some(value))),
+        }
+    }
+}
+
+impl<T: SqlType> ToSqlite for T
+where T::Underlying: ToSql,
+{
+    fn to_sqlite(&self) -> Result<ToSqlOutput> {
+        // The converted value is a temporary, so it cannot be borrowed by the output.
+        let value =
+            match self.to_sql_value().to_sql()? {
+                ToSqlOutput::Borrowed(value) => Value::from(value),
+                ToSqlOutput::Owned(value) => value,
+                #[allow(unreachable_patterns)]
+                _ => return Err(Error::ToSqlConversionFailure("unsupported SQL value".into())),
+            };
+        Ok(ToSqlOutput::Owned(value))
+    }
+}
+
+impl<T: SqlType> FromSqlite for T
+where T::Underlying: FromSql,
+{
+    fn from_sqlite(value: ValueRef) -> FromSqlResult<Self> {
+        let value = T::Underlying::column_result(value)?;
+        T::from_sql_value(value)
+            .map_err(FromSqlError::Other)
+    }
+}
+
+// The unsigned integers are stored as 64-bit signed integers: the conversion of u64 fails for the
+// values that do not fit.
+macro_rules! unsigned_integer {
+    ($($typ:ty),*) => {$(
+        impl ToSqlite for $typ {
+            fn to_sqlite(&self) -> Result<ToSqlOutput> {
+                let value = i64::try_from(*self)
+                    .map_err(|error| Error::ToSqlConversionFailure(Box::new(error)))?;
+                Ok(ToSqlOutput::Owned(Value::Integer(value)))
+            }
+        }
+
+        impl FromSqlite for $typ {
+            fn from_sqlite(value: ValueRef) -> FromSqlResult<Self> {
+                let value = value.as_i64()?;
+                <$typ>::try_from(value)
+                    .map_err(|_| FromSqlError::OutOfRange(value))
+            }
+        }
+    )*};
+}
+
+unsigned_integer!(u8, u16, u32, u64);
+
+#[cfg(feature = "uuid")]
+impl ToSqlite for Uuid {
+    fn to_sqlite(&self) -> Result<ToSqlOutput> {
+        Ok(ToSqlOutput::Borrowed(ValueRef::Blob(self.as_bytes())))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl FromSqlite for Uuid {
+    fn from_sqlite(value: ValueRef) -> FromSqlResult<Self> {
+        Uuid::from_bytes(value.as_blob()?)
+            .map_err(|error| FromSqlError::Other(error.to_string().into()))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: Serialize> ToSqlite for Json<T> {
+    fn to_sqlite(&self) -> Result<ToSqlOutput> {
+        serde_json::to_string(&self.0)
+            .map(|json| ToSqlOutput::Owned(Value::Text(json)))
+            .map_err(|error| Error::ToSqlConversionFailure(Box::new(error)))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: DeserializeOwned> FromSqlite for Json<T> {
+    fn from_sqlite(value: ValueRef) -> FromSqlResult<Self> {
+        serde_json::from_str(value.as_str()?)
+            .map(Json)
+            .map_err(|error| FromSqlError::Other(Box::new(error)))
+    }
+}
+
+// The other types are stored as text.
+macro_rules! text {
+    ($($(#[$attribute:meta])* $typ:ty),*) => {$(
+        $(#[$attribute])*
+        impl ToSqlite for $typ {
+            fn to_sqlite(&self) -> Result<ToSqlOutput> {
+                Ok(ToSqlOutput::Owned(Value::Text(self.to_string())))
+            }
+        }
+
+        $(#[$attribute])*
+        impl FromSqlite for $typ {
+            fn from_sqlite(value: ValueRef) -> FromSqlResult<Self> {
+                <$typ>::from_str(value.as_str()?)
+                    .map_err(|error| FromSqlError::Other(error.to_string().into()))
+            }
+        }
+    )*};
+}
+
+text!(
+    #[cfg(feature = "bigdecimal")]
+    BigDecimal,
+    #[cfg(feature = "rust_decimal")]
+    Decimal,
+    #[cfg(feature = "serde_json")]
+    serde_json::Value,
+    IpAddr
+);
@@ -0,0 +1,82 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+#![feature(proc_macro_hygiene)]
+
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+#[macro_use]
+mod connection;
+mod teardown;
+
+backend_extern_crate!();
+
+use tql::PrimaryKey;
+use tql_macros::sql;
+
+use connection::get_connection;
+use teardown::TearDown;
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct OldTableAlterExpr {
+    primary_key: PrimaryKey,
+    field1: String,
+    old_field: i32,
+    obsolete: i32,
+}
+
+#[derive(SqlTable)]
+struct TableAlterExpr {
+    primary_key: PrimaryKey,
+    field1: String,
+    field2: Option<i32>,
+    new_field: i32,
+}
+
+#[test]
+fn test_alter() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(OldTableAlterExpr.drop());
+        let _ = sql!(TableAlterExpr.drop());
+    });
+
+    let _ = sql!(OldTableAlterExpr.create());
+
+    let id = sql!(OldTableAlterExpr.insert(field1 = "value1", old_field = 42, obsolete = 0)).unwrap();
+
+    assert!(sql!(OldTableAlterExpr.rename_to(TableAlterExpr)).is_ok());
+    assert!(sql!(TableAlterExpr.rename_column(old_field, new_field)).is_ok());
+    assert!(sql!(TableAlterExpr.add_column(field2)).is_ok());
+    assert!(sql!(TableAlterExpr.drop_column(obsolete)).is_ok());
+
+    let table = sql!(TableAlterExpr.get(id)).unwrap();
+    assert_eq!(id, table.primary_key);
+    assert_eq!("value1", table.field1);
+    assert_eq!(None, table.field2);
+    assert_eq!(42, table.new_field);
+
+    assert!(sql!(TableAlterExpr.add_column(field2)).is_err());
+}
@@ -100,6 +100,16 @@ struct OtherTypes {
     int64: i64,
 }
 
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct IndexedTable {
+    pk: PrimaryKey,
+    #[sql(index)]
+    field1: String,
+    #[sql(index)]
+    field2: Option<i32>,
+}
+
 #[test]
 fn test_create() {
     let connection = get_connection();
@@ -124,3 +134,20 @@ fn test_create() {
     assert!(sql!(SqlTable.drop()).is_ok());
     assert!(sql!(RelatedTable.drop()).is_ok());
 }
+
+#[test]
+fn test_create_if_not_exists() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(IndexedTable.drop());
+    });
+
+    assert!(sql!(IndexedTable.create_if_not_exists()).is_ok());
+    assert!(sql!(IndexedTable.create()).is_err());
+    assert!(sql!(IndexedTable.create_if_not_exists()).is_ok());
+
+    assert!(sql!(IndexedTable.insert(field1 = "value1", field2 = Some(42))).is_ok());
+
+    assert!(sql!(IndexedTable.drop()).is_ok());
+}
@@ -21,6 +21,7 @@
 
 #![feature(proc_macro_hygiene)]
 
+extern crate chrono;
 extern crate tql;
 #[macro_use]
 extern crate tql_macros;
@@ -31,7 +32,9 @@ mod teardown;
 
 backend_extern_crate!();
 
-use tql::PrimaryKey;
+use chrono::{DateTime, Duration};
+use chrono::offset::Utc;
+use tql::{ForeignKey, PrimaryKey};
 use tql_macros::sql;
 
 use connection::{get_connection, is_not_found};
@@ -45,6 +48,31 @@ struct TableDeleteExpr {
     field2: i32,
 }
 
+#[derive(SqlTable)]
+#[sql(soft_delete)]
+struct SoftDeleteExpr {
+    id: PrimaryKey,
+    field1: String,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+#[sql(soft_delete)]
+struct SoftDeleteRelatedExpr {
+    id: PrimaryKey,
+    field1: String,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct SoftDeleteJoinExpr {
+    id: PrimaryKey,
+    field1: String,
+    related_field: ForeignKey<SoftDeleteRelatedExpr>,
+}
+
 #[test]
 fn test_delete() {
     let connection = get_connection();
@@ -111,3 +139,81 @@ fn test_delete() {
     let table = sql!(TableDeleteExpr.get(id));
     assert!(is_not_found(table));
 }
+
+#[test]
+fn test_soft_delete() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(SoftDeleteExpr.drop());
+    });
+
+    let _ = sql!(SoftDeleteExpr.create());
+
+    let before = Utc::now() - Duration::minutes(1);
+    let id1 = sql!(SoftDeleteExpr.insert(field1 = "first")).unwrap();
+    let id2 = sql!(SoftDeleteExpr.insert(field1 = "second")).unwrap();
+
+    let num_deleted = sql!(SoftDeleteExpr.get(id1).delete()).unwrap();
+    assert_eq!(1, num_deleted);
+
+    let table = sql!(SoftDeleteExpr.get(id1));
+    assert!(is_not_found(table));
+
+    let tables = sql!(SoftDeleteExpr.all()).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id2, tables[0].id);
+
+    let tables = sql!(SoftDeleteExpr.with_deleted().sort(id)).unwrap();
+    assert_eq!(2, tables.len());
+    assert!(tables[0].deleted_at.map_or(false, |deleted_at| deleted_at > before && deleted_at < Utc::now()));
+    assert!(tables[1].deleted_at.is_none());
+
+    // Deleting a row twice keeps its deletion date.
+    let num_deleted = sql!(SoftDeleteExpr.get(id1).delete()).unwrap();
+    assert_eq!(0, num_deleted);
+
+    // The deleted rows are only updated with with_deleted().
+    let num_updated = sql!(SoftDeleteExpr.get(id1).update(field1 = "updated")).unwrap();
+    assert_eq!(0, num_updated);
+
+    let num_updated = sql!(SoftDeleteExpr.with_deleted().get(id1).update(field1 = "updated")).unwrap();
+    assert_eq!(1, num_updated);
+
+    let num_deleted = sql!(SoftDeleteExpr.get(id1).hard_delete()).unwrap();
+    assert_eq!(1, num_deleted);
+
+    let tables = sql!(SoftDeleteExpr.with_deleted()).unwrap();
+    assert_eq!(1, tables.len());
+}
+
+#[test]
+fn test_soft_delete_join() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(SoftDeleteJoinExpr.drop());
+        let _ = sql!(SoftDeleteRelatedExpr.drop());
+    });
+
+    let _ = sql!(SoftDeleteRelatedExpr.create());
+    let _ = sql!(SoftDeleteJoinExpr.create());
+
+    let id1 = sql!(SoftDeleteRelatedExpr.insert(field1 = "first")).unwrap();
+    let id2 = sql!(SoftDeleteRelatedExpr.insert(field1 = "second")).unwrap();
+    let related1 = sql!(SoftDeleteRelatedExpr.get(id1)).unwrap();
+    let related2 = sql!(SoftDeleteRelatedExpr.get(id2)).unwrap();
+    sql!(SoftDeleteJoinExpr.insert(field1 = "first", related_field = related1)).unwrap();
+    let join_id2 = sql!(SoftDeleteJoinExpr.insert(field1 = "second", related_field = related2)).unwrap();
+
+    sql!(SoftDeleteRelatedExpr.get(id1).delete()).unwrap();
+
+    // The rows related to a deleted row are excluded from the joins.
+    let tables = sql!(SoftDeleteJoinExpr.all().join(related_field)).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(join_id2, tables[0].id);
+    assert_eq!("second", tables[0].related_field.as_ref().unwrap().field1);
+
+    let tables = sql!(SoftDeleteJoinExpr.all()).unwrap();
+    assert_eq!(2, tables.len());
+}
@@ -47,6 +47,22 @@ struct TableDropExpr {
     field2: i32,
 }
 
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct TableDropIfExistsExpr {
+    primary_key: PrimaryKey,
+    field1: String,
+}
+
+// NOTE: SQLite does not support drop_cascade().
+#[cfg(feature = "postgres")]
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct TableDropCascadeExpr {
+    primary_key: PrimaryKey,
+    field1: String,
+}
+
 #[test]
 fn test_drop() {
     let connection = get_connection();
@@ -73,3 +89,33 @@ fn test_drop() {
         Ok(_) => assert!(false),
     }
 }
+
+#[test]
+fn test_drop_if_exists() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(TableDropIfExistsExpr.drop());
+    });
+
+    let _ = sql!(TableDropIfExistsExpr.create());
+
+    assert!(sql!(TableDropIfExistsExpr.drop_if_exists()).is_ok());
+    assert!(sql!(TableDropIfExistsExpr.drop()).is_err());
+    assert!(sql!(TableDropIfExistsExpr.drop_if_exists()).is_ok());
+}
+
+#[cfg(feature = "postgres")]
+#[test]
+fn test_drop_cascade() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(TableDropCascadeExpr.drop());
+    });
+
+    let _ = sql!(TableDropCascadeExpr.create());
+
+    assert!(sql!(TableDropCascadeExpr.drop_cascade()).is_ok());
+    assert!(sql!(TableDropCascadeExpr.drop_cascade()).is_err());
+}
@@ -25,9 +25,12 @@ extern crate tql;
 #[macro_use]
 extern crate tql_macros;
 
+use std::error::Error;
+use std::net::IpAddr;
+
 #[cfg(feature = "postgres")]
 use postgres::error::UNDEFINED_TABLE;
-use tql::{ForeignKey, PrimaryKey};
+use tql::{ForeignKey, PrimaryKey, SqlType};
 use tql_macros::sql;
 
 #[macro_use]
@@ -78,6 +81,78 @@ struct RelatedTableInsertExpr {
     field1: i32,
 }
 
+#[derive(SqlTable)]
+struct TypesInsertExpr {
+    primary_key: PrimaryKey,
+    uint8: u8,
+    uint32: u32,
+    uint64: Option<u64>,
+    address: IpAddr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Email(String);
+
+impl SqlType for Email {
+    type Underlying = String;
+
+    fn to_sql_value(&self) -> String {
+        self.0.clone()
+    }
+
+    fn from_sql_value(value: String) -> Result<Self, Box<Error + Send + Sync>> {
+        if value.contains('@') {
+            Ok(Email(value))
+        }
+        else {
+            Err(format!("invalid email {}", value).into())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cents(i64);
+
+impl SqlType for Cents {
+    type Underlying = i64;
+
+    fn to_sql_value(&self) -> i64 {
+        self.0
+    }
+
+    fn from_sql_value(value: i64) -> Result<Self, Box<Error + Send + Sync>> {
+        Ok(Cents(value))
+    }
+}
+
+#[derive(SqlTable)]
+struct UserTypesInsertExpr {
+    primary_key: PrimaryKey,
+    email: Email,
+    balance: Cents,
+    credit: Option<Cents>,
+}
+
+#[derive(Clone, Debug, PartialEq, SqlEnum)]
+enum Status {
+    Todo,
+    Done,
+}
+
+#[derive(Clone, Debug, PartialEq, SqlEnum)]
+#[sql(storage = "integer")]
+enum Priority {
+    Low = 1,
+    High = 10,
+}
+
+#[derive(SqlTable)]
+struct EnumsInsertExpr {
+    primary_key: PrimaryKey,
+    status: Status,
+    priority: Option<Priority>,
+}
+
 #[test]
 fn test_insert() {
     let connection = get_connection();
@@ -186,3 +261,89 @@ fn test_insert() {
     )).unwrap();
     assert_eq!(4, id);
 }
+
+#[test]
+fn test_insert_extended_types() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(TypesInsertExpr.drop());
+    });
+
+    let _ = sql!(TypesInsertExpr.create());
+
+    let uint32 = u32::max_value();
+    let address: IpAddr = "::1".parse().unwrap();
+    let id = sql!(TypesInsertExpr.insert(uint8 = 255, uint32 = uint32, uint64 = Some(1 << 40), address = address))
+        .unwrap();
+
+    let table = sql!(TypesInsertExpr.get(id)).unwrap();
+    assert_eq!(255, table.uint8);
+    assert_eq!(u32::max_value(), table.uint32);
+    assert_eq!(Some(1 << 40), table.uint64);
+    assert_eq!(address, table.address);
+
+    let tables = sql!(TypesInsertExpr.filter(uint32 == uint32 && address == address)).unwrap();
+    assert_eq!(1, tables.len());
+
+    // The values of u64 that do not fit in a BIGINT cannot be stored.
+    let uint64 = Some(u64::max_value());
+    let result = sql!(TypesInsertExpr.insert(uint8 = 0, uint32 = 0, uint64 = uint64, address = address));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_insert_user_defined_types() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(UserTypesInsertExpr.drop());
+    });
+
+    let _ = sql!(UserTypesInsertExpr.create());
+
+    let email = Email("me@example.com".to_string());
+    let id = sql!(UserTypesInsertExpr.insert(email = email, balance = Cents(1250), credit = None)).unwrap();
+    let _ = sql!(UserTypesInsertExpr.insert(email = Email("you@example.com".to_string()), balance = Cents(10),
+        credit = Some(Cents(500)))).unwrap();
+
+    let table = sql!(UserTypesInsertExpr.get(id)).unwrap();
+    assert_eq!(Email("me@example.com".to_string()), table.email);
+    assert_eq!(Cents(1250), table.balance);
+    assert_eq!(None, table.credit);
+
+    let minimum = Cents(100);
+    let tables = sql!(UserTypesInsertExpr.filter(balance > minimum)).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id, tables[0].primary_key);
+
+    let tables = sql!(UserTypesInsertExpr.filter(credit == Some(Cents(500)))).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(Email("you@example.com".to_string()), tables[0].email);
+}
+
+#[test]
+fn test_insert_enums() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(EnumsInsertExpr.drop());
+    });
+
+    let _ = sql!(EnumsInsertExpr.create());
+
+    let id = sql!(EnumsInsertExpr.insert(status = Status::Todo, priority = Some(Priority::High))).unwrap();
+    let _ = sql!(EnumsInsertExpr.insert(status = Status::Done, priority = None)).unwrap();
+
+    let table = sql!(EnumsInsertExpr.get(id)).unwrap();
+    assert_eq!(Status::Todo, table.status);
+    assert_eq!(Some(Priority::High), table.priority);
+
+    let tables = sql!(EnumsInsertExpr.filter(status == Status::Done)).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(None, tables[0].priority);
+
+    let tables = sql!(EnumsInsertExpr.filter(priority == Some(Priority::High))).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id, tables[0].primary_key);
+}
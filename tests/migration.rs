@@ -0,0 +1,302 @@
+/*
+ * Copyright (c) 2017-2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+#![feature(proc_macro_hygiene)]
+
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+#[macro_use]
+mod connection;
+mod teardown;
+
+backend_extern_crate!();
+
+use tql::{ForeignKey, PrimaryKey};
+use tql::migration::{
+    Backend,
+    Column,
+    Database,
+    HISTORY_TABLE,
+    Migration,
+    Mismatch,
+    Rename,
+    Schema,
+    Step,
+    applied_migrations,
+    migrate,
+    rollback,
+};
+use tql_macros::sql;
+
+use connection::get_connection;
+use teardown::TearDown;
+
+#[derive(SqlTable)]
+struct MigrationAuthor {
+    id: PrimaryKey,
+    name: String,
+}
+
+#[derive(SqlTable)]
+struct MigrationPost {
+    id: PrimaryKey,
+    #[sql(index)]
+    title: String,
+    body: Option<String>,
+    author: ForeignKey<MigrationAuthor>,
+}
+
+#[derive(SqlTable)]
+struct InspectedAuthor {
+    id: PrimaryKey,
+    name: String,
+}
+
+#[derive(SqlTable)]
+struct InspectedPost {
+    id: PrimaryKey,
+    #[sql(index)]
+    title: String,
+    body: Option<String>,
+    author: ForeignKey<InspectedAuthor>,
+}
+
+#[derive(SqlTable)]
+struct CheckedAuthor {
+    id: PrimaryKey,
+    name: String,
+}
+
+#[derive(SqlTable)]
+struct CheckedPost {
+    id: PrimaryKey,
+    title: String,
+    body: Option<String>,
+    author: ForeignKey<CheckedAuthor>,
+}
+
+#[derive(SqlTable)]
+struct DriftedPost {
+    id: PrimaryKey,
+    title: String,
+    body: Option<String>,
+    author: ForeignKey<CheckedAuthor>,
+    views: i32,
+}
+
+#[derive(SqlTable)]
+struct UncreatedTable {
+    id: PrimaryKey,
+}
+
+fn schema() -> Schema {
+    Schema::new()
+        .table::<MigrationAuthor>()
+        .table::<MigrationPost>()
+}
+
+#[test]
+fn test_migrate() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(MigrationPost.drop());
+        let _ = sql!(MigrationAuthor.drop());
+        let _ = Database::execute(&connection, &format!("DROP TABLE {}", HISTORY_TABLE));
+    });
+
+    let backend = connection.backend();
+    let initial = Migration::new("0001_initial", &Schema::new(), &schema(), backend);
+    assert_eq!(vec!["0001_initial"], migrate(&connection, &[initial.clone()]).unwrap());
+    assert!(migrate(&connection, &[initial.clone()]).unwrap().is_empty());
+
+    let author_id = sql!(MigrationAuthor.insert(name = "Me")).unwrap();
+    let author = sql!(MigrationAuthor.get(author_id)).unwrap();
+    let id = sql!(MigrationPost.insert(title = "Title", author = author)).unwrap();
+
+    let mut new_schema = schema();
+    {
+        let post = new_schema.tables.iter_mut().find(|table| table.name == "MigrationPost").unwrap();
+        post.columns.push(Column {
+            name: "views".to_string(),
+            sql_type: "INTEGER".to_string(),
+            nullable: false,
+            default: Some("0".to_string()),
+        });
+        post.indexes.clear();
+    }
+    let views = Migration::new("0002_views", &schema(), &new_schema, backend);
+    let migrations = [initial.clone(), views.clone()];
+    assert_eq!(vec!["0002_views"], migrate(&connection, &migrations).unwrap());
+    assert_eq!(vec!["0001_initial", "0002_views"], applied_migrations(&connection).unwrap());
+
+    let post = sql!(MigrationPost.get(id)).unwrap();
+    assert_eq!("Title", post.title);
+
+    assert!(rollback(&connection, &initial).is_err());
+    rollback(&connection, &views).unwrap();
+    assert_eq!(vec!["0001_initial"], applied_migrations(&connection).unwrap());
+
+    let post = sql!(MigrationPost.get(id)).unwrap();
+    assert_eq!("Title", post.title);
+    assert_eq!(None, post.body);
+
+    rollback(&connection, &initial).unwrap();
+    assert!(applied_migrations(&connection).unwrap().is_empty());
+    assert!(sql!(MigrationPost.get(id)).is_err());
+}
+
+#[test]
+fn test_check_schema() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(DriftedPost.drop());
+        let _ = sql!(CheckedPost.drop());
+        let _ = sql!(CheckedAuthor.drop());
+    });
+
+    sql!(CheckedAuthor.create()).unwrap();
+    sql!(CheckedPost.create()).unwrap();
+    Database::execute(&connection, "CREATE TABLE DriftedPost (id INTEGER PRIMARY KEY, title INTEGER NOT NULL, \
+        body CHARACTER VARYING NOT NULL, author INTEGER NOT NULL REFERENCES CheckedPost(id), deleted BOOLEAN)")
+        .unwrap();
+
+    assert!(CheckedAuthor::check_schema(&connection).unwrap().is_empty());
+    assert!(CheckedPost::check_schema(&connection).unwrap().is_empty());
+
+    let mismatches = DriftedPost::check_schema(&connection).unwrap();
+    let table = || "DriftedPost".to_string();
+    assert_eq!(Mismatch::Type {
+        table: table(),
+        column: "title".to_string(),
+        expected: "CHARACTER VARYING".to_string(),
+        found: "INTEGER".to_string(),
+    }, mismatches[0]);
+    assert_eq!(Mismatch::Nullability {
+        table: table(),
+        column: "body".to_string(),
+        nullable: false,
+    }, mismatches[1]);
+    match mismatches[2] {
+        Mismatch::Type { ref column, ref expected, .. } => {
+            assert_eq!("author", column);
+            assert!(expected.starts_with("INTEGER REFERENCES CheckedAuthor"));
+        },
+        ref mismatch => panic!("unexpected mismatch: {}", mismatch),
+    }
+    assert_eq!(Mismatch::MissingColumn {
+        table: table(),
+        column: "views".to_string(),
+    }, mismatches[3]);
+    assert_eq!(Mismatch::MissingField {
+        table: table(),
+        column: "deleted".to_string(),
+    }, mismatches[4]);
+    assert_eq!(5, mismatches.len());
+    assert_eq!("Column DriftedPost.title has type INTEGER, expected CHARACTER VARYING", mismatches[0].to_string());
+
+    let models = Schema::new()
+        .table::<CheckedAuthor>()
+        .table::<UncreatedTable>();
+    let mismatches = tql::check_all(&connection, &models).unwrap();
+    assert_eq!(vec![Mismatch::MissingTable {
+        table: "UncreatedTable".to_string(),
+    }], mismatches);
+}
+
+#[test]
+fn test_from_database() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(InspectedPost.drop());
+        let _ = sql!(InspectedAuthor.drop());
+    });
+
+    sql!(InspectedAuthor.create()).unwrap();
+    sql!(InspectedPost.create()).unwrap();
+
+    // NOTE: PostgreSQL converts the names of the tables to lowercase.
+    let schema = Schema::from_database(&connection).unwrap();
+    let position = |name: &str| schema.tables.iter().position(|table| table.name.eq_ignore_ascii_case(name)).unwrap();
+    assert!(position("InspectedAuthor") < position("InspectedPost"));
+
+    let post = &schema.tables[position("InspectedPost")];
+    let columns: Vec<_> = post.columns.iter()
+        .map(|column| (column.name.as_str(), column.nullable))
+        .collect();
+    assert_eq!(vec![("id", false), ("title", false), ("body", true), ("author", false)], columns);
+    assert!(post.columns[0].sql_type.ends_with(" PRIMARY KEY"));
+    assert_eq!("CHARACTER VARYING", post.columns[1].sql_type);
+    assert!(post.columns[3].referenced_table().unwrap().eq_ignore_ascii_case("InspectedAuthor"));
+    let indexes: Vec<_> = post.indexes.iter()
+        .map(|index| index.columns.clone())
+        .collect();
+    assert_eq!(vec![vec!["title".to_string()]], indexes);
+}
+
+#[test]
+fn test_snapshot() {
+    let schema = schema();
+    let snapshot = schema.to_snapshot();
+    assert_eq!(schema, Schema::from_snapshot(&snapshot).unwrap());
+    assert!(Schema::from_snapshot("table\tMigrationPost").is_err());
+
+    let mut new_schema = schema.clone();
+    new_schema.tables[1].columns[2].name = "content".to_string();
+    let steps = schema.diff(&new_schema);
+    assert_eq!(vec!["Add column MigrationPost.content", "Drop column MigrationPost.body"],
+               steps.iter().map(ToString::to_string).collect::<Vec<_>>());
+
+    let rename = Rename {
+        table: "MigrationPost".to_string(),
+        old_name: "body".to_string(),
+        new_name: "content".to_string(),
+    };
+    assert_eq!(vec![rename.clone()], schema.rename_candidates(&new_schema));
+    let steps = schema.diff_with_renames(&new_schema, &[rename.clone()]);
+    assert_eq!(vec![Step::RenameColumn {
+        table: "MigrationPost".to_string(),
+        old_name: "body".to_string(),
+        new_name: "content".to_string(),
+    }], steps);
+    assert_eq!("Rename column MigrationPost.body to content", steps[0].to_string());
+    let migration =
+        Migration::with_renames("0002_content", &schema, &new_schema, &[rename.clone()], Backend::Postgres);
+    assert_eq!(vec!["ALTER TABLE MigrationPost RENAME COLUMN body TO content"], migration.up);
+    assert_eq!(vec!["ALTER TABLE MigrationPost RENAME COLUMN content TO body"], migration.down);
+
+    new_schema.tables[1].columns[2].nullable = !schema.tables[1].columns[2].nullable;
+    assert!(schema.rename_candidates(&new_schema).is_empty());
+    let steps = schema.diff_with_renames(&new_schema, &[rename]);
+    assert_eq!(vec!["Rename column MigrationPost.body to content", "Change column MigrationPost.content"],
+               steps.iter().map(ToString::to_string).collect::<Vec<_>>());
+
+    let steps = Schema::new().diff(&schema);
+    assert_eq!(vec!["Create table MigrationAuthor", "Create table MigrationPost"],
+               steps.iter().map(ToString::to_string).collect::<Vec<_>>());
+    let steps = schema.diff(&Schema::new());
+    assert_eq!(vec!["Drop table MigrationPost", "Drop table MigrationAuthor"],
+               steps.iter().map(ToString::to_string).collect::<Vec<_>>());
+}
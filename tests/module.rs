@@ -132,4 +132,52 @@ fn test_insert() {
         int64 = Some(int64)
     )).unwrap();
     assert_eq!(4, id);
+
+    let table = sql!(models::TableModuleExpr.get(id)).unwrap();
+    assert_eq!("value3", table.field1);
+    assert_eq!(Some(42), table.int64);
+}
+
+#[test]
+fn test_module_without_macro_use() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(other_models::TableOtherModule.drop());
+        let _ = sql!(other_models::RelatedTableOtherModule.drop());
+    });
+
+    let _ = sql!(other_models::RelatedTableOtherModule.create());
+    let _ = sql!(other_models::TableOtherModule.create());
+
+    let related_id = sql!(other_models::RelatedTableOtherModule.insert(field1 = 42)).unwrap();
+    let related_field = sql!(other_models::RelatedTableOtherModule.get(related_id)).unwrap();
+
+    let id = sql!(other_models::TableOtherModule.insert(field1 = "value1", related_field = related_field)).unwrap();
+
+    let table = sql!(other_models::TableOtherModule.get(id)).unwrap();
+    assert_eq!("value1", table.field1);
+    assert!(table.related_field.is_none());
+
+    let tables = sql!(other_models::TableOtherModule.filter(field1 == "value1").join(related_field)).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(42, tables[0].related_field.as_ref().unwrap().field1);
+}
+
+// NOTE: this module is declared without #[macro_use] after the queries on its tables.
+mod other_models {
+    use tql::{ForeignKey, PrimaryKey};
+
+    #[derive(SqlTable)]
+    pub struct TableOtherModule {
+        pub primary_key: PrimaryKey,
+        pub field1: String,
+        pub related_field: ForeignKey<RelatedTableOtherModule>,
+    }
+
+    #[derive(SqlTable)]
+    pub struct RelatedTableOtherModule {
+        pub primary_key: PrimaryKey,
+        pub field1: i32,
+    }
 }
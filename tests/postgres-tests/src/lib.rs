@@ -0,0 +1,57 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Models used by the tests of the queries on tables from another crate.
+
+extern crate chrono;
+extern crate postgres;
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+use chrono::DateTime;
+use chrono::offset::Utc;
+use tql::{ForeignKey, PrimaryKey};
+
+#[derive(SqlEmbed)]
+pub struct Address {
+    pub street: String,
+    pub city: String,
+}
+
+#[derive(SqlTable)]
+pub struct Author {
+    pub id: PrimaryKey,
+    pub name: String,
+    #[sql(embed)]
+    pub address: Address,
+}
+
+#[derive(SqlTable)]
+#[sql(soft_delete)]
+pub struct Book {
+    pub id: PrimaryKey,
+    pub title: String,
+    pub author: ForeignKey<Author>,
+    #[sql(version)]
+    pub version: i32,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
@@ -27,7 +27,7 @@ extern crate tql;
 extern crate tql_macros;
 
 use tql::PrimaryKey;
-use tql_macros::to_sql;
+use tql_macros::{sql_aggregate, to_sql};
 
 #[derive(SqlTable)]
 #[allow(dead_code)]
@@ -37,6 +37,9 @@ struct Table {
     field2: i32,
 }
 
+#[sql_aggregate(template = "MAX($0) - MIN($0)")]
+fn spread() -> f64;
+
 #[test]
 fn test_aggregate() {
     assert_eq!(
@@ -68,3 +71,15 @@ fn test_aggregate() {
         to_sql!(Table.filter(field2 > 10).values(field1).aggregate(average = avg(field2)).filter(average < 20))
     );
 }
+
+#[test]
+fn test_user_aggregate() {
+    assert_eq!(
+        "SELECT CAST(MAX(field2) - MIN(field2) AS DOUBLE PRECISION) FROM Table",
+        to_sql!(Table.aggregate(spread(field2)))
+    );
+    assert_eq!(
+        "SELECT CAST(MAX(field2) - MIN(field2) AS DOUBLE PRECISION) FROM Table GROUP BY field1 HAVING CAST(MAX(field2) - MIN(field2) AS DOUBLE PRECISION) > 10",
+        to_sql!(Table.values(field1).aggregate(spread(field2)).filter(field2_spread > 10))
+    );
+}
@@ -0,0 +1,109 @@
+/*
+ * Copyright (c) 2017-2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+#![feature(proc_macro_hygiene)]
+
+extern crate postgres;
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+use tql::{ForeignKey, PrimaryKey};
+use tql_macros::to_sql;
+
+#[derive(SqlEmbed)]
+#[allow(dead_code)]
+struct Address {
+    street: String,
+    zip: Option<i32>,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Table {
+    id: PrimaryKey,
+    field1: String,
+    #[sql(index)]
+    field2: Option<i32>,
+    related_field: ForeignKey<RelatedTable>,
+    #[sql(embed)]
+    address: Address,
+    #[sql(version)]
+    version: i32,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct RelatedTable {
+    id: PrimaryKey,
+}
+
+#[test]
+fn test_add_column() {
+    assert_eq!(
+        "ALTER TABLE Table ADD COLUMN field2 INTEGER; CREATE INDEX Table_field2_idx ON Table (field2)",
+        to_sql!(Table.add_column(field2))
+    );
+    assert_eq!(
+        "ALTER TABLE Table ADD COLUMN field1 CHARACTER VARYING NOT NULL",
+        to_sql!(Table.add_column(field1))
+    );
+    assert_eq!(
+        "ALTER TABLE Table ADD COLUMN related_field INTEGER REFERENCES RelatedTable(id) NOT NULL",
+        to_sql!(Table.add_column(related_field))
+    );
+    assert_eq!(
+        "ALTER TABLE Table ADD COLUMN address_street CHARACTER VARYING NOT NULL; ALTER TABLE Table ADD COLUMN address_zip INTEGER",
+        to_sql!(Table.add_column(address))
+    );
+    assert_eq!(
+        "ALTER TABLE Table ADD COLUMN version INTEGER NOT NULL DEFAULT 1",
+        to_sql!(Table.add_column(version))
+    );
+}
+
+#[test]
+fn test_drop_column() {
+    assert_eq!(
+        "ALTER TABLE Table DROP COLUMN field3",
+        to_sql!(Table.drop_column(field3))
+    );
+}
+
+#[test]
+fn test_rename_column() {
+    assert_eq!(
+        "ALTER TABLE Table RENAME COLUMN name TO field1",
+        to_sql!(Table.rename_column(name, field1))
+    );
+    assert_eq!(
+        "ALTER TABLE Table RENAME COLUMN location_street TO address_street; ALTER TABLE Table RENAME COLUMN location_zip TO address_zip",
+        to_sql!(Table.rename_column(location, address))
+    );
+}
+
+#[test]
+fn test_rename_to() {
+    assert_eq!(
+        "ALTER TABLE Table RENAME TO Tables",
+        to_sql!(Table.rename_to(Tables))
+    );
+}
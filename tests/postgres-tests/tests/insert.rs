@@ -21,11 +21,14 @@
 
 #![feature(proc_macro_hygiene)]
 
+extern crate chrono;
 extern crate postgres;
 extern crate tql;
 #[macro_use]
 extern crate tql_macros;
 
+use chrono::DateTime;
+use chrono::offset::Utc;
 use tql::{ForeignKey, PrimaryKey};
 use tql_macros::to_sql;
 
@@ -46,6 +49,33 @@ struct RelatedTable {
     field1: String,
 }
 
+#[derive(SqlEmbed)]
+#[allow(dead_code)]
+struct Address {
+    street: String,
+    city: String,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Customers {
+    pk: PrimaryKey,
+    name: String,
+    #[sql(embed)]
+    address: Address,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Posts {
+    id: PrimaryKey,
+    title: String,
+    #[sql(auto_now_add)]
+    date_added: DateTime<Utc>,
+    #[sql(auto_now)]
+    date_updated: DateTime<Utc>,
+}
+
 #[test]
 fn test_insert() {
     assert_eq!(
@@ -65,3 +95,23 @@ fn test_insert() {
         to_sql!(Table.insert(field1 = "value1", field2 = 55, related_field = related_object, optional_field = 42))
     );
 }
+
+#[test]
+fn test_insert_embedded() {
+    assert_eq!(
+        "INSERT INTO Customers(name, address_street, address_city) VALUES('John', $1, $2) RETURNING pk",
+        to_sql!(Customers.insert(name = "John", address = address))
+    );
+}
+
+#[test]
+fn test_insert_auto_time() {
+    assert_eq!(
+        "INSERT INTO Posts(title) VALUES('Tql') RETURNING id",
+        to_sql!(Posts.insert(title = "Tql"))
+    );
+    assert_eq!(
+        "INSERT INTO Posts(title, date_added) VALUES('Tql', $1) RETURNING id",
+        to_sql!(Posts.insert(title = "Tql", date_added = date))
+    );
+}
@@ -30,7 +30,7 @@ extern crate tql_macros;
 use chrono::DateTime;
 use chrono::offset::Utc;
 use tql::{ForeignKey, PrimaryKey};
-use tql_macros::to_sql;
+use tql_macros::{sql_function, to_sql};
 
 #[derive(SqlTable)]
 #[allow(dead_code)]
@@ -50,6 +50,28 @@ struct RelatedTable {
     field1: String,
 }
 
+#[derive(SqlEmbed)]
+#[allow(dead_code)]
+struct Address {
+    street: String,
+    city: String,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Customers {
+    id: PrimaryKey,
+    name: String,
+    #[sql(embed)]
+    address: Address,
+}
+
+#[sql_function(template = "LOWER($0)")]
+fn lower(string: String) -> String;
+
+#[sql_function(postgres = "POSITION($1 IN $0)", sqlite = "INSTR($0, $1)")]
+fn position(string: String, substring: &str) -> i32;
+
 const SELECT: &str = "SELECT Table.id, Table.field1, Table.field2, Table.field3, Table.date";
 
 #[test]
@@ -127,23 +149,23 @@ fn test_filter_method_call() {
         to_sql!(Table.filter(date.year() == 2015 && date.month() == 10 && date.day() == 26 && date.hour() == 1 && date.minute() == 39 && date.second() > 0))
     );
     assert_eq!(
-        format!("{} FROM Table WHERE field1 LIKE '%' || 'value' || '%' = TRUE", SELECT),
+        format!(r"{} FROM Table WHERE field1 LIKE '%' || REPLACE(REPLACE(REPLACE('value', '\', '\\'), '%', '\%'), '_', '\_') || '%' ESCAPE '\' = TRUE", SELECT),
         to_sql!(Table.filter(field1.contains("value") == true))
     );
     assert_eq!(
-        format!("{} FROM Table WHERE field1 LIKE '%' || 'value' || '%'", SELECT),
+        format!(r"{} FROM Table WHERE field1 LIKE '%' || REPLACE(REPLACE(REPLACE('value', '\', '\\'), '%', '\%'), '_', '\_') || '%' ESCAPE '\'", SELECT),
         to_sql!(Table.filter(field1.contains("value")))
     );
     assert_eq!(
-        format!("{} FROM Table WHERE field1 LIKE 'va' || '%'", SELECT),
+        format!(r"{} FROM Table WHERE field1 LIKE REPLACE(REPLACE(REPLACE('va', '\', '\\'), '%', '\%'), '_', '\_') || '%' ESCAPE '\'", SELECT),
         to_sql!(Table.filter(field1.starts_with("va")))
     );
     assert_eq!(
-        format!("{} FROM Table WHERE field1 LIKE '%' || 'e1'", SELECT),
+        format!(r"{} FROM Table WHERE field1 LIKE '%' || REPLACE(REPLACE(REPLACE('e1', '\', '\\'), '%', '\%'), '_', '\_') ESCAPE '\'", SELECT),
         to_sql!(Table.filter(field1.ends_with("e1")))
     );
     assert_eq!(
-        format!("{} FROM Table WHERE field1 LIKE '%' || $1 || '%'", SELECT),
+        format!(r"{} FROM Table WHERE field1 LIKE '%' || REPLACE(REPLACE(REPLACE($1, '\', '\\'), '%', '\%'), '_', '\_') || '%' ESCAPE '\'", SELECT),
         to_sql!(Table.filter(field1.contains(value)))
     );
     assert_eq!(
@@ -151,16 +173,108 @@ fn test_filter_method_call() {
         to_sql!(Table.filter(field1.len() == 6))
     );
     assert_eq!(
-        format!("{} FROM Table WHERE field1 LIKE '%3'", SELECT),
-        to_sql!(Table.filter(field1.regex(r"%3")))
+        format!("{} FROM Table WHERE field1 ~ '3$'", SELECT),
+        to_sql!(Table.filter(field1.regex(r"3$")))
     );
     assert_eq!(
-        format!("{} FROM Table WHERE field1 LIKE '%E3'", SELECT),
-        to_sql!(Table.filter(field1.regex(r"%E3")))
+        format!("{} FROM Table WHERE field1 ~ '^v.*E3$'", SELECT),
+        to_sql!(Table.filter(field1.regex(r"^v.*E3$")))
     );
     assert_eq!(
-        format!("{} FROM Table WHERE field1 ILIKE '%E3'", SELECT),
-        to_sql!(Table.filter(field1.iregex(r"%E3")))
+        format!("{} FROM Table WHERE field1 ~* '^v.*E3$'", SELECT),
+        to_sql!(Table.filter(field1.iregex(r"^v.*E3$")))
+    );
+}
+
+#[test]
+fn test_filter_string_methods() {
+    assert_eq!(
+        format!(r"{} FROM Table WHERE field1 ILIKE '%' || REPLACE(REPLACE(REPLACE('VAL', '\', '\\'), '%', '\%'), '_', '\_') || '%' ESCAPE '\'", SELECT),
+        to_sql!(Table.filter(field1.icontains("VAL")))
+    );
+    assert_eq!(
+        format!(r"{} FROM Table WHERE field1 ILIKE REPLACE(REPLACE(REPLACE('VA', '\', '\\'), '%', '\%'), '_', '\_') || '%' ESCAPE '\'", SELECT),
+        to_sql!(Table.filter(field1.istarts_with("VA")))
+    );
+    assert_eq!(
+        format!(r"{} FROM Table WHERE field1 ILIKE '%' || REPLACE(REPLACE(REPLACE($1, '\', '\\'), '%', '\%'), '_', '\_') ESCAPE '\'", SELECT),
+        to_sql!(Table.filter(field1.iends_with(value)))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE LOWER(field1) = LOWER('VALUE1')", SELECT),
+        to_sql!(Table.filter(field1.eq_ignore_case("VALUE1")))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE UPPER(field1) = 'VALUE1'", SELECT),
+        to_sql!(Table.filter(field1.to_uppercase() == "VALUE1"))
+    );
+    assert_eq!(
+        format!(r"{} FROM Table WHERE LOWER(TRIM(field1)) LIKE REPLACE(REPLACE(REPLACE('va', '\', '\\'), '%', '\%'), '_', '\_') || '%' ESCAPE '\'", SELECT),
+        to_sql!(Table.filter(field1.trim().to_lowercase().starts_with("va")))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE LENGTH(TRIM(field1)) > 3", SELECT),
+        to_sql!(Table.filter(field1.trim().len() > 3))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE LOWER(SUBSTR(field1, $1, 3)) = $2", SELECT),
+        to_sql!(Table.filter(field1.substring(start, 3).to_lowercase() == value))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE LOWER(TRIM(field1)) = 'value'", SELECT),
+        to_sql!(Table.filter(field1.trim().lower() == "value"))
+    );
+}
+
+#[test]
+fn test_filter_user_function() {
+    assert_eq!(
+        format!("{} FROM Table WHERE LOWER(field1) = 'value'", SELECT),
+        to_sql!(Table.filter(field1.lower() == "value"))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE POSITION('lu' IN field1) = 3", SELECT),
+        to_sql!(Table.filter(field1.position("lu") == 3))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE POSITION($1 IN field1) > $2 AND Table.field2 = $3", SELECT),
+        to_sql!(Table.filter(field1.position(substring) > position && field2 == value))
+    );
+}
+
+#[test]
+fn test_filter_date() {
+    assert_eq!(
+        format!("{} FROM Table WHERE Table.date > NOW() - INTERVAL '7 days'", SELECT),
+        to_sql!(Table.filter(date > tql::now() - tql::days(7)))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE Table.date < NOW() + INTERVAL '1 months' - CAST($1 AS INTEGER) * INTERVAL '1 hours'", SELECT),
+        to_sql!(Table.filter(date < tql::now() + tql::months(1) - tql::hours(delay)))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE DATE_TRUNC('day', date) < NOW() - INTERVAL '2 days'", SELECT),
+        to_sql!(Table.filter(date.date_trunc("day") < tql::now() - tql::days(2)))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE EXTRACT(ISODOW FROM date) - 1 = 0", SELECT),
+        to_sql!(Table.filter(date.weekday() == 0))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE CAST(date AS DATE) = $1", SELECT),
+        to_sql!(Table.filter(date.date() == day))
+    );
+}
+
+#[test]
+fn test_filter_embedded() {
+    assert_eq!(
+        "SELECT Customers.id, Customers.name, Customers.address_street, Customers.address_city FROM Customers WHERE Customers.address_city = 'Paris'",
+        to_sql!(Customers.filter(address.city == "Paris"))
+    );
+    assert_eq!(
+        "SELECT Customers.id, Customers.name, Customers.address_street, Customers.address_city FROM Customers WHERE Customers.name = $1 AND Customers.address_street = $2",
+        to_sql!(Customers.filter(name == customer_name && address.street == street))
     );
 }
 
@@ -315,3 +429,27 @@ fn test_limit() {
         to_sql!(Table.all()[-index as i64])
     );
 }
+
+#[test]
+fn test_subquery() {
+    assert_eq!(
+        format!("{} FROM Table WHERE Table.related_field IN (SELECT RelatedTable.id FROM RelatedTable WHERE RelatedTable.field1 = $1)", SELECT),
+        to_sql!(Table.filter(related_field.in_(sql!(RelatedTable.filter(field1 == value).only(id)))))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE Table.field1 = $1 AND Table.related_field IN (SELECT RelatedTable.id FROM RelatedTable WHERE RelatedTable.field1 = $2) LIMIT $3", SELECT),
+        to_sql!(Table.filter(field1 == value1 && related_field.in_(sql!(RelatedTable.filter(field1 == value2).only(id))))[..limit])
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE EXISTS (SELECT RelatedTable.id, RelatedTable.field1 FROM RelatedTable WHERE RelatedTable.field1 = 'test')", SELECT),
+        to_sql!(Table.filter(exists(sql!(RelatedTable.filter(field1 == "test")))))
+    );
+    assert_eq!(
+        format!(r"{} FROM Table WHERE field1 LIKE '%' || REPLACE(REPLACE(REPLACE($1, '\', '\\'), '%', '\%'), '_', '\_') || '%' ESCAPE '\' AND Table.related_field IN (SELECT RelatedTable.id FROM RelatedTable WHERE RelatedTable.field1 = $2)", SELECT),
+        to_sql!(Table.filter(field1.contains(value1) && related_field.in_(sql!(RelatedTable.filter(field1 == value2).only(id)))))
+    );
+    assert_eq!(
+        format!("{} FROM Table WHERE Table.field2 > (SELECT CAST(AVG(field2) AS DOUBLE PRECISION) FROM Table WHERE Table.field1 = $1)", SELECT),
+        to_sql!(Table.filter(field2 > sql!(Table.filter(field1 == value).aggregate(avg(field2)))))
+    );
+}
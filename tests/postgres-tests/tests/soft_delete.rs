@@ -0,0 +1,94 @@
+/*
+ * Copyright (c) 2017-2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+#![feature(proc_macro_hygiene)]
+
+extern crate chrono;
+extern crate postgres;
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+use chrono::DateTime;
+use chrono::offset::Utc;
+use tql::{ForeignKey, PrimaryKey};
+use tql_macros::to_sql;
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+#[sql(soft_delete)]
+struct Posts {
+    id: PrimaryKey,
+    title: String,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Comments {
+    id: PrimaryKey,
+    text: String,
+    post: ForeignKey<Posts>,
+}
+
+#[test]
+fn test_soft_delete() {
+    assert_eq!(
+        "SELECT Posts.id, Posts.title, Posts.deleted_at FROM Posts WHERE Posts.deleted_at IS NULL",
+        to_sql!(Posts.all())
+    );
+    assert_eq!(
+        "SELECT Posts.id, Posts.title, Posts.deleted_at FROM Posts WHERE (Posts.id = 1) AND Posts.deleted_at IS NULL",
+        to_sql!(Posts.get(1))
+    );
+    assert_eq!(
+        "SELECT Posts.id, Posts.title, Posts.deleted_at FROM Posts WHERE (Posts.title = 'Tql' OR Posts.title = 'SQL') AND Posts.deleted_at IS NULL ORDER BY title",
+        to_sql!(Posts.filter(title == "Tql" || title == "SQL").sort(title))
+    );
+    assert_eq!(
+        "SELECT CAST(AVG(id) AS DOUBLE PRECISION) FROM Posts WHERE Posts.deleted_at IS NULL",
+        to_sql!(Posts.aggregate(avg(id)))
+    );
+    assert_eq!(
+        "SELECT Posts.id, Posts.title, Posts.deleted_at FROM Posts WHERE Posts.title = 'Tql'",
+        to_sql!(Posts.with_deleted().filter(title == "Tql"))
+    );
+    assert_eq!(
+        "UPDATE Posts SET deleted_at = CURRENT_TIMESTAMP WHERE (Posts.id = 1) AND Posts.deleted_at IS NULL",
+        to_sql!(Posts.get(1).delete())
+    );
+    assert_eq!(
+        "DELETE FROM Posts WHERE Posts.id = 1",
+        to_sql!(Posts.get(1).hard_delete())
+    );
+    assert_eq!(
+        "UPDATE Posts SET title = 'Tql' WHERE (Posts.id = 1) AND Posts.deleted_at IS NULL",
+        to_sql!(Posts.get(1).update(title = "Tql"))
+    );
+    assert_eq!(
+        "UPDATE Posts SET title = 'Tql' WHERE Posts.id = 1",
+        to_sql!(Posts.with_deleted().get(1).update(title = "Tql"))
+    );
+    assert_eq!(
+        "SELECT Comments.id, Comments.text, Posts.id, Posts.title, Posts.deleted_at FROM Comments INNER JOIN Posts ON Comments.post = Posts.id AND Posts.deleted_at IS NULL",
+        to_sql!(Comments.all().join(post))
+    );
+}
@@ -0,0 +1,77 @@
+/*
+ * Copyright (c) 2017-2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+#![feature(proc_macro_hygiene)]
+
+extern crate chrono;
+extern crate postgres;
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+use chrono::DateTime;
+use chrono::offset::Utc;
+use tql::PrimaryKey;
+use tql_macros::to_sql;
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Posts {
+    id: PrimaryKey,
+    title: String,
+    #[sql(auto_now_add)]
+    date_added: DateTime<Utc>,
+    #[sql(auto_now)]
+    date_updated: DateTime<Utc>,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Items {
+    id: PrimaryKey,
+    name: String,
+    #[sql(version)]
+    version: i32,
+}
+
+#[test]
+fn test_update_auto_now() {
+    assert_eq!(
+        "UPDATE Posts SET title = 'Tql', date_updated = CURRENT_TIMESTAMP WHERE Posts.id = 1",
+        to_sql!(Posts.get(1).update(title = "Tql"))
+    );
+    assert_eq!(
+        "UPDATE Posts SET date_updated = $1 WHERE Posts.title = 'Draft'",
+        to_sql!(Posts.filter(title == "Draft").update(date_updated = date))
+    );
+}
+
+#[test]
+fn test_update_version() {
+    assert_eq!(
+        "UPDATE Items SET name = 'Tql', version = version + 1 WHERE (Items.id = 1) AND Items.version = $1",
+        to_sql!(Items.get(1).update(name = "Tql", version = version))
+    );
+    assert_eq!(
+        "UPDATE Items SET version = version + 1, name = $1 WHERE (Items.name = 'SQL' OR Items.name = $2) AND Items.version = 3",
+        to_sql!(Items.filter(name == "SQL" || name == old_name).update(version = 3, name = new_name))
+    );
+}
@@ -27,6 +27,9 @@ macro_rules! let_vec {
     };
 }
 
+use std::cell::Cell;
+#[cfg(feature = "json")]
+use std::collections::HashMap;
 use std::str::FromStr;
 
 extern crate chrono;
@@ -40,14 +43,22 @@ mod teardown;
 
 backend_extern_crate!();
 
-use chrono::DateTime;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Timelike};
 use chrono::offset::Utc;
+#[cfg(feature = "json")]
+use tql::Json;
 use tql::{ForeignKey, PrimaryKey};
-use tql_macros::sql;
+use tql_macros::{sql, sql_function};
 
 use connection::{get_connection, is_not_found};
 use teardown::TearDown;
 
+#[sql_function(template = "UPPER($0)")]
+fn upper(string: String) -> String;
+
+#[sql_function(postgres = "POSITION($1 IN $0)", sqlite = "INSTR($0, $1)")]
+fn position(string: String, substring: &str) -> i32;
+
 #[derive(SqlTable)]
 struct TableSelectExpr {
     id: PrimaryKey,
@@ -117,6 +128,49 @@ struct Table5_Table6 {
     table6: ForeignKey<Table6>,
 }
 
+#[cfg(feature = "postgres")]
+#[derive(SqlTable)]
+struct ArraysSelectExpr {
+    id: PrimaryKey,
+    title: String,
+    tags: Vec<String>,
+    scores: Option<Vec<i32>>,
+}
+
+#[cfg(feature = "json")]
+#[derive(SqlTable)]
+struct JsonSelectExpr {
+    id: PrimaryKey,
+    meta: Json<HashMap<String, String>>,
+}
+
+#[derive(Clone, SqlEmbed)]
+struct AddressSelectExpr {
+    street: String,
+    city: String,
+    zip: Option<i32>,
+}
+
+#[derive(SqlTable)]
+struct EmbedSelectExpr {
+    id: PrimaryKey,
+    name: String,
+    #[sql(embed)]
+    address: AddressSelectExpr,
+}
+
+/// A user function with the name of a date function of tql: the clock of the tests is in the past.
+fn now() -> DateTime<Utc> {
+    FromStr::from_str("2015-11-16T15:51:12-05:00").unwrap()
+}
+
+#[derive(SqlTable)]
+struct DateSelectExpr {
+    id: PrimaryKey,
+    datetime: DateTime<Utc>,
+    naive_datetime: NaiveDateTime,
+}
+
 #[test]
 fn test_select() {
     let connection = get_connection();
@@ -305,6 +359,28 @@ fn test_select() {
     let_vec!(table1 = tables);
     assert_eq!(id4, table1.id);
 
+    let tables = sql!(TableSelectExpr.filter(datetime < tql::now() - tql::days(30))).unwrap();
+    assert_eq!(5, tables.len());
+
+    let tables = sql!(TableSelectExpr.filter(datetime > tql::now() + tql::hours(1))).unwrap();
+    assert_eq!(0, tables.len());
+
+    let age = 1;
+    let tables = sql!(TableSelectExpr.filter(datetime.date_trunc("day") < tql::now() - tql::years(age))).unwrap();
+    assert_eq!(5, tables.len());
+
+    // NOTE: 2015-11-16 is a Monday.
+    let mut tables = sql!(TableSelectExpr.filter(datetime.weekday() == 0)).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id4, table1.id);
+
+    let date = NaiveDate::from_ymd(2015, 11, 16);
+    let mut tables = sql!(TableSelectExpr.filter(datetime.date() == date)).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id4, table1.id);
+
     let mut tables = sql!(TableSelectExpr.filter(field1.contains("value1"))).unwrap();
     assert_eq!(1, tables.len());
     let_vec!(table1 = tables);
@@ -349,6 +425,16 @@ fn test_select() {
     assert_eq!(id4, table4.id);
     assert_eq!(id5, table5.id);
 
+    // The LIKE wildcards are matched literally.
+    let tables = sql!(TableSelectExpr.filter(field1.contains("%"))).unwrap();
+    assert_eq!(0, tables.len());
+
+    let tables = sql!(TableSelectExpr.filter(field1.starts_with("v_l"))).unwrap();
+    assert_eq!(0, tables.len());
+
+    let tables = sql!(TableSelectExpr.filter(field1.ends_with(r"\1"))).unwrap();
+    assert_eq!(0, tables.len());
+
     let mut tables = sql!(TableSelectExpr.filter(field1.len() == 6)).unwrap();
     assert_eq!(5, tables.len());
     let_vec!(table1, table2, table3, table4, table5 = tables);
@@ -358,22 +444,71 @@ fn test_select() {
     assert_eq!(id4, table4.id);
     assert_eq!(id5, table5.id);
 
-    #[cfg(feature = "postgres")]
-    {
-        let mut tables = sql!(TableSelectExpr.filter(field1.regex("%3"))).unwrap();
-        assert_eq!(1, tables.len());
-        let_vec!(table1 = tables);
-        assert_eq!(id3, table1.id);
+    let mut tables = sql!(TableSelectExpr.filter(field1.regex("3$"))).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id3, table1.id);
 
-        let tables = sql!(TableSelectExpr.filter(field1.regex("%E3"))).unwrap();
-        assert_eq!(0, tables.len());
-    }
+    let tables = sql!(TableSelectExpr.filter(field1.regex("^v.*E3$"))).unwrap();
+    assert_eq!(0, tables.len());
+
+    let mut tables = sql!(TableSelectExpr.filter(field1.regex("^value[1-2]$"))).unwrap();
+    assert_eq!(2, tables.len());
+    let_vec!(table1, table2 = tables);
+    assert_eq!(id1, table1.id);
+    assert_eq!(id2, table2.id);
+
+    let mut tables = sql!(TableSelectExpr.filter(field1.iregex("^v.*E3$"))).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id3, table1.id);
 
-    let mut tables = sql!(TableSelectExpr.filter(field1.iregex("%E3"))).unwrap();
+    let mut tables = sql!(TableSelectExpr.filter(field1.icontains("UE3"))).unwrap();
     assert_eq!(1, tables.len());
     let_vec!(table1 = tables);
     assert_eq!(id3, table1.id);
 
+    let tables = sql!(TableSelectExpr.filter(field1.istarts_with("VA"))).unwrap();
+    assert_eq!(5, tables.len());
+
+    let mut tables = sql!(TableSelectExpr.filter(field1.iends_with("E2"))).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id2, table1.id);
+
+    let mut tables = sql!(TableSelectExpr.filter(field1.eq_ignore_case("VALUE5"))).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id5, table1.id);
+
+    let mut tables = sql!(TableSelectExpr.filter(field1.to_uppercase() == "VALUE1")).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id1, table1.id);
+
+    let mut tables = sql!(TableSelectExpr.filter(field1.trim().to_uppercase().ends_with("E4"))).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id4, table1.id);
+
+    let start = 2;
+    let mut tables = sql!(TableSelectExpr.filter(field1.substring(start, 4).to_lowercase() == "alue" && field1.ends_with("3"))).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id3, table1.id);
+
+    let mut tables = sql!(TableSelectExpr.filter(field1.upper() == "VALUE4")).unwrap();
+    assert_eq!(1, tables.len());
+    let_vec!(table1 = tables);
+    assert_eq!(id4, table1.id);
+
+    let substring = "5";
+    let mut tables = sql!(TableSelectExpr.filter(field1.position(substring) == 6 || field1.position("2") > 0)).unwrap();
+    assert_eq!(2, tables.len());
+    let_vec!(table1, table2 = tables);
+    assert_eq!(id2, table1.id);
+    assert_eq!(id5, table2.id);
+
     let table = sql!(TableSelectExpr.filter(field1 == "value2").get()).unwrap();
     assert_eq!(id2, table.id);
 
@@ -532,6 +667,26 @@ fn test_select() {
     let table = sql!(TableSelectExpr[i64::from(-index)]).unwrap();
     assert_eq!(id3, table.id);
 
+    let related_value = 24;
+    let mut tables = sql!(TableSelectExpr.filter(related_field.in_(sql!(RelatedTableSelectExpr.filter(field1 == related_value).only(id)))).sort(id)).unwrap();
+    assert_eq!(3, tables.len());
+    let_vec!(table1, table2, table3 = tables);
+    assert_eq!(id3, table1.id);
+    assert_eq!(id4, table2.id);
+    assert_eq!(id5, table3.id);
+
+    let tables = sql!(TableSelectExpr.filter(exists(sql!(RelatedTableSelectExpr.filter(field1 == 42))))).unwrap();
+    assert_eq!(5, tables.len());
+
+    let tables = sql!(TableSelectExpr.filter(exists(sql!(RelatedTableSelectExpr.filter(field1 == 1))))).unwrap();
+    assert_eq!(0, tables.len());
+
+    let mut tables = sql!(TableSelectExpr.filter(field2 > sql!(TableSelectExpr.aggregate(avg(field2)))).sort(id)).unwrap();
+    assert_eq!(2, tables.len());
+    let_vec!(table1, table2 = tables);
+    assert_eq!(id1, table1.id);
+    assert_eq!(id5, table2.id);
+
     let table2_id = sql!(Table2.insert(field1 = 24, field2 = 42)).unwrap();
     let related1 = sql!(Table2.get(table2_id)).unwrap();
     let table2_id = sql!(Table3.insert(field1 = 25, field2 = 43)).unwrap();
@@ -577,3 +732,175 @@ fn test_select() {
     //table5.tables6.all();
     sql!(Table5_Table6.all().join(table5));
 }
+
+#[cfg(feature = "postgres")]
+#[test]
+fn test_select_arrays() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(ArraysSelectExpr.drop());
+    });
+
+    let _ = sql!(ArraysSelectExpr.create());
+
+    let tags = vec!["rust".to_string(), "sql".to_string()];
+    let id1 = sql!(ArraysSelectExpr.insert(title = "first", tags = tags, scores = Some(vec![1, 2, 3]))).unwrap();
+    let tags = vec!["python".to_string()];
+    let id2 = sql!(ArraysSelectExpr.insert(title = "second", tags = tags)).unwrap();
+
+    let table = sql!(ArraysSelectExpr.get(id1)).unwrap();
+    assert_eq!(vec!["rust".to_string(), "sql".to_string()], table.tags);
+    assert_eq!(Some(vec![1, 2, 3]), table.scores);
+
+    let tables = sql!(ArraysSelectExpr.filter(tags.contains_element("rust"))).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id1, tables[0].id);
+
+    let wanted = vec!["python".to_string(), "go".to_string()];
+    let tables = sql!(ArraysSelectExpr.filter(tags.overlaps(&wanted))).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id2, tables[0].id);
+
+    let tables = sql!(ArraysSelectExpr.filter(tags.len() == 2 && title.len() == 5)).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id1, tables[0].id);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_select_json() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(JsonSelectExpr.drop());
+    });
+
+    let _ = sql!(JsonSelectExpr.create());
+
+    let mut map = HashMap::new();
+    map.insert("color".to_string(), "red".to_string());
+    map.insert("size".to_string(), "large".to_string());
+    let meta = Json(map);
+    let id1 = sql!(JsonSelectExpr.insert(meta = meta)).unwrap();
+    let mut map = HashMap::new();
+    map.insert("color".to_string(), "blue".to_string());
+    let meta = Json(map);
+    let id2 = sql!(JsonSelectExpr.insert(meta = meta)).unwrap();
+
+    let table = sql!(JsonSelectExpr.get(id1)).unwrap();
+    assert_eq!(Some(&"large".to_string()), table.meta.get("size"));
+
+    let tables = sql!(JsonSelectExpr.filter(meta.get_str("color") == "blue")).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id2, tables[0].id);
+
+    let tables = sql!(JsonSelectExpr.filter(meta.has_key("size"))).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id1, tables[0].id);
+
+    let key = "color";
+    let tables = sql!(JsonSelectExpr.filter(meta.has_key(key)).sort(id)).unwrap();
+    assert_eq!(2, tables.len());
+}
+
+#[test]
+fn test_select_embed() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(EmbedSelectExpr.drop());
+    });
+
+    let _ = sql!(EmbedSelectExpr.create());
+
+    let address = AddressSelectExpr {
+        street: "1 Main Street".to_string(),
+        city: "Paris".to_string(),
+        zip: Some(75001),
+    };
+    let id1 = sql!(EmbedSelectExpr.insert(name = "first", address = address)).unwrap();
+    let address = AddressSelectExpr {
+        street: "2 Main Street".to_string(),
+        city: "Montreal".to_string(),
+        zip: None,
+    };
+    let id2 = sql!(EmbedSelectExpr.insert(name = "second", address = address)).unwrap();
+
+    let table = sql!(EmbedSelectExpr.get(id1)).unwrap();
+    assert_eq!("first", table.name);
+    assert_eq!("1 Main Street", table.address.street);
+    assert_eq!("Paris", table.address.city);
+    assert_eq!(Some(75001), table.address.zip);
+
+    let tables = sql!(EmbedSelectExpr.filter(address.city == "Montreal")).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id2, tables[0].id);
+    assert_eq!("second", tables[0].name);
+    assert_eq!(None, tables[0].address.zip);
+
+    let city = "Paris".to_string();
+    let tables = sql!(EmbedSelectExpr.filter(name == "first" && address.city == city)).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id1, tables[0].id);
+
+    let tables = sql!(EmbedSelectExpr.filter(address.street == "1 Main Street" || address.street == "2 Main Street").sort(id)).unwrap();
+    assert_eq!(2, tables.len());
+    assert_eq!(id1, tables[0].id);
+    assert_eq!(id2, tables[1].id);
+
+    // The expression of an embedded struct is evaluated once, even though it is sent as one argument per field.
+    let calls = Cell::new(0);
+    let new_address = || {
+        calls.set(calls.get() + 1);
+        AddressSelectExpr {
+            street: "3 Main Street".to_string(),
+            city: "Lyon".to_string(),
+            zip: Some(69001),
+        }
+    };
+    let id3 = sql!(EmbedSelectExpr.insert(name = "third", address = new_address())).unwrap();
+    assert_eq!(1, calls.get());
+
+    let table = sql!(EmbedSelectExpr.get(id3)).unwrap();
+    assert_eq!("3 Main Street", table.address.street);
+    assert_eq!("Lyon", table.address.city);
+    assert_eq!(Some(69001), table.address.zip);
+}
+
+#[test]
+fn test_select_recent_dates() {
+    let connection = get_connection();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(DateSelectExpr.drop());
+    });
+
+    let _ = sql!(DateSelectExpr.create());
+
+    // NOTE: the dates from the current day are compared with the dates computed by the database.
+    let datetime = Utc::now() - Duration::minutes(5);
+    let naive_datetime = datetime.naive_utc();
+    let id = sql!(DateSelectExpr.insert(datetime = datetime, naive_datetime = naive_datetime)).unwrap();
+
+    let tables = sql!(DateSelectExpr.filter(datetime > tql::now() - tql::minutes(1))).unwrap();
+    assert_eq!(0, tables.len());
+
+    let tables = sql!(DateSelectExpr.filter(datetime > tql::now() - tql::hours(1))).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id, tables[0].id);
+
+    let tables = sql!(DateSelectExpr.filter(datetime < tql::now() && datetime > tql::now() - tql::days(1))).unwrap();
+    assert_eq!(1, tables.len());
+
+    let minute = datetime.with_second(0).and_then(|datetime| datetime.with_nanosecond(0)).unwrap();
+    let tables = sql!(DateSelectExpr.filter(datetime.date_trunc("minute") == minute)).unwrap();
+    assert_eq!(1, tables.len());
+
+    let naive_minute = minute.naive_utc();
+    let tables = sql!(DateSelectExpr.filter(naive_datetime.date_trunc("minute") == naive_minute)).unwrap();
+    assert_eq!(1, tables.len());
+
+    let tables = sql!(DateSelectExpr.filter(datetime > now())).unwrap();
+    assert_eq!(1, tables.len());
+}
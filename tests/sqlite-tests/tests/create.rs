@@ -30,7 +30,9 @@ extern crate tql_macros;
 use chrono::DateTime;
 use chrono::naive::{NaiveDate, NaiveDateTime, NaiveTime};
 use chrono::offset::{Local, Utc};
-use tql::{ForeignKey, PrimaryKey};
+use std::error::Error;
+use std::net::IpAddr;
+use tql::{ForeignKey, PrimaryKey, SqlType};
 use tql_macros::to_sql;
 
 #[derive(SqlTable)]
@@ -75,6 +77,113 @@ struct OtherTypes {
     int64: i64,
 }
 
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct ExtendedTypes {
+    pk: PrimaryKey,
+    uint8: u8,
+    uint16: u16,
+    uint32: u32,
+    uint64: u64,
+    optional_uint64: Option<u64>,
+    address: IpAddr,
+}
+
+#[derive(Clone, Debug)]
+struct Email(String);
+
+impl SqlType for Email {
+    type Underlying = String;
+
+    fn to_sql_value(&self) -> String {
+        self.0.clone()
+    }
+
+    fn from_sql_value(value: String) -> Result<Self, Box<Error + Send + Sync>> {
+        Ok(Email(value))
+    }
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct UserTypes {
+    pk: PrimaryKey,
+    email: Email,
+    optional_email: Option<Email>,
+}
+
+#[derive(Clone, Debug, SqlEnum)]
+#[allow(dead_code)]
+enum Status {
+    Todo,
+    Done,
+}
+
+#[derive(Clone, Debug, SqlEnum)]
+#[allow(dead_code)]
+#[sql(storage = "integer")]
+enum Priority {
+    Low = 1,
+    High = 10,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Enums {
+    pk: PrimaryKey,
+    status: Status,
+    priority: Option<Priority>,
+}
+
+#[derive(SqlEmbed)]
+#[allow(dead_code)]
+struct Address {
+    street: String,
+    city: String,
+    zip: Option<i32>,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Customers {
+    pk: PrimaryKey,
+    name: String,
+    #[sql(embed)]
+    address: Address,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Posts {
+    pk: PrimaryKey,
+    title: String,
+    #[sql(auto_now_add)]
+    date_added: DateTime<Utc>,
+    #[sql(auto_now)]
+    date_updated: Option<NaiveDateTime>,
+    #[sql(auto_now_add)]
+    day: NaiveDate,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Items {
+    pk: PrimaryKey,
+    name: String,
+    #[sql(version)]
+    version: i64,
+}
+
+#[derive(SqlTable)]
+#[allow(dead_code)]
+struct Articles {
+    pk: PrimaryKey,
+    #[sql(index)]
+    title: String,
+    #[sql(index)]
+    author: Option<String>,
+}
+
 #[test]
 fn test_create() {
     assert_eq!(
@@ -93,4 +202,60 @@ fn test_create() {
         "CREATE TABLE OtherTypes (pk INTEGER PRIMARY KEY NOT NULL, boolean BOOLEAN NOT NULL, bytestring BYTEA NOT NULL, float64 DOUBLE PRECISION NOT NULL, int8 CHARACTER(1) NOT NULL, int16 SMALLINT NOT NULL, int32 INTEGER NOT NULL, int64 BIGINT NOT NULL)",
         to_sql!(OtherTypes.create())
     );
+    assert_eq!(
+        "CREATE TABLE ExtendedTypes (pk INTEGER PRIMARY KEY NOT NULL, uint8 SMALLINT NOT NULL, uint16 INTEGER NOT NULL, uint32 BIGINT NOT NULL, uint64 BIGINT NOT NULL, optional_uint64 BIGINT, address TEXT NOT NULL)",
+        to_sql!(ExtendedTypes.create())
+    );
+    assert_eq!(
+        "CREATE TABLE UserTypes (pk INTEGER PRIMARY KEY NOT NULL, email CHARACTER VARYING NOT NULL, optional_email CHARACTER VARYING)",
+        to_sql!(UserTypes.create())
+    );
+    assert_eq!(
+        "CREATE TABLE Enums (pk INTEGER PRIMARY KEY NOT NULL, status CHARACTER VARYING NOT NULL, priority INTEGER)",
+        to_sql!(Enums.create())
+    );
+    assert_eq!(
+        "CREATE TABLE Customers (pk INTEGER PRIMARY KEY NOT NULL, name CHARACTER VARYING NOT NULL, address_street CHARACTER VARYING NOT NULL, address_city CHARACTER VARYING NOT NULL, address_zip INTEGER)",
+        to_sql!(Customers.create())
+    );
+    assert_eq!(
+        "CREATE TABLE Posts (pk INTEGER PRIMARY KEY NOT NULL, title CHARACTER VARYING NOT NULL, date_added TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT (STRFTIME('%Y-%m-%dT%H:%M:%f+00:00', 'now')), date_updated TIMESTAMP DEFAULT (STRFTIME('%Y-%m-%dT%H:%M:%f', 'now')), day DATE NOT NULL DEFAULT CURRENT_DATE)",
+        to_sql!(Posts.create())
+    );
+    assert_eq!(
+        "CREATE TABLE Items (pk INTEGER PRIMARY KEY NOT NULL, name CHARACTER VARYING NOT NULL, version BIGINT NOT NULL DEFAULT 1)",
+        to_sql!(Items.create())
+    );
+}
+
+#[test]
+fn test_create_if_not_exists() {
+    assert_eq!(
+        "CREATE TABLE IF NOT EXISTS RelatedTable (id INTEGER PRIMARY KEY NOT NULL, field1 CHARACTER VARYING NOT NULL)",
+        to_sql!(RelatedTable.create_if_not_exists())
+    );
+    assert_eq!(
+        "CREATE TABLE IF NOT EXISTS Articles (pk INTEGER PRIMARY KEY NOT NULL, title CHARACTER VARYING NOT NULL, author CHARACTER VARYING); CREATE INDEX IF NOT EXISTS Articles_title_idx ON Articles (title); CREATE INDEX IF NOT EXISTS Articles_author_idx ON Articles (author)",
+        to_sql!(Articles.create_if_not_exists())
+    );
+}
+
+#[test]
+fn test_create_indexes() {
+    assert_eq!(
+        "CREATE TABLE Articles (pk INTEGER PRIMARY KEY NOT NULL, title CHARACTER VARYING NOT NULL, author CHARACTER VARYING); CREATE INDEX Articles_title_idx ON Articles (title); CREATE INDEX Articles_author_idx ON Articles (author)",
+        to_sql!(Articles.create())
+    );
+}
+
+#[test]
+fn test_drop() {
+    assert_eq!(
+        "DROP TABLE Table",
+        to_sql!(Table.drop())
+    );
+    assert_eq!(
+        "DROP TABLE IF EXISTS Table",
+        to_sql!(Table.drop_if_exists())
+    );
 }
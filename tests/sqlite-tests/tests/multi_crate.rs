@@ -0,0 +1,51 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+#![feature(proc_macro_hygiene)]
+
+extern crate sqlite_tests;
+extern crate tql_macros;
+
+use tql_macros::to_sql;
+
+#[test]
+fn test_multi_crate() {
+    assert_eq!(
+        "SELECT Book.id, Book.title, Book.version, Book.deleted_at FROM Book WHERE Book.deleted_at IS NULL",
+        to_sql!(sqlite_tests::Book.all())
+    );
+    assert_eq!(
+        "UPDATE Book SET title = 'Tql', version = version + 1 WHERE (Book.id = 1) AND Book.version = 2 AND Book.deleted_at IS NULL",
+        to_sql!(sqlite_tests::Book.get(1).update(title = "Tql", version = 2))
+    );
+    assert_eq!(
+        "UPDATE Book SET deleted_at = (STRFTIME('%Y-%m-%dT%H:%M:%f+00:00', 'now')) WHERE (Book.id = 1) AND Book.deleted_at IS NULL",
+        to_sql!(sqlite_tests::Book.get(1).delete())
+    );
+    assert_eq!(
+        "SELECT Book.id, Book.title, Book.version, Book.deleted_at, Author.id, Author.name, Author.address_street, Author.address_city FROM Book INNER JOIN Author ON Book.author = Author.id WHERE Book.deleted_at IS NULL",
+        to_sql!(sqlite_tests::Book.all().join(author))
+    );
+    assert_eq!(
+        "SELECT Author.id, Author.name, Author.address_street, Author.address_city FROM Author WHERE Author.address_city = 'Paris'",
+        to_sql!(sqlite_tests::Author.filter(address.city == "Paris"))
+    );
+}
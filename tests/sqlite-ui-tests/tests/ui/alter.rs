@@ -0,0 +1,73 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests of the columns added without a value for the existing rows, which SQLite cannot do.
+
+#![feature(proc_macro_hygiene)]
+
+extern crate rusqlite;
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+use rusqlite::Connection;
+use tql::{ForeignKey, PrimaryKey};
+use tql_macros::sql;
+
+pub fn get_connection() -> Connection {
+    Connection::open_in_memory().unwrap()
+}
+
+#[derive(SqlEmbed)]
+struct Address {
+    street: String,
+    zip: Option<i32>,
+}
+
+#[derive(SqlTable)]
+struct Table {
+    id: PrimaryKey,
+    field1: String,
+    field2: Option<i32>,
+    related_field: ForeignKey<RelatedTable>,
+    #[sql(embed)]
+    address: Address,
+    #[sql(version)]
+    version: i32,
+}
+
+#[derive(SqlTable)]
+struct RelatedTable {
+    id: PrimaryKey,
+}
+
+fn main() {
+    let connection = get_connection();
+
+    sql!(Table.add_column(field1));
+    //~^ ERROR cannot add the column of the field `field1` since it cannot be NULL and has no default value
+    sql!(Table.add_column(related_field));
+    //~^ ERROR cannot add the column of the field `related_field` since it cannot be NULL and has no default value
+    sql!(Table.add_column(address));
+    //~^ ERROR cannot add the column of the field `address` since it cannot be NULL and has no default value
+    sql!(Table.add_column(field2));
+    sql!(Table.add_column(version));
+}
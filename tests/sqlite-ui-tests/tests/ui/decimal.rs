@@ -0,0 +1,66 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+
+//! Tests of the decimal columns, which cannot be ordered on SQLite.
+
+#![feature(proc_macro_hygiene)]
+
+extern crate rusqlite;
+extern crate rust_decimal;
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+use rusqlite::Connection;
+use tql::PrimaryKey;
+use tql_macros::sql;
+
+pub fn get_connection() -> Connection {
+    Connection::open_in_memory().unwrap()
+}
+
+#[derive(SqlEmbed)]
+struct Price {
+    amount: rust_decimal::Decimal,
+    currency: String,
+}
+
+#[derive(SqlTable)]
+struct Table {
+    id: PrimaryKey,
+    amount: rust_decimal::Decimal,
+    discount: Option<rust_decimal::Decimal>,
+    #[sql(embed)]
+    price: Price,
+}
+
+fn main() {
+    let connection = get_connection();
+
+    sql!(Table.filter(amount > 10));
+    //~^ ERROR cannot order the decimal field `amount` because SQLite stores the decimal numbers as text
+    sql!(Table.filter(discount <= 1 || price.amount >= 10));
+    //~^ ERROR cannot order the decimal field `discount` because SQLite stores the decimal numbers as text
+    //~| ERROR cannot order the decimal field `amount` because SQLite stores the decimal numbers as text
+    sql!(Table.sort(-amount));
+    //~^ ERROR cannot sort by the decimal field `amount` because SQLite stores the decimal numbers as text
+}
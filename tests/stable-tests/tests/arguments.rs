@@ -0,0 +1,108 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests of the expressions used as query arguments on stable.
+
+extern crate chrono;
+extern crate rusqlite;
+#[macro_use]
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+use std::error::Error;
+
+use chrono::{DateTime, Duration};
+use chrono::offset::Utc;
+use rusqlite::Connection;
+use tql::{PrimaryKey, SqlType};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rating(i32);
+
+impl SqlType for Rating {
+    type Underlying = i32;
+
+    fn to_sql_value(&self) -> i32 {
+        self.0
+    }
+
+    fn from_sql_value(value: i32) -> Result<Self, Box<Error + Send + Sync>> {
+        Ok(Rating(value))
+    }
+}
+
+#[derive(SqlTable)]
+struct Post {
+    id: PrimaryKey,
+    title: String,
+    score: i32,
+    rating: Rating,
+    date_added: DateTime<Utc>,
+}
+
+struct Form {
+    title: String,
+    score: i32,
+}
+
+#[test]
+fn test_expression_arguments() {
+    let connection = Connection::open_in_memory().unwrap();
+    sql!(connection, Post.create()).unwrap();
+
+    let form = Form {
+        title: "first".to_string(),
+        score: 10,
+    };
+    let values = vec![41, 3];
+    let id1 = sql!(connection, Post.insert(title = form.title, score = values[0] + 1, rating = Rating(values[1]),
+        date_added = Utc::now())).unwrap();
+    let id2 = sql!(connection, Post.insert(title = format!("{} post", "second"), score = form.score,
+        rating = Rating(5), date_added = Utc::now() - Duration::days(2))).unwrap();
+
+    let post = sql!(connection, Post.get(id1)).unwrap();
+    assert_eq!("first", post.title);
+    assert_eq!(42, post.score);
+    assert_eq!(Rating(3), post.rating);
+
+    let post = sql!(connection, Post.get(id2)).unwrap();
+    assert_eq!("second post", post.title);
+    assert_eq!(10, post.score);
+    assert_eq!(Rating(5), post.rating);
+
+    let posts = sql!(connection, Post.filter(title == format!("{} post", "second"))).unwrap();
+    assert_eq!(1, posts.len());
+    assert_eq!(id2, posts[0].id);
+
+    let posts = sql!(connection, Post.filter(date_added > Utc::now() - Duration::days(1))).unwrap();
+    assert_eq!(1, posts.len());
+    assert_eq!(id1, posts[0].id);
+
+    let posts = sql!(connection, Post.filter(score > values[1] * 5 && rating == Rating(values[1]))).unwrap();
+    assert_eq!(1, posts.len());
+    assert_eq!(id1, posts[0].id);
+
+    let num_updated = sql!(connection, Post.get(id2).update(score = values.len() as i32 + form.score)).unwrap();
+    assert_eq!(1, num_updated);
+    let post = sql!(connection, Post.get(id2)).unwrap();
+    assert_eq!(12, post.score);
+}
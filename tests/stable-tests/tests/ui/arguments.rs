@@ -0,0 +1,57 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests of the type errors in the expressions used as query arguments on stable.
+
+extern crate rusqlite;
+#[macro_use]
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+use rusqlite::Connection;
+use tql::PrimaryKey;
+
+#[derive(SqlTable)]
+struct Table {
+    id: PrimaryKey,
+    field1: String,
+    i32_field: i32,
+}
+
+struct Form {
+    title: String,
+    score: i64,
+}
+
+fn main() {
+    let connection = Connection::open_in_memory().unwrap();
+    let form = Form {
+        title: "title".to_string(),
+        score: 42,
+    };
+
+    let _ = sql!(connection, Table.insert(field1 = form.title, i32_field = form.score));
+    //~^ ERROR mismatched types
+
+    let _ = sql!(connection, Table.filter(i32_field > form.score));
+    //~^ ERROR mismatched types
+}
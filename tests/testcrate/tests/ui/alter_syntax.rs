@@ -0,0 +1,68 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests of the syntax of the methods related to `Query::AlterTable`.
+
+#![feature(proc_macro_hygiene)]
+
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+#[macro_use]
+mod connection;
+backend_extern_crate!();
+
+use tql::PrimaryKey;
+use tql_macros::sql;
+
+use connection::{Connection, get_connection};
+
+#[derive(SqlTable)]
+struct Table {
+    id: PrimaryKey,
+    field1: String,
+    field2: Option<i32>,
+}
+
+fn main() {
+    let connection = get_connection();
+
+    let _ = sql!(Table.add_column());
+    //~^ ERROR this method takes 1 parameter but 0 parameters were supplied
+
+    let _ = sql!(Table.drop_column(field1));
+    //~^ ERROR cannot drop the column `field1` since it is still used by the struct `Table`
+    //~| HELP remove the field from the struct before altering the table
+
+    let _ = sql!(Table.rename_column(field1, field2));
+    //~^ ERROR cannot rename the column `field1` since it is still used by the struct `Table`
+    //~| HELP rename the field in the struct before altering the table
+
+    let _ = sql!(Table.rename_column(field3));
+    //~^ ERROR this method takes 2 parameters but 1 parameter was supplied
+
+    let _ = sql!(Table.rename_to(Table));
+    //~^ ERROR cannot rename the table `Table` to itself
+
+    let _ = sql!(Table.add_column(field2).drop_column(field3));
+    //~^ ERROR cannot call the drop_column() method with the add_column() method
+}
@@ -0,0 +1,75 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests of the `#[derive(SqlEmbed)]` attribute.
+
+#![feature(proc_macro_hygiene)]
+
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+#[macro_use]
+mod connection;
+backend_extern_crate!();
+
+use tql::PrimaryKey;
+use tql_macros::sql;
+
+use connection::{Connection, get_connection};
+
+#[derive(Clone, SqlEmbed)]
+struct Address {
+    street: String,
+    city: String,
+}
+
+#[derive(SqlEmbed)]
+struct Location {
+    id: PrimaryKey,
+    //~^ ERROR primary keys and foreign keys cannot be embedded
+    #[sql(embed)]
+    address: Address,
+    //~^ ERROR nested embedded structs are not supported
+}
+
+#[derive(SqlTable)]
+struct Table {
+    id: PrimaryKey,
+    #[sql(embed)]
+    address: Address,
+}
+
+fn main() {
+    let connection = get_connection();
+    let address = Address {
+        street: "1 Main Street".to_string(),
+        city: "Paris".to_string(),
+    };
+    let _ = sql!(Table.filter(address == address));
+    //~^ ERROR cannot compare the embedded field `address`, compare one of its fields instead
+
+    let _ = sql!(Table.get(1).update(address += address));
+    //~^ ERROR expected = but got +=
+
+    let _ = sql!(Table.filter(address.country == "France"));
+    //~^ ERROR no field `country` on type `Address`
+}
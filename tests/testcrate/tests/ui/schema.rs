@@ -0,0 +1,84 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests of the `#[sql(schema = "…")]` attribute.
+
+#![feature(proc_macro_hygiene)]
+
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+#[macro_use]
+mod connection;
+backend_extern_crate!();
+
+use tql::{ForeignKey, PrimaryKey};
+
+#[derive(SqlTable)]
+#[sql(schema = "tests/ui/schema/schema.tql")]
+struct Author {
+    id: PrimaryKey,
+    name: String,
+    bio: Option<String>,
+}
+
+#[derive(SqlTable)]
+#[sql(schema = "tests/ui/schema/schema.tql")]
+struct Post {
+    //~^ ERROR missing field for the column `body` of the table `post` in the schema `tests/ui/schema/schema.tql`
+    id: PrimaryKey,
+    author: ForeignKey<Author>,
+    title: i32,
+    //~^ ERROR mismatched types: expected `INTEGER` for the column `title`, found `CHARACTER VARYING` in the schema `tests/ui/schema/schema.tql`
+    published: bool,
+    //~^ ERROR mismatched types: the column `published` is nullable in the schema `tests/ui/schema/schema.tql`, expected `Option<…>`
+    views: Option<i64>,
+    //~^ ERROR mismatched types: the column `views` is NOT NULL in the schema `tests/ui/schema/schema.tql`, expected a non-optional type
+    subtitle: String,
+    //~^ ERROR column `subtitle` not found in the table `post` of the schema `tests/ui/schema/schema.tql`
+}
+
+#[derive(SqlTable)]
+#[sql(schema = "tests/ui/schema/schema.tql")]
+struct Tag {
+    id: PrimaryKey,
+    post: ForeignKey<Author>,
+    //~^ ERROR mismatched types: expected `REFERENCES Author` for the column `post`, found `INTEGER REFERENCES post(id)` in the schema `tests/ui/schema/schema.tql`
+    name: String,
+}
+
+#[derive(SqlTable)]
+#[sql(schema = "tests/ui/schema/schema.tql")]
+struct Comment {
+    //~^ ERROR table `Comment` not found in the schema `tests/ui/schema/schema.tql`
+    id: PrimaryKey,
+}
+
+#[derive(SqlTable)]
+#[sql(schema = "tests/ui/schema/missing.tql")]
+//~^ ERROR cannot read the schema `tests/ui/schema/missing.tql`: No such file or directory (os error 2)
+struct Other {
+    id: PrimaryKey,
+}
+
+fn main() {
+}
@@ -0,0 +1,71 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests of the `#[sql(soft_delete)]` attribute.
+
+#![feature(proc_macro_hygiene)]
+
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+#[macro_use]
+mod connection;
+backend_extern_crate!();
+
+use tql::PrimaryKey;
+use tql_macros::sql;
+
+use connection::{Connection, get_connection};
+
+#[derive(SqlTable)]
+#[sql(soft_delete)]
+struct Table {
+    //~^ ERROR #[sql(soft_delete)] requires a `deleted_at: Option<DateTime<Utc>>` field
+    id: PrimaryKey,
+}
+
+#[derive(SqlTable)]
+#[sql(soft_delete)]
+struct OtherTable {
+    id: PrimaryKey,
+    deleted_at: Option<i32>,
+    //~^ ERROR mismatched types: expected `Option<DateTime<Utc>>` for the `deleted_at` field of a soft-deleted table
+}
+
+#[derive(SqlTable)]
+#[sql(soft_deleted)]
+//~^ ERROR unknown argument, expected `schema = "…"` or `soft_delete`
+struct Item {
+    id: PrimaryKey,
+}
+
+fn main() {
+    let connection = get_connection();
+    let _ = sql!(Item.with_deleted());
+    //~^ ERROR the with_deleted() method can only be called on a table with #[sql(soft_delete)], but `Item` has none
+
+    let _ = sql!(Item.get(1).hard_delete());
+    //~^ ERROR the hard_delete() method can only be called on a table with #[sql(soft_delete)], but `Item` has none
+
+    let _ = sql!(OtherTable.get(1).with_deleted().delete());
+    //~^ ERROR cannot call the with_deleted() method with the delete() method
+}
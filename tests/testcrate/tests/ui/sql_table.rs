@@ -33,18 +33,11 @@ backend_extern_crate!();
 
 use tql::{DateTime,ForeignKey};
 
-struct Connection {
-    value: String,
-}
 #[derive(SqlTable)]
 struct Table<'a> {
     //~^ WARNING No primary key found
     string: &'a str,
     //~^ ERROR use of unsupported type name `& 'a str`
-    connection: Connection,
-    //~^ ERROR use of unsupported type name `Connection`
-    connection2: Option<Connection>,
-    //~^ ERROR use of unsupported type name `Connection`
     nested_options: Option<Option<String>>,
     //~^ ERROR use of unsupported type name `Option<String>`
     datetime: DateTime,
@@ -57,8 +50,8 @@ struct Table<'a> {
     //~^ ERROR use of unsupported type name `Option`
     vector: Vec,
     //~^ ERROR use of unsupported type name `Vec`
-    vector_i32: Vec<i32>,
-    //~^ ERROR use of unsupported type name `Vec<i32>`
+    vector_u32: Vec<u32>,
+    //~^ ERROR use of unsupported type name `Vec<u32>`
 }
 
 fn main() {
@@ -47,6 +47,10 @@ struct Table {
     // ~^ ERROR `Connection` does not name an SQL table
     // ~| HELP did you forget to add the #[derive(SqlTable)] attribute on the Connection struct?
     related_field2: ForeignKey<RelatedTable>,
+    connection: Connection,
+    //~^ ERROR the trait bound `Connection: tql::SqlType` is not satisfied
+    connection2: Option<Connection>,
+    //~^ ERROR the trait bound `Connection: tql::SqlType` is not satisfied
 }
 
 #[derive(SqlTable)]
@@ -0,0 +1,72 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests of the `#[sql(version)]` attribute.
+
+#![feature(proc_macro_hygiene)]
+
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+#[macro_use]
+mod connection;
+backend_extern_crate!();
+
+use tql::PrimaryKey;
+use tql_macros::sql;
+
+use connection::{Connection, get_connection};
+
+#[derive(SqlTable)]
+struct Table {
+    id: PrimaryKey,
+    #[sql(version)]
+    version: String,
+    //~^ ERROR mismatched types: expected `i16`, `i32` or `i64` for a #[sql(version)] field
+}
+
+#[derive(SqlTable)]
+struct OtherTable {
+    id: PrimaryKey,
+    #[sql(version)]
+    version: i32,
+    #[sql(version)]
+    revision: i32,
+    //~^ ERROR a table can only have one #[sql(version)] field
+}
+
+#[derive(SqlTable)]
+struct Item {
+    id: PrimaryKey,
+    title: String,
+    #[sql(version)]
+    version: i32,
+}
+
+fn main() {
+    let connection = get_connection();
+    let _ = sql!(Item.get(1).update(title = "Title"));
+    //~^ ERROR missing the current version of the row in update(): add `version = …`
+
+    let _ = sql!(Item.get(1).update(title = "Title", version += 1));
+    //~^ ERROR the `version` field is incremented automatically, assign the current version with `version = …`
+}
@@ -21,6 +21,7 @@
 
 #![feature(proc_macro_hygiene)]
 
+extern crate chrono;
 #[macro_use]
 extern crate lazy_static;
 extern crate tql;
@@ -35,7 +36,9 @@ backend_extern_crate!();
 
 use std::sync::Mutex;
 
-use tql::{ForeignKey, PrimaryKey};
+use chrono::{DateTime, Duration};
+use chrono::offset::Utc;
+use tql::{ForeignKey, PrimaryKey, is_stale_object};
 use tql_macros::sql;
 
 use connection::{Connection, get_connection};
@@ -58,6 +61,37 @@ struct RelatedTable {
     field1: String,
 }
 
+#[derive(Clone, SqlEmbed)]
+struct AddressUpdateExpr {
+    street: String,
+    city: String,
+}
+
+#[derive(SqlTable)]
+struct EmbedUpdateExpr {
+    id: PrimaryKey,
+    #[sql(embed)]
+    address: AddressUpdateExpr,
+}
+
+#[derive(SqlTable)]
+struct AutoTimeUpdateExpr {
+    id: PrimaryKey,
+    title: String,
+    #[sql(auto_now_add)]
+    date_added: DateTime<Utc>,
+    #[sql(auto_now)]
+    date_updated: DateTime<Utc>,
+}
+
+#[derive(SqlTable)]
+struct VersionUpdateExpr {
+    id: PrimaryKey,
+    title: String,
+    #[sql(version)]
+    version: i32,
+}
+
 lazy_static! {
     static ref LOCK: Mutex<Connection> = Mutex::new(get_connection());
 }
@@ -193,3 +227,129 @@ fn test_update_operation() {
     assert_eq!(0, table.field2);
     assert_eq!(3, table.field3);
 }
+
+#[test]
+fn test_update_embed() {
+    let connection = LOCK.lock().unwrap();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(EmbedUpdateExpr.drop());
+    });
+
+    let _ = sql!(EmbedUpdateExpr.create());
+
+    let address = AddressUpdateExpr {
+        street: "1 Main Street".to_string(),
+        city: "Paris".to_string(),
+    };
+    let id = sql!(EmbedUpdateExpr.insert(address = address)).unwrap();
+
+    let address = AddressUpdateExpr {
+        street: "2 Main Street".to_string(),
+        city: "Montreal".to_string(),
+    };
+    let num_updated = sql!(EmbedUpdateExpr.filter(address.city == "Paris").update(address = address)).unwrap();
+    assert_eq!(1, num_updated);
+
+    let table = sql!(EmbedUpdateExpr.get(id)).unwrap();
+    assert_eq!("2 Main Street", table.address.street);
+    assert_eq!("Montreal", table.address.city);
+}
+
+#[test]
+fn test_update_auto_now() {
+    let connection = LOCK.lock().unwrap();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(AutoTimeUpdateExpr.drop());
+    });
+
+    let _ = sql!(AutoTimeUpdateExpr.create());
+
+    let id = sql!(AutoTimeUpdateExpr.insert(title = "Draft")).unwrap();
+
+    let table = sql!(AutoTimeUpdateExpr.get(id)).unwrap();
+    assert_eq!(table.date_added, table.date_updated);
+    let date_added = table.date_added;
+    let date_updated = table.date_updated;
+
+    let num_updated = sql!(AutoTimeUpdateExpr.get(id).update(title = "Published")).unwrap();
+    assert_eq!(1, num_updated);
+
+    let table = sql!(AutoTimeUpdateExpr.get(id)).unwrap();
+    assert_eq!("Published", table.title);
+    assert_eq!(date_added, table.date_added);
+    assert!(table.date_updated >= date_updated);
+}
+
+#[test]
+fn test_auto_now_order() {
+    let connection = LOCK.lock().unwrap();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(AutoTimeUpdateExpr.drop());
+    });
+
+    let _ = sql!(AutoTimeUpdateExpr.create());
+
+    // NOTE: the dates set by the database are compared with the dates written from Rust.
+    let before = Utc::now() - Duration::minutes(1);
+    let after = Utc::now() + Duration::minutes(1);
+    let id_after = sql!(AutoTimeUpdateExpr.insert(title = "After", date_added = after, date_updated = after)).unwrap();
+    let id = sql!(AutoTimeUpdateExpr.insert(title = "Now")).unwrap();
+    let id_before = sql!(AutoTimeUpdateExpr.insert(title = "Before", date_added = before, date_updated = before))
+        .unwrap();
+
+    let table = sql!(AutoTimeUpdateExpr.get(id)).unwrap();
+    assert!(before < table.date_added && table.date_added < after);
+
+    let tables = sql!(AutoTimeUpdateExpr.sort(date_added)).unwrap();
+    let ids: Vec<_> = tables.iter().map(|table| table.id).collect();
+    assert_eq!(vec![id_before, id, id_after], ids);
+
+    let tables = sql!(AutoTimeUpdateExpr.filter(date_added > before && date_added < after)).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(id, tables[0].id);
+
+    let num_updated = sql!(AutoTimeUpdateExpr.get(id_before).update(title = "Updated")).unwrap();
+    assert_eq!(1, num_updated);
+
+    let table = sql!(AutoTimeUpdateExpr.get(id_before)).unwrap();
+    assert_eq!(before, table.date_added);
+    assert!(before < table.date_updated && table.date_updated < after);
+
+    let tables = sql!(AutoTimeUpdateExpr.filter(date_updated > before).sort(-date_updated)).unwrap();
+    assert_eq!(3, tables.len());
+    assert_eq!(id_after, tables[0].id);
+}
+
+#[test]
+fn test_update_version() {
+    let connection = LOCK.lock().unwrap();
+
+    let _teardown = TearDown::new(|| {
+        let _ = sql!(VersionUpdateExpr.drop());
+    });
+
+    let _ = sql!(VersionUpdateExpr.create());
+
+    let id = sql!(VersionUpdateExpr.insert(title = "Draft")).unwrap();
+
+    let table = sql!(VersionUpdateExpr.get(id)).unwrap();
+    assert_eq!(1, table.version);
+    let version = table.version;
+
+    let num_updated = sql!(VersionUpdateExpr.get(id).update(title = "Published", version = version)).unwrap();
+    assert_eq!(1, num_updated);
+
+    let table = sql!(VersionUpdateExpr.get(id)).unwrap();
+    assert_eq!("Published", table.title);
+    assert_eq!(2, table.version);
+
+    let result = sql!(VersionUpdateExpr.get(id).update(title = "Edited", version = version));
+    assert!(is_stale_object(&result.unwrap_err()));
+
+    let table = sql!(VersionUpdateExpr.get(id)).unwrap();
+    assert_eq!("Published", table.title);
+    assert_eq!(2, table.version);
+}
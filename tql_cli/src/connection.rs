@@ -0,0 +1,120 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! The connection to the database on which the migrations are applied.
+
+use postgres::{self, TlsMode};
+use rusqlite;
+use tql::migration::{Backend, Database, Result};
+
+const SQLITE_SCHEME: &str = "sqlite://";
+
+/// A connection to either a PostgreSQL or a SQLite database.
+pub enum Connection {
+    Postgres(postgres::Connection),
+    Sqlite(rusqlite::Connection),
+}
+
+impl Connection {
+    /// Connect to the database at `url`: a `postgres://` URL or the path of a SQLite file
+    /// (optionally prefixed by `sqlite://`).
+    pub fn open(url: &str) -> Result<Self> {
+        match backend(url) {
+            Backend::Postgres => Ok(Connection::Postgres(postgres::Connection::connect(url, TlsMode::None)?)),
+            Backend::Sqlite => {
+                let path =
+                    if url.starts_with(SQLITE_SCHEME) {
+                        &url[SQLITE_SCHEME.len()..]
+                    }
+                    else {
+                        url
+                    };
+                Ok(Connection::Sqlite(rusqlite::Connection::open(path)?))
+            },
+        }
+    }
+}
+
+impl Database for Connection {
+    fn backend(&self) -> Backend {
+        match *self {
+            Connection::Postgres(_) => Backend::Postgres,
+            Connection::Sqlite(_) => Backend::Sqlite,
+        }
+    }
+
+    fn execute(&self, query: &str) -> Result<()> {
+        match *self {
+            Connection::Postgres(ref connection) => connection.batch_execute(query)?,
+            Connection::Sqlite(ref connection) => connection.execute_batch(query)?,
+        }
+        Ok(())
+    }
+
+    fn query_strings(&self, query: &str) -> Result<Vec<String>> {
+        match *self {
+            Connection::Postgres(ref connection) => {
+                let rows = connection.query(query, &[])?;
+                Ok(rows.iter().map(|row| row.get(0)).collect())
+            },
+            Connection::Sqlite(ref connection) => {
+                let mut statement = connection.prepare(query)?;
+                let params: &[&rusqlite::types::ToSql] = &[];
+                let rows = statement.query_map(params, |row| row.get(0))?;
+                let mut strings = vec![];
+                for row in rows {
+                    strings.push(row?);
+                }
+                Ok(strings)
+            },
+        }
+    }
+
+    fn query_rows(&self, query: &str) -> Result<Vec<Vec<Option<String>>>> {
+        match *self {
+            Connection::Postgres(ref connection) => {
+                let rows = connection.query(query, &[])?;
+                Ok(rows.iter().map(|row| (0..row.len()).map(|index| row.get(index)).collect()).collect())
+            },
+            Connection::Sqlite(ref connection) => {
+                let mut statement = connection.prepare(query)?;
+                let count = statement.column_count();
+                let params: &[&rusqlite::types::ToSql] = &[];
+                let rows = statement.query_map(params, |row| (0..count).map(|index| row.get(index)).collect())?;
+                let mut values = vec![];
+                for row in rows {
+                    values.push(row?);
+                }
+                Ok(values)
+            },
+        }
+    }
+}
+
+/// Get the backend of the database at `url`.
+pub fn backend(url: &str) -> Backend {
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        Backend::Postgres
+    }
+    else {
+        Backend::Sqlite
+    }
+}
@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! The generation of the models of the tables of an existing database.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use tql::migration::{Column, Schema, TableSchema};
+
+/// The Rust keywords, which cannot be the name of a field.
+const KEYWORDS: &[&str] = &[
+    "Self", "abstract", "as", "become", "box", "break", "const", "continue", "crate", "do", "else", "enum", "extern",
+    "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut",
+    "override", "priv", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// The source code of the models of the tables.
+pub struct Models {
+    pub source: String,
+    /// The columns and indexes which cannot be represented in the models.
+    pub warnings: Vec<String>,
+}
+
+/// Generate the structs deriving `SqlTable` for the tables of the `schema`.
+pub fn models(schema: &Schema) -> Models {
+    let mut imports = BTreeMap::new();
+    let mut structs = vec![];
+    let mut warnings = vec![];
+    for table in &schema.tables {
+        structs.push(table_struct(table, &mut imports, &mut warnings));
+    }
+    let mut source = String::new();
+    for (module, items) in &imports {
+        let items: Vec<_> = items.iter().cloned().collect();
+        if items.len() == 1 {
+            source.push_str(&format!("use {}::{};\n", module, items[0]));
+        }
+        else {
+            source.push_str(&format!("use {}::{{{}}};\n", module, items.join(", ")));
+        }
+    }
+    if !imports.is_empty() {
+        source.push('\n');
+    }
+    source.push_str(&structs.join("\n"));
+    Models {
+        source,
+        warnings,
+    }
+}
+
+type Imports = BTreeMap<&'static str, BTreeSet<&'static str>>;
+
+/// Generate the struct of the `table`.
+fn table_struct(table: &TableSchema, imports: &mut Imports, warnings: &mut Vec<String>) -> String {
+    let name = struct_name(&table.name);
+    let mut source = String::from("#[derive(SqlTable)]\n");
+    if name.contains('_') {
+        source.push_str("#[allow(non_camel_case_types)]\n");
+    }
+    source.push_str(&format!("struct {} {{\n", name));
+    let primary_key_count = table.columns.iter()
+        .filter(|column| column.type_and_constraint().1 == "PRIMARY KEY")
+        .count();
+    for column in &table.columns {
+        let typ =
+            if KEYWORDS.contains(&column.name.as_str()) {
+                Err("the name is a Rust keyword".to_string())
+            }
+            else {
+                field_type(column, primary_key_count == 1, imports)
+            };
+        match typ {
+            Ok(typ) => {
+                // NOTE: a foreign key column is created with NOT NULL.
+                if column.referenced_table().is_some() && column.nullable {
+                    warnings.push(format!("column {}.{}: a foreign key cannot be NULL", table.name, column.name));
+                }
+                let is_indexed = table.indexes.iter()
+                    .any(|index| index.columns == [column.name.clone()]);
+                if is_indexed {
+                    source.push_str("    #[sql(index)]\n");
+                }
+                source.push_str(&format!("    {}: {},\n", column.name, typ));
+            },
+            Err(reason) => {
+                source.push_str(&format!("    // FIXME: column {}: {}.\n", column.name, reason));
+                warnings.push(format!("column {}.{}: {}", table.name, column.name, reason));
+            },
+        }
+    }
+    for index in table.indexes.iter().filter(|index| index.columns.len() > 1) {
+        warnings.push(format!("index {} on {}: only the indexes on a single column are supported", index.name,
+                              table.name));
+    }
+    source.push_str("}\n");
+    source
+}
+
+/// Get the name of the struct of the `table`.
+///
+/// Since the table names are not case-sensitive, only the first letter is changed.
+fn struct_name(table: &str) -> String {
+    let mut chars = table.chars();
+    match chars.next() {
+        Some(first_char) => first_char.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Get the type of the field storing the `column`, or the reason why it is not supported.
+fn field_type(column: &Column, has_single_primary_key: bool, imports: &mut Imports) -> Result<String, String> {
+    let (sql_type, constraint) = column.type_and_constraint();
+    let is_integer = rust_type(sql_type).map(|(typ, _)| typ) == Some("i32");
+    if constraint == "PRIMARY KEY" && has_single_primary_key && is_integer {
+        add_import(imports, "tql::PrimaryKey");
+        return Ok("PrimaryKey".to_string());
+    }
+    if let Some(table) = column.referenced_table() {
+        add_import(imports, "tql::ForeignKey");
+        return Ok(format!("ForeignKey<{}>", struct_name(table)));
+    }
+
+    let is_array = sql_type.ends_with("[]");
+    let element_type =
+        if is_array {
+            &sql_type[..sql_type.len() - 2]
+        }
+        else {
+            sql_type
+        };
+    let (typ, type_imports) = rust_type(element_type)
+        .ok_or_else(|| format!("unsupported type {}", sql_type))?;
+    for import in type_imports {
+        add_import(imports, import);
+    }
+    let typ =
+        if is_array {
+            format!("Vec<{}>", typ)
+        }
+        else {
+            typ.to_string()
+        };
+    if column.nullable {
+        Ok(format!("Option<{}>", typ))
+    }
+    else {
+        Ok(typ)
+    }
+}
+
+/// Get the Rust type of the `sql_type`, with the items to import.
+fn rust_type(sql_type: &str) -> Option<(&'static str, Vec<&'static str>)> {
+    // NOTE: the parameters of the type (e.g. the length in VARCHAR(255)) are ignored.
+    let sql_type = sql_type.split('(').next().unwrap_or(sql_type).trim();
+    let typ =
+        match sql_type {
+            "BIGINT" | "BIGSERIAL" | "INT8" => ("i64", vec![]),
+            "BLOB" | "BYTEA" => ("Vec<u8>", vec![]),
+            "BOOL" | "BOOLEAN" => ("bool", vec![]),
+            "CHAR" | "CHARACTER" | "CHARACTER VARYING" | "TEXT" | "VARCHAR" => ("String", vec![]),
+            "DATE" => ("NaiveDate", vec!["chrono::NaiveDate"]),
+            "DATETIME" | "TIMESTAMP" => ("NaiveDateTime", vec!["chrono::NaiveDateTime"]),
+            "DECIMAL" | "NUMERIC" => ("rust_decimal::Decimal", vec![]),
+            "DOUBLE" | "DOUBLE PRECISION" | "FLOAT" | "FLOAT8" => ("f64", vec![]),
+            "FLOAT4" | "REAL" => ("f32", vec![]),
+            "INET" => ("IpAddr", vec!["std::net::IpAddr"]),
+            "INT" | "INT4" | "INTEGER" | "SERIAL" => ("i32", vec![]),
+            "INT2" | "SMALLINT" | "SMALLSERIAL" => ("i16", vec![]),
+            "JSON" | "JSONB" => ("serde_json::Value", vec![]),
+            "TIME" => ("NaiveTime", vec!["chrono::NaiveTime"]),
+            "TIMESTAMP WITH TIME ZONE" | "TIMESTAMPTZ" => ("DateTime<Utc>", vec!["chrono::DateTime", "chrono::Utc"]),
+            "UUID" => ("uuid::Uuid", vec![]),
+            _ => return None,
+        };
+    Some(typ)
+}
+
+/// Add the item at `path` to the `imports`.
+fn add_import(imports: &mut Imports, path: &'static str) {
+    let separator = path.rfind("::").expect("module separator");
+    imports.entry(&path[..separator])
+        .or_insert_with(BTreeSet::new)
+        .insert(&path[separator + 2..]);
+}
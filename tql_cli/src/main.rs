@@ -0,0 +1,311 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Command-line tool managing the migrations of tql.
+
+extern crate postgres;
+extern crate rusqlite;
+extern crate tql;
+
+mod connection;
+mod inspect;
+mod migrations;
+mod snapshot;
+
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use tql::migration::{Migration, Rename, Result, Schema, applied_migrations, migrate, rollback};
+
+use connection::{Connection, backend};
+use inspect::models;
+use migrations::{
+    last_schema,
+    migration_names,
+    next_migration_name,
+    read_file,
+    read_migrations,
+    write_file,
+    write_migration,
+};
+use snapshot::model_schema;
+
+const USAGE: &str = "Usage: tql <command> [options]
+
+Commands:
+    dumpschema [FILE]       Write the snapshot of the schema of the database (default: db_schema.tql)
+    inspectdb               Print the models of the tables of the database
+    makemigrations [NAME]   Create a migration from the changes in the schema of the models
+    migrate                 Apply the migrations that are not applied yet
+    rollback                Revert the last applied migration
+    showmigrations          List the migrations and whether they are applied
+    sqlmigrate NAME         Print the SQL queries of a migration
+
+Options:
+    --database URL          PostgreSQL URL or SQLite file (default: $DATABASE_URL)
+    --migrations DIR        Directory of the migrations (default: migrations)
+    --schema FILE           Snapshot of the schema of the models (default: generated by running the
+                            tests of the crate in the current directory)
+    --rename TABLE.OLD=NEW  Rename a column instead of dropping and creating it (makemigrations)
+    --backwards             Print the queries reverting the migration (sqlmigrate)";
+
+/// The default file of the snapshot of the database: it is not called `schema.tql` like the
+/// snapshots of the migrations to avoid mistaking one for the other.
+const DUMP_FILE: &str = "db_schema.tql";
+
+enum Command {
+    DumpSchema(Option<String>),
+    InspectDb,
+    MakeMigrations(Option<String>),
+    Migrate,
+    Rollback,
+    ShowMigrations,
+    SqlMigrate(String),
+}
+
+struct Options {
+    backwards: bool,
+    database: Option<String>,
+    migrations: PathBuf,
+    renames: Vec<Rename>,
+    schema: Option<PathBuf>,
+}
+
+impl Options {
+    fn database(&self) -> Result<&str> {
+        self.database.as_ref()
+            .map(String::as_str)
+            .ok_or_else(|| "missing database: use --database URL or set DATABASE_URL".into())
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        Connection::open(self.database()?)
+    }
+}
+
+fn main() {
+    let result = parse_arguments(env::args().skip(1).collect())
+        .and_then(|(command, options)| run(command, &options));
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn parse_arguments(arguments: Vec<String>) -> Result<(Command, Options)> {
+    let mut options = Options {
+        backwards: false,
+        database: env::var("DATABASE_URL").ok(),
+        migrations: PathBuf::from("migrations"),
+        renames: vec![],
+        schema: None,
+    };
+    let mut positional = vec![];
+    let mut arguments = arguments.into_iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--backwards" => options.backwards = true,
+            "--database" | "--migrations" | "--rename" | "--schema" => {
+                let value = arguments.next()
+                    .ok_or_else(|| format!("missing value for {}", argument))?;
+                match argument.as_str() {
+                    "--database" => options.database = Some(value),
+                    "--migrations" => options.migrations = PathBuf::from(value),
+                    "--rename" => options.renames.push(parse_rename(&value)?),
+                    _ => options.schema = Some(PathBuf::from(value)),
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            _ if argument.starts_with('-') => return Err(format!("unknown option {}\n\n{}", argument, USAGE).into()),
+            _ => positional.push(argument),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let command =
+        match positional.next().as_ref().map(String::as_str) {
+            Some("dumpschema") => Command::DumpSchema(positional.next()),
+            Some("inspectdb") => Command::InspectDb,
+            Some("makemigrations") => Command::MakeMigrations(positional.next()),
+            Some("migrate") => Command::Migrate,
+            Some("rollback") => Command::Rollback,
+            Some("showmigrations") => Command::ShowMigrations,
+            Some("sqlmigrate") => {
+                let name = positional.next()
+                    .ok_or_else(|| format!("missing migration name\n\n{}", USAGE))?;
+                Command::SqlMigrate(name)
+            },
+            Some(command) => return Err(format!("unknown command {}\n\n{}", command, USAGE).into()),
+            None => return Err(USAGE.into()),
+        };
+    if let Some(argument) = positional.next() {
+        return Err(format!("unexpected argument {}\n\n{}", argument, USAGE).into());
+    }
+    Ok((command, options))
+}
+
+/// Parse a rename written as `TABLE.OLD=NEW`.
+fn parse_rename(value: &str) -> Result<Rename> {
+    let invalid_rename = || format!("invalid rename {}: expected TABLE.OLD=NEW", value);
+    let mut parts = value.splitn(2, '=');
+    let column = parts.next().ok_or_else(invalid_rename)?;
+    let new_name = parts.next().ok_or_else(invalid_rename)?;
+    let mut parts = column.splitn(2, '.');
+    let table = parts.next().ok_or_else(invalid_rename)?;
+    let old_name = parts.next().ok_or_else(invalid_rename)?;
+    if table.is_empty() || old_name.is_empty() || new_name.is_empty() {
+        return Err(invalid_rename().into());
+    }
+    Ok(Rename {
+        table: table.to_string(),
+        old_name: old_name.to_string(),
+        new_name: new_name.to_string(),
+    })
+}
+
+fn run(command: Command, options: &Options) -> Result<()> {
+    match command {
+        Command::DumpSchema(file) => {
+            let connection = options.connect()?;
+            let path = PathBuf::from(file.as_ref().map_or(DUMP_FILE, String::as_str));
+            write_file(&path, &Schema::from_database(&connection)?.to_snapshot())?;
+            println!("Wrote {}", path.display());
+            Ok(())
+        },
+        Command::InspectDb => {
+            let connection = options.connect()?;
+            let models = models(&Schema::from_database(&connection)?);
+            print!("{}", models.source);
+            for warning in models.warnings {
+                eprintln!("warning: {}", warning);
+            }
+            Ok(())
+        },
+        Command::MakeMigrations(name) => make_migrations(name, options),
+        Command::Migrate => {
+            let connection = options.connect()?;
+            let applied = migrate(&connection, &read_migrations(&options.migrations)?)?;
+            if applied.is_empty() {
+                println!("No migrations to apply.");
+            }
+            for name in applied {
+                println!("Applied {}", name);
+            }
+            Ok(())
+        },
+        Command::Rollback => {
+            let connection = options.connect()?;
+            let name =
+                match applied_migrations(&connection)?.pop() {
+                    Some(name) => name,
+                    None => {
+                        println!("No migrations to rollback.");
+                        return Ok(());
+                    },
+                };
+            let migration = find_migration(options, &name)?;
+            rollback(&connection, &migration)?;
+            println!("Reverted {}", name);
+            Ok(())
+        },
+        Command::ShowMigrations => {
+            let connection = options.connect()?;
+            let applied = applied_migrations(&connection)?;
+            for name in migration_names(&options.migrations)? {
+                let mark = if applied.contains(&name) { "X" } else { " " };
+                println!("[{}] {}", mark, name);
+            }
+            Ok(())
+        },
+        Command::SqlMigrate(name) => {
+            let migration = find_migration(options, &name)?;
+            let queries = if options.backwards { migration.down } else { migration.up };
+            for query in queries {
+                print!("{}", query);
+            }
+            Ok(())
+        },
+    }
+}
+
+fn find_migration(options: &Options, name: &str) -> Result<Migration> {
+    read_migrations(&options.migrations)?
+        .into_iter()
+        .find(|migration| migration.name == name)
+        .ok_or_else(|| format!("cannot find the migration {} in {}", name, options.migrations.display()).into())
+}
+
+fn make_migrations(name: Option<String>, options: &Options) -> Result<()> {
+    let backend = backend(options.database()?);
+    let schema =
+        match options.schema {
+            Some(ref path) => Schema::from_snapshot(&read_file(path)?)?,
+            None => model_schema()?,
+        };
+    let old_schema = last_schema(&options.migrations)?;
+    for rename in &options.renames {
+        check_rename(rename, &old_schema, &schema)?;
+    }
+    let steps = old_schema.diff_with_renames(&schema, &options.renames);
+    if steps.is_empty() {
+        println!("No changes detected.");
+        return Ok(());
+    }
+    // NOTE: a column is only renamed when asked since a rename cannot be told apart from a dropped
+    // column and an added column.
+    for rename in old_schema.rename_candidates(&schema) {
+        let is_renamed = options.renames.iter()
+            .any(|other| other.table == rename.table && other.old_name == rename.old_name);
+        if !is_renamed {
+            eprintln!("warning: the column {}.{} is dropped and {} is added: use --rename {}.{}={} if it was renamed",
+                      rename.table, rename.old_name, rename.new_name, rename.table, rename.old_name, rename.new_name);
+        }
+    }
+    let default_name = if old_schema.tables.is_empty() { "initial" } else { "auto" };
+    let name = next_migration_name(&options.migrations, name.as_ref().map_or(default_name, String::as_str))?;
+    let migration = Migration::with_renames(&name, &old_schema, &schema, &options.renames, backend);
+    let path = write_migration(&options.migrations, &migration, &schema)?;
+    println!("Created {}:", path.display());
+    for step in steps {
+        println!("  - {}", step);
+    }
+    Ok(())
+}
+
+/// Check that the old column of the `rename` is only in the `old` schema and its new column only in
+/// the `new` schema.
+fn check_rename(rename: &Rename, old: &Schema, new: &Schema) -> Result<()> {
+    let has_column = |schema: &Schema, column: &str|
+        schema.get(&rename.table).map_or(false, |table| table.column(column).is_some());
+    if !has_column(old, &rename.old_name) || has_column(new, &rename.old_name) {
+        return Err(format!("cannot rename {}.{}: this column must be removed from the schema", rename.table,
+                           rename.old_name).into());
+    }
+    if !has_column(new, &rename.new_name) || has_column(old, &rename.new_name) {
+        return Err(format!("cannot rename {}.{} to {}: this column must be added to the schema", rename.table,
+                           rename.old_name, rename.new_name).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! The migrations stored in a directory.
+//!
+//! Each migration is a subdirectory containing the queries to apply it (`up.sql`), the queries to
+//! revert it (`down.sql`) and the snapshot of the schema after it is applied (`schema.tql`).
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use tql::migration::{Migration, Result, Schema};
+
+const DOWN_FILE: &str = "down.sql";
+const SCHEMA_FILE: &str = "schema.tql";
+const UP_FILE: &str = "up.sql";
+
+/// Get the names of the migrations in `directory`, sorted by name.
+pub fn migration_names(directory: &Path) -> Result<Vec<String>> {
+    let mut names = vec![];
+    if !directory.exists() {
+        return Ok(names);
+    }
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        if entry.path().join(UP_FILE).is_file() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Read the migrations in `directory`, in the order they must be applied.
+pub fn read_migrations(directory: &Path) -> Result<Vec<Migration>> {
+    let mut migrations = vec![];
+    for name in migration_names(directory)? {
+        let path = directory.join(&name);
+        migrations.push(Migration {
+            up: queries(read_file(&path.join(UP_FILE))?),
+            down: queries(read_file(&path.join(DOWN_FILE))?),
+            name,
+        });
+    }
+    Ok(migrations)
+}
+
+/// Read the snapshot of the schema after the last migration in `directory` (the empty schema if
+/// there are no migrations).
+pub fn last_schema(directory: &Path) -> Result<Schema> {
+    match migration_names(directory)?.last() {
+        Some(name) => Schema::from_snapshot(&read_file(&directory.join(name).join(SCHEMA_FILE))?),
+        None => Ok(Schema::new()),
+    }
+}
+
+/// Get the name of the next migration in `directory`, prefixed by its number.
+pub fn next_migration_name(directory: &Path, name: &str) -> Result<String> {
+    let count = migration_names(directory)?.len();
+    Ok(format!("{:04}_{}", count + 1, name))
+}
+
+/// Write the `migration` in `directory`, with the snapshot of the `schema` after it.
+pub fn write_migration(directory: &Path, migration: &Migration, schema: &Schema) -> Result<PathBuf> {
+    let path = directory.join(&migration.name);
+    fs::create_dir_all(&path)?;
+    write_file(&path.join(UP_FILE), &sql_file(&migration.up))?;
+    write_file(&path.join(DOWN_FILE), &sql_file(&migration.down))?;
+    write_file(&path.join(SCHEMA_FILE), &schema.to_snapshot())?;
+    Ok(path)
+}
+
+/// Get the queries of an SQL file.
+///
+/// The file is executed as a single batch, so it is not split in separate queries.
+fn queries(sql: String) -> Vec<String> {
+    if sql.trim().is_empty() {
+        vec![]
+    }
+    else {
+        vec![sql]
+    }
+}
+
+fn sql_file(queries: &[String]) -> String {
+    queries.iter()
+        .map(|query| format!("{};\n", query))
+        .collect()
+}
+
+pub fn read_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)
+        .map_err(|error| format!("cannot open {}: {}", path.display(), error))?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+pub fn write_file(path: &Path, content: &str) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
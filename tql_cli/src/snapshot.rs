@@ -0,0 +1,90 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! The snapshot of the schema of the models, generated by building the crate in the current
+//! directory.
+//!
+//! `#[derive(SqlTable)]` generates a test writing the snapshot of its table in the directory of the
+//! `TQL_SNAPSHOT_DIR` environment variable: these tests are run with `cargo test` and their
+//! snapshots are merged.
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{self, Command, Stdio};
+
+use tql::migration::{Result, SNAPSHOT_DIR_VARIABLE, Schema};
+
+use migrations::read_file;
+
+/// The prefix of the name of the tests generated by `#[derive(SqlTable)]`.
+const TEST_PREFIX: &str = "__tql_snapshot_";
+
+/// Build the crate in the current directory and get the schema of its tables.
+///
+/// Only the tables of the library and of the binaries are included, not those of the integration
+/// tests.
+pub fn model_schema() -> Result<Schema> {
+    let directory = env::temp_dir().join(format!("tql_snapshot_{}", process::id()));
+    fs::create_dir_all(&directory)?;
+    let schema = run_snapshot_tests(&directory).and_then(|()| read_snapshots(&directory));
+    fs::remove_dir_all(&directory)?;
+    schema
+}
+
+fn run_snapshot_tests(directory: &Path) -> Result<()> {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut command = Command::new(cargo);
+    command.arg("test");
+    // NOTE: cargo fails when asked to test the library of a crate without one.
+    if Path::new("src/lib.rs").exists() {
+        command.arg("--lib");
+    }
+    // NOTE: the output of the tests is only shown when they fail since the standard output is the
+    // result of the command.
+    let output = command.args(&["--bins", TEST_PREFIX])
+        .env(SNAPSHOT_DIR_VARIABLE, directory)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|error| format!("cannot run cargo: {}", error))?;
+    if !output.status.success() {
+        io::stderr().write_all(&output.stdout)?;
+        return Err("cannot generate the snapshot of the models: cargo test failed".into());
+    }
+    Ok(())
+}
+
+/// Merge the snapshots of the tables written in `directory`.
+fn read_snapshots(directory: &Path) -> Result<Schema> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(directory)? {
+        paths.push(entry?.path());
+    }
+    // NOTE: sort the tables to get the same schema whatever the order of the files.
+    paths.sort();
+    let mut tables = vec![];
+    for path in paths {
+        tables.extend(Schema::from_snapshot(&read_file(&path)?)?.tables);
+    }
+    Ok(Schema::from_tables(tables))
+}
@@ -0,0 +1,272 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests of the migration commands on a SQLite database.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const SCHEMA: &str = "tql schema 1
+table\tAuthor
+column\tid\tINTEGER PRIMARY KEY\tNOT NULL
+column\tname\tCHARACTER VARYING\tNOT NULL
+";
+
+fn tql(directory: &Path, arguments: &[&str]) -> Output {
+    tql_command(directory, arguments)
+        .args(&["--schema", "schema.tql"])
+        .output()
+        .unwrap()
+}
+
+fn tql_command(directory: &Path, arguments: &[&str]) -> Command {
+    // NOTE: the test executable is in target/debug/deps.
+    let executable = env::current_exe().unwrap()
+        .parent().unwrap()
+        .parent().unwrap()
+        .join("tql");
+    let mut command = Command::new(executable);
+    command.args(arguments)
+        .args(&["--database", "database.sqlite"])
+        .current_dir(directory);
+    command
+}
+
+fn stdout(output: Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn write_schema(directory: &Path, schema: &str) {
+    write_file(&directory.join("schema.tql"), schema);
+}
+
+fn write_file(path: &Path, content: &str) {
+    let mut file = File::create(path).unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+}
+
+fn test_directory(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("tql_cli_{}", name));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+#[test]
+fn test_migrations() {
+    let directory = test_directory("migrations");
+    write_schema(&directory, SCHEMA);
+
+    assert_eq!("Created migrations/0001_initial:\n  - Create table Author\n",
+               stdout(tql(&directory, &["makemigrations"])));
+    assert_eq!("No changes detected.\n", stdout(tql(&directory, &["makemigrations"])));
+    assert_eq!("[ ] 0001_initial\n", stdout(tql(&directory, &["showmigrations"])));
+    assert_eq!("Applied 0001_initial\n", stdout(tql(&directory, &["migrate"])));
+    assert_eq!("No migrations to apply.\n", stdout(tql(&directory, &["migrate"])));
+
+    write_schema(&directory, &format!("{}column\temail\tCHARACTER VARYING\tNULL\n", SCHEMA));
+    assert_eq!("Created migrations/0002_email:\n  - Add column Author.email\n",
+               stdout(tql(&directory, &["makemigrations", "email"])));
+    assert_eq!("ALTER TABLE Author ADD COLUMN email CHARACTER VARYING;\n",
+               stdout(tql(&directory, &["sqlmigrate", "0002_email"])));
+    assert_eq!("Applied 0002_email\n", stdout(tql(&directory, &["migrate"])));
+    assert_eq!("[X] 0001_initial\n[X] 0002_email\n", stdout(tql(&directory, &["showmigrations"])));
+
+    assert_eq!("Reverted 0002_email\n", stdout(tql(&directory, &["rollback"])));
+    assert_eq!("[X] 0001_initial\n[ ] 0002_email\n", stdout(tql(&directory, &["showmigrations"])));
+    assert_eq!("Reverted 0001_initial\n", stdout(tql(&directory, &["rollback"])));
+    assert_eq!("No migrations to rollback.\n", stdout(tql(&directory, &["rollback"])));
+}
+
+#[test]
+fn test_rename() {
+    let directory = test_directory("rename");
+    write_schema(&directory, SCHEMA);
+    stdout(tql(&directory, &["makemigrations"]));
+
+    write_schema(&directory, &SCHEMA.replace("\tname\t", "\tfull_name\t"));
+    let output = tql(&directory, &["makemigrations"]);
+    assert_eq!("warning: the column Author.name is dropped and full_name is added: use --rename \
+               Author.name=full_name if it was renamed\n", String::from_utf8_lossy(&output.stderr));
+    assert_eq!("Created migrations/0002_auto:\n  - Add column Author.full_name\n  - Drop column Author.name\n",
+               stdout(output));
+    fs::remove_dir_all(directory.join("migrations/0002_auto")).unwrap();
+
+    let output = tql(&directory, &["makemigrations", "--rename", "Author.title=full_name"]);
+    assert!(!output.status.success());
+    assert_eq!("error: cannot rename Author.title: this column must be removed from the schema\n",
+               String::from_utf8_lossy(&output.stderr));
+
+    let output = tql(&directory, &["makemigrations", "--rename", "Author.name=full_name"]);
+    assert!(output.stderr.is_empty());
+    assert_eq!("Created migrations/0002_auto:\n  - Rename column Author.name to full_name\n", stdout(output));
+    assert_eq!("ALTER TABLE Author RENAME COLUMN name TO full_name;\n",
+               stdout(tql(&directory, &["sqlmigrate", "0002_auto"])));
+    assert_eq!("ALTER TABLE Author RENAME COLUMN full_name TO name;\n",
+               stdout(tql(&directory, &["sqlmigrate", "0002_auto", "--backwards"])));
+}
+
+#[test]
+fn test_dumpschema() {
+    let directory = test_directory("dumpschema");
+    let schema = format!("{}table\tPost
+column\tid\tINTEGER PRIMARY KEY\tNOT NULL
+column\ttitle\tCHARACTER VARYING\tNOT NULL
+column\tauthor\tINTEGER REFERENCES Author(id)\tNOT NULL
+column\tviews\tBIGINT\tNULL
+index\tPost_title_idx\ttitle
+", SCHEMA);
+    write_schema(&directory, &schema);
+    stdout(tql(&directory, &["makemigrations"]));
+    stdout(tql(&directory, &["migrate"]));
+
+    assert_eq!("Wrote db_schema.tql\n", stdout(tql(&directory, &["dumpschema"])));
+    assert_eq!(schema, fs::read_to_string(directory.join("db_schema.tql")).unwrap());
+    assert_eq!("Wrote dump.tql\n", stdout(tql(&directory, &["dumpschema", "dump.tql"])));
+    assert_eq!(schema, fs::read_to_string(directory.join("dump.tql")).unwrap());
+}
+
+#[test]
+fn test_models_snapshot() {
+    let directory = test_directory("models_snapshot");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    write_file(&directory.join("Cargo.toml"), &format!("[package]
+name = \"models\"
+version = \"0.1.0\"
+
+[dependencies.rusqlite]
+version = \"^0.13.0\"
+
+[dependencies.tql]
+default-features = false
+features = [\"sqlite\"]
+path = \"{root}\"
+
+[dependencies.tql_macros]
+path = \"{root}/tql_macros\"
+", root = root.display()));
+    fs::create_dir(directory.join("src")).unwrap();
+    write_file(&directory.join("src/lib.rs"), "extern crate rusqlite;
+extern crate tql;
+#[macro_use]
+extern crate tql_macros;
+
+use tql::{ForeignKey, PrimaryKey};
+
+#[derive(SqlTable)]
+pub struct Author {
+    pub id: PrimaryKey,
+    pub name: String,
+}
+
+#[derive(SqlTable)]
+pub struct Post {
+    pub id: PrimaryKey,
+    #[sql(index)]
+    pub title: String,
+    pub author: ForeignKey<Author>,
+    pub views: Option<i64>,
+}
+");
+
+    // NOTE: the crate is built in its directory to not wait for the lock of the target directory of
+    // this test.
+    let output = tql_command(&directory, &["makemigrations"])
+        .env_remove("CARGO_TARGET_DIR")
+        .output()
+        .unwrap();
+    assert_eq!("Created migrations/0001_initial:\n  - Create table Author\n  - Create table Post\n",
+               stdout(output));
+    assert_eq!(format!("{}table\tPost
+column\tid\tINTEGER PRIMARY KEY\tNOT NULL
+column\ttitle\tCHARACTER VARYING\tNOT NULL
+column\tauthor\tINTEGER REFERENCES Author(id)\tNOT NULL
+column\tviews\tBIGINT\tNULL
+index\tPost_title_idx\ttitle
+", SCHEMA), fs::read_to_string(directory.join("migrations/0001_initial/schema.tql")).unwrap());
+}
+
+#[test]
+fn test_inspectdb() {
+    let directory = test_directory("inspectdb");
+    write_schema(&directory, &format!("{}table\tPost
+column\tid\tINTEGER PRIMARY KEY\tNOT NULL
+column\ttitle\tCHARACTER VARYING(255)\tNOT NULL
+column\tauthor\tINTEGER REFERENCES Author(id)\tNOT NULL
+column\tviews\tBIGINT\tNULL
+column\tdate_added\tTIMESTAMP WITH TIME ZONE\tNOT NULL\tCURRENT_TIMESTAMP
+column\tcontent\tXML\tNULL
+index\tPost_title_idx\ttitle
+", SCHEMA));
+    stdout(tql(&directory, &["makemigrations"]));
+    stdout(tql(&directory, &["migrate"]));
+
+    let output = tql(&directory, &["inspectdb"]);
+    assert_eq!("warning: column Post.content: unsupported type XML\n", String::from_utf8_lossy(&output.stderr));
+    assert_eq!("use chrono::{DateTime, Utc};
+use tql::{ForeignKey, PrimaryKey};
+
+#[derive(SqlTable)]
+struct Author {
+    id: PrimaryKey,
+    name: String,
+}
+
+#[derive(SqlTable)]
+struct Post {
+    id: PrimaryKey,
+    #[sql(index)]
+    title: String,
+    author: ForeignKey<Author>,
+    views: Option<i64>,
+    date_added: DateTime<Utc>,
+    // FIXME: column content: unsupported type XML.
+}
+", stdout(output));
+}
+
+#[test]
+fn test_errors() {
+    let directory = test_directory("errors");
+
+    let output = tql(&directory, &["makemigrations"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: cannot open schema.tql: "));
+
+    // NOTE: there is no crate to build in this directory.
+    let output = tql_command(&directory, &["makemigrations"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+            .ends_with("error: cannot generate the snapshot of the models: cargo test failed\n"));
+
+    let output = tql(&directory, &["sqlmigrate", "0001_initial"]);
+    assert!(!output.status.success());
+    assert_eq!("error: cannot find the migration 0001_initial in migrations\n",
+               String::from_utf8_lossy(&output.stderr));
+
+    let output = tql(&directory, &["unknown"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: unknown command unknown\n"));
+}
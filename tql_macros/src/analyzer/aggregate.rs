@@ -31,7 +31,7 @@ use syn::{
 };
 use syn::spanned::Spanned;
 
-use ast::{
+use crate::ast::{
     Aggregate,
     AggregateFilter,
     AggregateFilterExpression,
@@ -41,9 +41,10 @@ use ast::{
     WithSpan,
     first_token_span,
 };
-use error::{Error, Result, res};
-use plugin::new_ident;
-use state::aggregates_singleton;
+use crate::error::{Error, Result, res};
+use crate::function::FunctionKind;
+use crate::plugin::new_ident;
+use crate::state::aggregates_singleton;
 use super::{
     check_argument_count,
     path_expr_to_identifier,
@@ -63,10 +64,9 @@ pub fn argument_to_aggregate(arg: &Expression) -> Result<Aggregate> {
     if let Expr::Call(ref call) = *call {
         if let Some(identifier) = path_expr_to_string(&call.func, &mut errors) {
             aggregate.function = identifier.to_string();
-            if let Some(sql_function) = aggregates.get(&identifier) {
-                aggregate.sql_function = sql_function.clone();
-            }
-            else {
+            aggregate.span = call.func.span();
+            aggregate.sql_function = aggregates.get(&identifier).cloned();
+            if aggregate.sql_function.is_none() && !FunctionKind::Aggregate.is_declared(&identifier) {
                 let mut error = Error::new_with_code(
                     &format!("unresolved name `{}`", identifier),
                         // NOTE: we only want the position of the function name, not the parenthesis
@@ -86,7 +86,7 @@ pub fn argument_to_aggregate(arg: &Expression) -> Result<Aggregate> {
 
                 if aggregate.result_name.is_none() {
                     let result_name = aggregate.field.clone().expect("Aggregate identifier").to_string() + "_" +
-                        &aggregate.sql_function.to_lowercase();
+                        &aggregate.function.to_lowercase();
                     let mut ident = new_ident(&result_name);
                     // NOTE: violate the hygiene by assigning a known context to this new
                     // identifier.
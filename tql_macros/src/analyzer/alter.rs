@@ -0,0 +1,142 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+/// Analyzer for the add_column(), drop_column(), rename_column() and rename_to() methods.
+
+use syn::Ident;
+
+use crate::ast::{Query, TableAlteration};
+use crate::error::{Error, Result, res};
+use crate::parser::MethodCall;
+use crate::state::{is_required_field, table_columns};
+use crate::string::{plural, plural_verb};
+use super::path_expr_to_identifier;
+
+/// Convert the arguments of the `method_call` to a `TableAlteration` of the table `table_name`.
+pub fn method_call_to_alteration(method_call: &MethodCall, table_name: &str) -> Result<TableAlteration> {
+    let mut errors = vec![];
+    let method = method_call.name.to_string();
+    let expected_count = if method == "rename_column" { 2 } else { 1 };
+    if method_call.args.len() != expected_count {
+        let length = method_call.args.len();
+        return Err(vec![Error::new_with_code(
+            &format!("this method takes {expected_count} parameter{expected_plural} but {count} parameter{plural} supplied",
+                expected_count = expected_count,
+                expected_plural = plural(expected_count),
+                count = length,
+                plural = plural_verb(length)
+            ),
+            method_call.name.span(), "E0061"
+        )]);
+    }
+
+    let identifiers: Vec<Ident> = method_call.args.iter()
+        .filter_map(|arg| path_expr_to_identifier(arg, &mut errors))
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let alteration =
+        match method.as_str() {
+            "add_column" => {
+                let field = identifiers[0].clone();
+                if cfg!(feature = "rusqlite") {
+                    check_not_required_field(&field, table_name, &mut errors);
+                }
+                TableAlteration::AddColumn(field)
+            },
+            "drop_column" => {
+                let column = identifiers[0].clone();
+                check_not_field_column(&column, table_name, "drop", "remove the field from the struct", &mut errors);
+                TableAlteration::DropColumn(column)
+            },
+            "rename_column" => {
+                let old = identifiers[0].clone();
+                check_not_field_column(&old, table_name, "rename", "rename the field in the struct", &mut errors);
+                TableAlteration::RenameColumn {
+                    new: identifiers[1].clone(),
+                    old,
+                }
+            },
+            "rename_to" => {
+                let new_table = identifiers[0].clone();
+                if new_table == table_name {
+                    errors.push(Error::new(
+                        &format!("cannot rename the table `{}` to itself", table_name),
+                        new_table.span(),
+                    ));
+                }
+                TableAlteration::RenameTo(new_table)
+            },
+            _ => unreachable!("No alter method named {}", method),
+        };
+    res(alteration, errors)
+}
+
+/// Check that the column of the `field` of the table `table_name` can be `NULL` or has a default
+/// value, since SQLite would recreate the table without a value for this column in the existing rows.
+fn check_not_required_field(field: &Ident, table_name: &str, errors: &mut Vec<Error>) {
+    if is_required_field(table_name, &field.to_string()) {
+        let mut error = Error::new(
+            &format!("cannot add the column of the field `{}` since it cannot be NULL and has no default value",
+                field),
+            field.span(),
+        );
+        error.add_help("make the field optional to add its column to a table with SQLite");
+        errors.push(error);
+    }
+}
+
+/// Check that the `column` is not stored by a field of the table `table_name`, since the column
+/// of a field cannot be dropped or renamed without changing the struct first.
+fn check_not_field_column(column: &Ident, table_name: &str, action: &str, help: &str, errors: &mut Vec<Error>) {
+    // NOTE: the columns of a table declared in another crate are unknown, hence the check is
+    // skipped.
+    let is_field_column = table_columns(table_name)
+        .map_or(false, |columns| columns.iter().any(|name| column == name));
+    if is_field_column {
+        let mut error = Error::new(
+            &format!("cannot {action} the column `{column}` since it is still used by the struct `{table}`",
+                action = action,
+                column = column,
+                table = table_name
+            ),
+            column.span(),
+        );
+        error.add_help(&format!("{} before altering the table", help));
+        errors.push(error);
+    }
+}
+
+/// Get the fields whose columns are created by an `ALTER TABLE` query to be able to check that
+/// they exist.
+pub fn get_alter_idents(query: &Query) -> Vec<Ident> {
+    let mut idents = vec![];
+    if let Query::AlterTable { ref alteration, .. } = *query {
+        match *alteration {
+            TableAlteration::AddColumn(ref field) | TableAlteration::RenameColumn { new: ref field, .. } =>
+                idents.push(field.clone()),
+            TableAlteration::DropColumn(_) | TableAlteration::RenameTo(_) => (),
+        }
+    }
+    idents
+}
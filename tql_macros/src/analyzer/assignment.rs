@@ -28,14 +28,15 @@ use syn::{
 };
 use syn::spanned::Spanned;
 
-use ast::{
+use crate::ast::{
     Assignment,
     AssignmentOperator,
     Expression,
     WithSpan,
 };
-use error::{Error, Result, res};
-use plugin::number_literal;
+use crate::error::{Error, Result, res};
+use crate::plugin::number_literal;
+use crate::state::embedded_fields;
 use super::path_expr_to_identifier;
 
 /// Convert an `Expression` to an `Assignment`.
@@ -78,6 +79,18 @@ pub fn argument_to_assignment(arg: &Expression) -> Result<Assignment> {
     res(assignment, errors)
 }
 
+/// Check that the embedded fields are only assigned with `=`.
+pub fn analyze_assignments_types(assignments: &[Assignment], table_name: &str, errors: &mut Vec<Error>) {
+    for assignment in assignments {
+        if let Some(ref identifier) = assignment.identifier {
+            let operator = &assignment.operator.node;
+            if *operator != AssignmentOperator::Equal && embedded_fields(table_name, &identifier.to_string()).is_some() {
+                errors.push(Error::new(&format!("expected = but got {}", *operator), assignment.operator.span));
+            }
+        }
+    }
+}
+
 /// Convert a `BinOp` to an SQL `AssignmentOperator`.
 fn binop_to_assignment_operator(binop: &BinOp) -> (AssignmentOperator, Span) {
     match *binop {
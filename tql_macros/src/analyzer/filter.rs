@@ -22,19 +22,22 @@
 /// Analyzer for the filter() method.
 
 use proc_macro2::Span;
+use syn;
 use syn::{
     BinOp,
     Expr,
+    ExprCall,
+    ExprField,
     ExprUnary,
     Ident,
-    Path,
+    Member,
     UnOp,
 };
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::token::Comma;
 
-use ast::{
+use crate::ast::{
     self,
     Expression,
     Filter,
@@ -44,16 +47,35 @@ use ast::{
     LogicalOperator,
     Query,
     RelationalOperator,
+    SubQueryFilter,
+    SubQueryOperator,
     WithSpan,
 };
-use error::{Error, Result, res};
+use crate::attribute::field_ty_to_type;
+use crate::error::{Error, Result, res};
+use crate::state::{embedded_fields, is_decimal_field};
+use super::subquery::{is_subquery, macro_to_subquery};
 
 /// Analyze the types of the `FilterExpression`.
 pub fn analyze_filter_types(filter: &FilterExpression, table_name: &str, errors: &mut Vec<Error>) {
     // TODO: check that operators are used with the good types (perhaps not necessary because all
     // types may support all operators)?
     match *filter {
-        FilterExpression::Filter(_) => (),
+        FilterExpression::Filter(ref filter) => {
+            if let FilterValue::Identifier(ref table, ref identifier) = filter.operand1 {
+                // NOTE: an embedded struct is stored in multiple columns.
+                if embedded_fields(table, &identifier.to_string()).is_some() {
+                    errors.push(Error::new(
+                        &format!("cannot compare the embedded field `{}`, compare one of its fields instead",
+                            identifier),
+                        identifier.span(),
+                    ));
+                }
+            }
+            if cfg!(feature = "rusqlite") {
+                check_decimal_order(filter, errors);
+            }
+        },
         FilterExpression::Filters(ref filters) => {
             analyze_filter_types(&*filters.operand1, table_name, errors);
             analyze_filter_types(&*filters.operand2, table_name, errors);
@@ -65,10 +87,43 @@ pub fn analyze_filter_types(filter: &FilterExpression, table_name: &str, errors:
         FilterExpression::ParenFilter(ref filter) => {
             analyze_filter_types(filter, table_name, errors);
         },
+        FilterExpression::SubQuery(_) => (), // NOTE: the subquery was analyzed when it was converted.
         FilterExpression::FilterValue(_) => (),
     }
 }
 
+/// Check that a decimal field is only compared for equality, since SQLite stores it as text.
+fn check_decimal_order(filter: &Filter, errors: &mut Vec<Error>) {
+    let field =
+        match filter.operand1 {
+            FilterValue::Identifier(ref table, ref identifier) if is_decimal_field(table, &identifier.to_string()) =>
+                identifier,
+            FilterValue::EmbeddedField(ref table, ref identifier, ref embed_identifier) => {
+                let embed_field = embedded_fields(table, &identifier.to_string()).into_iter()
+                    .flatten()
+                    .find(|embed_field| embed_field.name == embed_identifier.to_string());
+                match embed_field {
+                    Some(embed_field) => {
+                        let typ = syn::parse_str(&embed_field.typ).expect("embedded field type");
+                        if !field_ty_to_type(&typ).node.is_decimal() {
+                            return;
+                        }
+                    },
+                    None => return,
+                }
+                embed_identifier
+            },
+            _ => return,
+        };
+    match filter.operator {
+        RelationalOperator::Equal | RelationalOperator::NotEqual => (),
+        _ => errors.push(Error::new(
+            &format!("cannot order the decimal field `{}` because SQLite stores the decimal numbers as text", field),
+            field.span(),
+        )),
+    }
+}
+
 /// Convert a Rust binary expression to a `FilterExpression`.
 fn binary_expression_to_filter_expression(expr1: &Expression, op: &BinOp, expr2: &Expression, table_name: &str) ->
     Result<FilterExpression>
@@ -89,6 +144,22 @@ fn binary_expression_to_filter_expression(expr1: &Expression, op: &BinOp, expr2:
         }
         else if is_relational_operator(op) {
             if let FilterExpression::FilterValue(filter1) = filter1 {
+                if let Expr::Macro(ref mac) = *expr2 {
+                    if is_subquery(expr2) {
+                        if let FilterValue::MethodCall(_) = filter1.node {
+                            return Err(vec![Error::new(
+                                "expected identifier", // TODO: improve this message.
+                                filter1.span,
+                            )]);
+                        }
+                        let operator = SubQueryOperator::Relational(binop_to_relational_operator(op));
+                        return Ok(FilterExpression::SubQuery(SubQueryFilter {
+                            operand1: Some(filter1.node),
+                            operator,
+                            query: macro_to_subquery(&mac.mac, operator)?,
+                        }));
+                    }
+                }
                 FilterExpression::Filter(Filter {
                     operand1: filter1.node,
                     operator: binop_to_relational_operator(op),
@@ -145,10 +216,36 @@ pub fn expression_to_filter_expression(arg: &Expression, table_name: &str) -> Re
             Expr::Binary(ref bin) => {
                 binary_expression_to_filter_expression(&bin.left, &bin.op, &bin.right, table_name)?
             },
+            Expr::Call(ref call) => {
+                call_expression_to_filter_expression(call, &mut errors)?
+            },
+            Expr::MethodCall(ref call) if call.method == "in_" && call.args.len() == 1 &&
+                is_subquery(&call.args[0]) =>
+            {
+                let mut operand1 = None;
+                if let Expr::Path(ref path) = *call.receiver {
+                    let identifier = path.path.segments.first().unwrap().into_value().ident.clone();
+                    operand1 = Some(FilterValue::Identifier(table_name.to_string(), identifier));
+                }
+                else {
+                    errors.push(Error::new(
+                        "expected identifier", // TODO: improve this message.
+                        call.receiver.span(),
+                    ));
+                }
+                match call.args[0] {
+                    Expr::Macro(ref mac) => FilterExpression::SubQuery(SubQueryFilter {
+                        operand1,
+                        operator: SubQueryOperator::In,
+                        query: macro_to_subquery(&mac.mac, SubQueryOperator::In)?,
+                    }),
+                    _ => unreachable!("is_subquery() only accepts macros"),
+                }
+            },
             Expr::MethodCall(ref call) => {
                 FilterExpression::FilterValue(WithSpan {
                     node: method_call_expression_to_filter_expression(call.method.clone(), &call.receiver, &call.args,
-                                                                      call.span(), &mut errors),
+                                                                      call.span(), table_name, &mut errors),
                     span: arg.span(),
                 })
             },
@@ -159,6 +256,24 @@ pub fn expression_to_filter_expression(arg: &Expression, table_name: &str) -> Re
                     span: arg.span(),
                 })
             },
+            Expr::Field(ExprField { ref base, member: Member::Named(ref subfield), .. }) => {
+                match **base {
+                    Expr::Path(ref path) => {
+                        let identifier = path.path.segments.first().unwrap().into_value().ident.clone();
+                        FilterExpression::FilterValue(WithSpan {
+                            node: FilterValue::EmbeddedField(table_name.to_string(), identifier, subfield.clone()),
+                            span: arg.span(),
+                        })
+                    },
+                    _ => {
+                        errors.push(Error::new(
+                            "expected identifier", // TODO: improve this message.
+                            base.span(),
+                        ));
+                        FilterExpression::NoFilters
+                    },
+                }
+            },
             Expr::Paren(ref paren) => {
                 let filter = expression_to_filter_expression(&paren.expr, table_name)?;
                 FilterExpression::ParenFilter(Box::new(filter))
@@ -179,6 +294,33 @@ pub fn expression_to_filter_expression(arg: &Expression, table_name: &str) -> Re
     res(filter, errors)
 }
 
+/// Convert a function call expression (`exists(sql!(…))`) to a filter expression.
+fn call_expression_to_filter_expression(call: &ExprCall, errors: &mut Vec<Error>) -> Result<FilterExpression> {
+    let is_exists =
+        if let Expr::Path(ref path) = *call.func {
+            path.path.segments.len() == 1 && path.path.segments[0].ident == "exists"
+        }
+        else {
+            false
+        };
+    if is_exists && call.args.len() == 1 {
+        if let Expr::Macro(ref mac) = call.args[0] {
+            if is_subquery(&call.args[0]) {
+                return Ok(FilterExpression::SubQuery(SubQueryFilter {
+                    operand1: None,
+                    operator: SubQueryOperator::Exists,
+                    query: macro_to_subquery(&mac.mac, SubQueryOperator::Exists)?,
+                }));
+            }
+        }
+    }
+    errors.push(Error::new(
+        "Expected binary operation", // TODO: improve this message.
+        call.span(),
+    ));
+    Ok(FilterExpression::NoFilters)
+}
+
 /// Check if a `BinOp` is a `LogicalOperator`.
 pub fn is_logical_operator(binop: &BinOp) -> bool {
     match *binop {
@@ -197,69 +339,89 @@ pub fn is_relational_operator(binop: &BinOp) -> bool {
 
 /// Convert a method call expression to a filter expression.
 fn method_call_expression_to_filter_expression(identifier: Ident, expr: &Expression, args: &Punctuated<Expr, Comma>,
-    position: Span, errors: &mut Vec<Error>) -> FilterValue
+    position: Span, table_name: &str, errors: &mut Vec<Error>) -> FilterValue
+{
+    match method_call_to_ast(identifier, expr, args, position, table_name, errors) {
+        Some(call) => FilterValue::MethodCall(call),
+        None => FilterValue::None,
+    }
+}
+
+/// Convert a method call to its AST representation.
+/// The object is either an identifier or another method call (e.g. `field.to_lowercase().contains(…)`).
+fn method_call_to_ast(identifier: Ident, expr: &Expression, args: &Punctuated<Expr, Comma>, position: Span,
+    table_name: &str, errors: &mut Vec<Error>) -> Option<ast::MethodCall>
 {
-    let dummy = FilterValue::None;
+    let arguments: Vec<Expression> = args.iter()
+        .cloned()
+        .collect();
     match *expr {
         Expr::Path(ref path) => {
-            path_method_call_to_filter(&path.path, identifier, args, position)
+            let object_name = path.path.segments.first().unwrap().into_value().ident.clone();
+            Some(ast::MethodCall {
+                arguments,
+                method_name: identifier,
+                object_call: None,
+                object_name,
+                position,
+                table: table_name.to_string(),
+            })
+        },
+        Expr::MethodCall(ref call) => {
+            let object_call = method_call_to_ast(call.method.clone(), &call.receiver, &call.args, call.span(),
+                table_name, errors)?;
+            Some(ast::MethodCall {
+                arguments,
+                method_name: identifier,
+                object_name: object_call.object_name.clone(),
+                object_call: Some(Box::new(object_call)),
+                position,
+                table: table_name.to_string(),
+            })
         },
         _ => {
             errors.push(Error::new(
                 "expected identifier", // TODO: improve this message.
                 expr.span(),
             ));
-            dummy
+            None
         },
     }
 }
 
-/// Convert a method call where the object is an identifier to a filter expression.
-fn path_method_call_to_filter(path: &Path, identifier: Ident, args: &Punctuated<Expr, Comma>, position: Span) -> FilterValue
-{
-    let object_name = path.segments.first().unwrap().into_value().ident.clone();
-    let arguments: Vec<Expression> = args.iter()
-        .cloned()
-        .collect();
-
-    FilterValue::MethodCall(ast::MethodCall {
-        arguments: arguments.clone(),
-        method_name: identifier,
-        object_name,
-        position,
-    })
-}
-
-pub fn get_method_calls(query: &Query) -> Vec<(ast::MethodCall, Option<Expression>)> {
+/// Get the method calls in the filters of the query (including the ones in the subqueries) with the
+/// name of the table they are called on.
+pub fn get_method_calls(query: &Query) -> Vec<(String, ast::MethodCall, Option<Expression>)> {
     match *query {
-        Query::Aggregate { ref filter, .. } | Query::Delete { ref filter, .. } | Query::Select { ref filter, .. } |
-            Query::Update { ref filter, .. } =>
-            get_methods_from_filter(filter),
-        Query::CreateTable { .. } | Query::Drop { .. } | Query::Insert { .. } =>
+        Query::Aggregate { ref filter, ref table, .. } | Query::Delete { ref filter, ref table, .. } |
+            Query::Select { ref filter, ref table, .. } | Query::Update { ref filter, ref table, .. } =>
+            get_methods_from_filter(filter, table),
+        Query::AlterTable { .. } | Query::CreateTable { .. } | Query::Drop { .. } | Query::Insert { .. } =>
             vec![],
     }
 }
 
-fn get_methods_from_filter(filter: &FilterExpression) -> Vec<(ast::MethodCall, Option<Expression>)> {
+fn get_methods_from_filter(filter: &FilterExpression, table: &str) -> Vec<(String, ast::MethodCall, Option<Expression>)> {
     let mut calls = vec![];
     match *filter {
         FilterExpression::Filter(ref filter) => {
             if let FilterValue::MethodCall(ref call) = filter.operand1 {
-                calls.push((call.clone(), Some(filter.operand2.clone())));
+                calls.push((table.to_string(), call.clone(), Some(filter.operand2.clone())));
             }
         },
         FilterExpression::Filters(ref filters) => {
-            calls.extend(get_methods_from_filter(&filters.operand1));
-            calls.extend(get_methods_from_filter(&filters.operand2));
+            calls.extend(get_methods_from_filter(&filters.operand1, table));
+            calls.extend(get_methods_from_filter(&filters.operand2, table));
         },
         FilterExpression::FilterValue(ref filter_value) => {
             if let FilterValue::MethodCall(ref call) = filter_value.node {
-                calls.push((call.clone(), None));
+                calls.push((table.to_string(), call.clone(), None));
             }
         },
-        FilterExpression::NegFilter(ref filter) => calls.extend(get_methods_from_filter(filter)),
+        FilterExpression::NegFilter(ref filter) => calls.extend(get_methods_from_filter(filter, table)),
         FilterExpression::NoFilters => (),
-        FilterExpression::ParenFilter(ref filter) => calls.extend(get_methods_from_filter(filter)),
+        FilterExpression::ParenFilter(ref filter) => calls.extend(get_methods_from_filter(filter, table)),
+        FilterExpression::SubQuery(ref filter) => calls.extend(get_method_calls(&filter.query.query)),
     }
     calls
 }
@@ -23,7 +23,7 @@
 
 use syn::Expr;
 
-use ast::{
+use crate::ast::{
     Expression,
     Filter,
     FilterExpression,
@@ -31,8 +31,8 @@ use ast::{
     Limit,
     RelationalOperator,
 };
-use error::{Result, res};
-use plugin::number_literal;
+use crate::error::{Result, res};
+use crate::plugin::number_literal;
 use super::filter::expression_to_filter_expression;
 
 /// Convert an expression from a `get()` method to a FilterExpression and a Limit.
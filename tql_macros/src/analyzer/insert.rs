@@ -27,14 +27,14 @@ use std::collections::HashSet;
 use proc_macro2::Span;
 use syn::Ident;
 
-use ast::{
+use crate::ast::{
     Assignment,
     AssignmentOperator,
     Query,
 };
-use error::Error;
+use crate::error::Error;
 #[cfg(feature = "unstable")]
-use parser::MethodCalls;
+use crate::parser::MethodCalls;
 
 /// Check that the method call contains all the fields from the `table` and that all assignments
 /// does not use an operation (e.g. +=).
@@ -23,8 +23,8 @@
 
 use syn::spanned::Spanned;
 
-use ast::{Expression, Join};
-use error::{Error, Result, res};
+use crate::ast::{Expression, Join};
+use crate::error::{Error, Result, res};
 use super::path_expr_to_identifier;
 
 /// Convert an `Expression` to a `Join`
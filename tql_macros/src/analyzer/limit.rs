@@ -24,14 +24,14 @@
 use syn::{Expr, ExprRange};
 use syn::spanned::Spanned;
 
-use ast::{
+use crate::ast::{
     Expression,
     Limit,
     Query,
 };
-use error::{Error, Result, res};
+use crate::error::{Error, Result, res};
 use super::check_type;
-use types::Type;
+use crate::types::Type;
 
 /// Analyze the types of the `Limit`.
 pub fn analyze_limit_types(limit: &Limit, errors: &mut Vec<Error>) {
@@ -19,22 +19,26 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
-use ast::Query;
-use error::{Error, Result, res};
-use state::methods_singleton;
+use crate::ast::Query;
+use crate::error::{Error, Result, res};
+use crate::state::methods_singleton;
 use super::get_method_calls;
 
 pub fn analyze_methods(query: &Query) -> Result<()> {
     let methods = methods_singleton();
     let calls = get_method_calls(query);
     let mut errors = vec![];
-    for (call, _) in calls {
-        let name = call.method_name.to_string();
-        if let Some(method) = methods.get(&name) {
-            if method.template.is_none() {
-                errors.push(Error::new(&format!("The method {} is not available on this backend", &name),
-                    call.method_name.span()))
+    for (_, call, _) in calls {
+        let mut call = Some(&call);
+        while let Some(method_call) = call {
+            let name = method_call.method_name.to_string();
+            if let Some(overloads) = methods.get(&name) {
+                if overloads.iter().all(|method| method.template.is_none()) {
+                    errors.push(Error::new(&format!("The method {} is not available on this backend", &name),
+                        method_call.method_name.span()))
+                }
             }
+            call = method_call.object_call.as_ref().map(|call| &**call);
         }
     }
     res((), errors)
@@ -22,6 +22,7 @@
 //! Semantic analyzer.
 
 mod aggregate;
+mod alter;
 mod assignment;
 mod filter;
 mod get;
@@ -30,6 +31,7 @@ mod method;
 mod join;
 mod limit;
 mod sort;
+mod subquery;
 
 use std::fmt::Display;
 use std::result;
@@ -46,10 +48,11 @@ use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::token::Comma;
 
-use ast::{
+use crate::ast::{
     Aggregate,
     AggregateFilterExpression,
     Assignment,
+    AssignmentOperator,
     Expression,
     FilterExpression,
     Groups,
@@ -57,13 +60,17 @@ use ast::{
     Limit,
     Order,
     Query,
+    TableAlteration,
+    find_assignment,
 };
-use error::{Error, Result, res};
-use parser::{MethodCall, MethodCalls};
-use plugin::number_literal;
+use crate::error::{Error, Result, res};
+use crate::parser::{MethodCall, MethodCalls};
+use crate::plugin::number_literal;
 use self::aggregate::{argument_to_aggregate, argument_to_group, expression_to_aggregate_filter_expression};
 pub use self::aggregate::{get_aggregate_calls, get_values_idents};
-use self::assignment::argument_to_assignment;
+use self::alter::method_call_to_alteration;
+pub use self::alter::get_alter_idents;
+use self::assignment::{analyze_assignments_types, argument_to_assignment};
 use self::filter::{analyze_filter_types, expression_to_filter_expression};
 pub use self::filter::get_method_calls;
 use self::get::get_expression_to_filter_expression;
@@ -75,15 +82,18 @@ use self::join::argument_to_join;
 use self::limit::{analyze_limit_types, argument_to_limit};
 pub use self::limit::get_limit_args;
 pub use self::method::analyze_methods;
-use self::sort::argument_to_order;
+use self::sort::{analyze_order_types, argument_to_order};
 pub use self::sort::get_sort_idents;
-use string::{find_near, plural_verb};
-use types::Type;
+pub use self::subquery::{SubQueryCheck, get_subquery_checks};
+use crate::state::{is_soft_delete, version_field};
+use crate::string::{find_near, plural_verb};
+use crate::types::Type;
 
 /// The type of the SQL query.
 #[derive(PartialEq)]
 enum SqlQueryType {
     Aggregate,
+    AlterTable,
     CreateTable,
     Delete,
     Drop,
@@ -109,6 +119,13 @@ struct QueryData {
     aggregate_filter: AggregateFilterExpression,
     aggregates: Vec<Aggregate>,
     groups: Groups,
+    // AlterTable
+    alteration: Option<TableAlteration>,
+    // CreateTable
+    if_not_exists: bool,
+    // Drop
+    cascade: bool,
+    if_exists: bool,
     // Aggregate, Delete, Select, Update
     filter: FilterExpression,
     // Aggregate / Select
@@ -117,14 +134,22 @@ struct QueryData {
     assignments: Vec<Assignment>,
     // Select
     limit: Limit,
+    only: Option<Ident>,
     order: Vec<Order>,
     use_pk: bool,
+    // Aggregate, Delete, Select
+    soft_delete: bool,
     // All
     query_type: SqlQueryType,
 }
 
 /// Analyze and transform the AST.
 pub fn analyze(method_calls: &MethodCalls) -> Result<Query> {
+    analyze_query(method_calls, false)
+}
+
+/// Analyze and transform the AST of a query or a subquery.
+fn analyze_query(method_calls: &MethodCalls, is_subquery: bool) -> Result<Query> {
     let mut errors = vec![];
 
     let table_name = method_calls.name.clone().expect("table name in method_calls").to_string();
@@ -138,6 +163,10 @@ pub fn analyze(method_calls: &MethodCalls) -> Result<Query> {
     // Get all the data from the query.
     let query_data = process_methods(&calls, &table_name, &mut delete_position)?;
 
+    if !is_subquery {
+        check_only_in_subquery(calls, &mut errors);
+    }
+
     let query = new_query(query_data, table_name);
 
     check_delete_without_filters(&query, delete_position, &mut errors);
@@ -145,6 +174,14 @@ pub fn analyze(method_calls: &MethodCalls) -> Result<Query> {
     res(query, errors)
 }
 
+/// Check that the methods called in the query exist, which does not need the information about
+/// the tables of the query.
+pub fn check_method_names(method_calls: &MethodCalls) -> Result<()> {
+    let mut errors = vec![];
+    check_methods(method_calls, &mut errors);
+    res((), errors)
+}
+
 /// Analyze the literal types in the `Query`.
 pub fn analyze_types(query: &Query) -> Result<()> {
     let mut errors = vec![];
@@ -152,17 +189,20 @@ pub fn analyze_types(query: &Query) -> Result<()> {
         Query::Aggregate { ref filter, ref table, .. } => {
             analyze_filter_types(filter, &table, &mut errors);
         },
+        Query::AlterTable { .. } => (), // Nothing to analyze.
         Query::CreateTable { .. } => (), // Nothing to analyze.
         Query::Delete { ref filter, ref table, .. } => {
             analyze_filter_types(filter, &table, &mut errors);
         },
         Query::Drop { .. } => (), // Nothing to analyze.
         Query::Insert { .. } => (),
-        Query::Select { ref filter, ref limit, ref table, .. } => {
+        Query::Select { ref filter, ref limit, ref order, ref table, .. } => {
             analyze_filter_types(filter, &table, &mut errors);
             analyze_limit_types(limit, &mut errors);
+            analyze_order_types(order, &table, &mut errors);
         },
-        Query::Update { ref filter, ref table, .. } => {
+        Query::Update { ref assignments, ref filter, ref table, .. } => {
+            analyze_assignments_types(assignments, &table, &mut errors);
             analyze_filter_types(filter, &table, &mut errors);
         },
     }
@@ -198,18 +238,36 @@ fn check_delete_without_filters(query: &Query, delete_position: Option<Span>, er
     }
 }
 
+/// Check that the only() method is not called outside of a subquery.
+fn check_only_in_subquery(calls: &[MethodCall], errors: &mut Vec<Error>) {
+    for call in calls.iter().filter(|call| call.name == "only") {
+        errors.push(Error::new(
+            "the only() method can only be called in a subquery",
+            call.name.span(),
+        ));
+    }
+}
+
 /// Check if the method calls sequence is valid.
 /// For instance, one cannot call both insert() and delete() methods in the same query.
 fn check_method_calls_validity(method_calls: &MethodCalls, errors: &mut Vec<Error>) {
     let method_map =
         hashmap!{
-            "aggregate" => vec!["filter", "join", "values"],
-            "all" => vec!["filter", "get", "join", "limit", "sort"],
+            "add_column" => vec![],
+            "aggregate" => vec!["filter", "join", "values", "with_deleted"],
+            "all" => vec!["filter", "get", "join", "limit", "only", "sort", "with_deleted"],
             "create" => vec![],
+            "create_if_not_exists" => vec![],
             "delete" => vec!["filter", "get"],
             "drop" => vec![],
+            "drop_cascade" => vec![],
+            "drop_column" => vec![],
+            "drop_if_exists" => vec![],
+            "hard_delete" => vec!["filter", "get"],
             "insert" => vec![],
-            "update" => vec!["filter", "get"],
+            "rename_column" => vec![],
+            "rename_to" => vec![],
+            "update" => vec!["filter", "get", "with_deleted"],
         };
 
     let main_method = method_calls.calls.iter()
@@ -264,6 +322,44 @@ fn check_methods(method_calls: &MethodCalls, errors: &mut Vec<Error>) {
     }
 }
 
+/// Check that the current version of the row is assigned to the `version_field` in `update()`, so
+/// that the row is only updated if it was not updated since it was read.
+fn check_version_assignment(assignments: &[Assignment], version_field: &str, method_call: &MethodCall,
+    errors: &mut Vec<Error>)
+{
+    match find_assignment(assignments, version_field) {
+        Some(assignment) =>
+            if assignment.operator.node != AssignmentOperator::Equal {
+                errors.push(Error::new(
+                    &format!("the `{}` field is incremented automatically, assign the current version with `{} = …`",
+                        version_field, version_field),
+                    assignment.operator.span,
+                ));
+            },
+        None => {
+            let mut error = Error::new(
+                &format!("missing the current version of the row in update(): add `{} = …`", version_field),
+                method_call.name.span(),
+            );
+            error.add_note("the row is only updated if its version did not change since it was read");
+            errors.push(error);
+        },
+    }
+}
+
+/// Check that the method is called on a table with `#[sql(soft_delete)]`.
+fn check_soft_delete(method_call: &MethodCall, table_name: &str, is_soft_delete: bool, errors: &mut Vec<Error>) {
+    if !is_soft_delete {
+        errors.push(Error::new(
+            &format!("the {method}() method can only be called on a table with #[sql(soft_delete)], but `{table}` has none",
+                method = method_call.name,
+                table = table_name
+            ),
+            method_call.name.span(),
+        ));
+    }
+}
+
 /// Check that the specified method call did not received any arguments.
 fn check_no_arguments(method_call: &MethodCall, errors: &mut Vec<Error>) {
     if !method_call.args.is_empty() {
@@ -294,7 +390,7 @@ fn convert_arguments<F, Type>(arguments: &[Expression], convert_argument: F) ->
     let mut errors = vec![];
 
     for arg in arguments {
-        try(convert_argument(arg), &mut errors, |item| {
+        r#try(convert_argument(arg), &mut errors, |item| {
             items.push(item);
         });
     }
@@ -306,19 +402,29 @@ fn convert_arguments<F, Type>(arguments: &[Expression], convert_argument: F) ->
 // TODO: return Vec<&'static str> instead?
 fn get_methods() -> Vec<String> {
     vec![
+        "add_column".to_string(),
         "aggregate".to_string(),
         "all".to_string(),
         "create".to_string(),
+        "create_if_not_exists".to_string(),
         "delete".to_string(),
         "drop".to_string(),
+        "drop_cascade".to_string(),
+        "drop_column".to_string(),
+        "drop_if_exists".to_string(),
         "filter".to_string(),
         "get".to_string(),
+        "hard_delete".to_string(),
         "insert".to_string(),
         "join".to_string(),
         "limit".to_string(),
+        "only".to_string(),
+        "rename_column".to_string(),
+        "rename_to".to_string(),
         "sort".to_string(),
         "update".to_string(),
         "values".to_string(),
+        "with_deleted".to_string(),
     ]
 }
 
@@ -378,8 +484,9 @@ fn mismatched_types<S: Display, T: Display>(expected_type: S, actual_type: &T, p
 }
 
 /// Create a new query from all the data gathered by the method calls.
-fn new_query(QueryData { filter, joins, limit, order, assignments, aggregates, groups,
-    aggregate_filter, query_type, use_pk }: QueryData, table_name: String) -> Query
+fn new_query(QueryData { filter, joins, limit, only, order, assignments, aggregates, groups,
+    aggregate_filter, alteration, cascade, if_exists, if_not_exists, query_type, soft_delete, use_pk }: QueryData,
+    table_name: String) -> Query
 {
     match query_type {
         SqlQueryType::Aggregate =>
@@ -389,20 +496,30 @@ fn new_query(QueryData { filter, joins, limit, order, assignments, aggregates, g
                 filter,
                 groups,
                 joins,
+                soft_delete,
+                table: table_name,
+            },
+        SqlQueryType::AlterTable =>
+            Query::AlterTable {
+                alteration: alteration.expect("table alteration"),
                 table: table_name,
             },
         SqlQueryType::CreateTable =>
             Query::CreateTable {
+                if_not_exists,
                 table: table_name,
             },
         SqlQueryType::Delete =>
             Query::Delete {
                 filter,
+                soft_delete,
                 table: table_name,
                 use_pk,
             },
         SqlQueryType::Drop =>
             Query::Drop {
+                cascade,
+                if_exists,
                 table: table_name,
             },
         SqlQueryType::Insert =>
@@ -416,7 +533,9 @@ fn new_query(QueryData { filter, joins, limit, order, assignments, aggregates, g
                 get: query_type == SqlQueryType::SelectOne,
                 joins,
                 limit,
+                only,
                 order,
+                soft_delete,
                 table: table_name,
                 use_pk,
             }
@@ -425,6 +544,7 @@ fn new_query(QueryData { filter, joins, limit, order, assignments, aggregates, g
             Query::Update {
                 assignments,
                 filter,
+                soft_delete,
                 table: table_name,
                 use_pk,
             },
@@ -459,11 +579,19 @@ fn path_expr_to_string(expression: &Expression, errors: &mut Vec<Error>) -> Opti
 fn process_methods(calls: &[MethodCall], table_name: &str, delete_position: &mut Option<Span>) -> Result<QueryData> {
     let mut errors = vec![];
     let mut query_data = QueryData::default();
+    let is_soft_delete = is_soft_delete(table_name);
+    query_data.soft_delete = is_soft_delete;
 
     for method_call in calls {
         match method_call.name.to_string().as_str() {
+            "add_column" | "drop_column" | "rename_column" | "rename_to" => {
+                r#try(method_call_to_alteration(method_call, table_name), &mut errors, |alteration| {
+                    query_data.alteration = Some(alteration);
+                    query_data.query_type = SqlQueryType::AlterTable;
+                });
+            },
             "aggregate" => {
-                try(convert_arguments(&method_call.args, argument_to_aggregate), &mut errors, |aggrs| {
+                r#try(convert_arguments(&method_call.args, argument_to_aggregate), &mut errors, |aggrs| {
                     query_data.aggregates = aggrs;
                 });
                 query_data.query_type = SqlQueryType::Aggregate;
@@ -471,8 +599,9 @@ fn process_methods(calls: &[MethodCall], table_name: &str, delete_position: &mut
             "all" => {
                 check_no_arguments(&method_call, &mut errors);
             },
-            "create" => {
+            "create" | "create_if_not_exists" => {
                 check_no_arguments(&method_call, &mut errors);
+                query_data.if_not_exists = method_call.name == "create_if_not_exists";
                 query_data.query_type = SqlQueryType::CreateTable;
             },
             "delete" => {
@@ -480,32 +609,48 @@ fn process_methods(calls: &[MethodCall], table_name: &str, delete_position: &mut
                 query_data.query_type = SqlQueryType::Delete;
                 *delete_position = Some(method_call.name.span());
             },
-            "drop" => {
+            "drop" | "drop_cascade" | "drop_if_exists" => {
                 check_no_arguments(&method_call, &mut errors);
+                if method_call.name == "drop_cascade" && cfg!(feature = "rusqlite") {
+                    let mut error = Error::new("cannot drop a table with CASCADE since SQLite does not support it",
+                        method_call.name.span());
+                    error.add_help("use drop() instead");
+                    errors.push(error);
+                }
+                query_data.cascade = method_call.name == "drop_cascade";
+                query_data.if_exists = method_call.name == "drop_if_exists";
                 query_data.query_type = SqlQueryType::Drop;
             },
             "filter" => {
                 if query_data.aggregates.is_empty() {
                     // If the aggregate() method was not called, filter() filters on the values
                     // (WHERE).
-                    try(expression_to_filter_expression(&method_call.args[0], table_name), &mut errors, |filter| {
+                    r#try(expression_to_filter_expression(&method_call.args[0], table_name), &mut errors, |filter| {
                         query_data.filter = filter;
                     });
                 }
                 else {
                     // If the aggregate() method was called, filter() filters on the aggregated
                     // values (HAVING).
-                    try(expression_to_aggregate_filter_expression(&method_call.args[0], &query_data.aggregates), &mut errors, |filter| {
+                    let filter = expression_to_aggregate_filter_expression(&method_call.args[0], &query_data.aggregates);
+                    r#try(filter, &mut errors, |filter| {
                         query_data.aggregate_filter = filter;
                     });
                 }
             },
+            "hard_delete" => {
+                check_no_arguments(&method_call, &mut errors);
+                check_soft_delete(method_call, table_name, is_soft_delete, &mut errors);
+                query_data.query_type = SqlQueryType::Delete;
+                query_data.soft_delete = false;
+                *delete_position = Some(method_call.name.span());
+            },
             "get" => {
                 if method_call.args.is_empty() {
                     query_data.limit = Limit::Index(number_literal(0));
                 }
                 else {
-                    try(get_expression_to_filter_expression(&method_call.args[0], table_name), &mut errors,
+                    r#try(get_expression_to_filter_expression(&method_call.args[0], table_name), &mut errors,
                         |(filter, use_pk, new_limit)| {
                             query_data.filter = filter;
                             query_data.use_pk = use_pk;
@@ -515,7 +660,7 @@ fn process_methods(calls: &[MethodCall], table_name: &str, delete_position: &mut
                 query_data.query_type = SqlQueryType::SelectOne;
             },
             "insert" => {
-                try(convert_arguments(&method_call.args, argument_to_assignment), &mut errors, |assigns| {
+                r#try(convert_arguments(&method_call.args, argument_to_assignment), &mut errors, |assigns| {
                     query_data.assignments = assigns;
                 });
                 if !query_data.assignments.is_empty() {
@@ -525,7 +670,7 @@ fn process_methods(calls: &[MethodCall], table_name: &str, delete_position: &mut
                 query_data.query_type = SqlQueryType::Insert;
             },
             "join" => {
-                try(convert_arguments(&method_call.args, |expr| argument_to_join(expr, table_name)), &mut errors,
+                r#try(convert_arguments(&method_call.args, |expr| argument_to_join(expr, table_name)), &mut errors,
                     |result| {
                         for new_join in result {
                             query_data.joins.push(new_join);
@@ -533,26 +678,47 @@ fn process_methods(calls: &[MethodCall], table_name: &str, delete_position: &mut
                     });
             },
             "limit" => {
-                try(argument_to_limit(&method_call.args[0]), &mut errors, |new_limit| {
+                r#try(argument_to_limit(&method_call.args[0]), &mut errors, |new_limit| {
                     query_data.limit = new_limit;
                 });
             },
+            "only" => {
+                if method_call.args.len() == 1 {
+                    query_data.only = path_expr_to_identifier(&method_call.args[0], &mut errors);
+                }
+                else {
+                    let length = method_call.args.len();
+                    errors.push(Error::new_with_code(
+                        &format!("this method takes 1 parameter but {} parameter{} supplied", length,
+                                 plural_verb(length)),
+                        method_call.name.span(), "E0061"
+                    ));
+                }
+            },
             "sort" => {
-                try(convert_arguments(&method_call.args, argument_to_order), &mut errors, |new_order| {
+                r#try(convert_arguments(&method_call.args, argument_to_order), &mut errors, |new_order| {
                     query_data.order = new_order;
                 });
             },
             "update" => {
-                try(convert_arguments(&method_call.args, argument_to_assignment), &mut errors, |assigns| {
+                r#try(convert_arguments(&method_call.args, argument_to_assignment), &mut errors, |assigns| {
                     query_data.assignments = assigns;
                 });
+                if let Some(version_field) = version_field(table_name) {
+                    check_version_assignment(&query_data.assignments, &version_field, method_call, &mut errors);
+                }
                 query_data.query_type = SqlQueryType::Update;
             },
             "values" => {
-                try(convert_arguments(&method_call.args, argument_to_group), &mut errors, |new_groups| {
+                r#try(convert_arguments(&method_call.args, argument_to_group), &mut errors, |new_groups| {
                     query_data.groups = new_groups;
                 });
             },
+            "with_deleted" => {
+                check_no_arguments(&method_call, &mut errors);
+                check_soft_delete(method_call, table_name, is_soft_delete, &mut errors);
+                query_data.soft_delete = false;
+            },
             _ => (), // NOTE: Nothing to do since check_methods() check for unknown method.
         }
     }
@@ -575,7 +741,7 @@ pub fn propose_similar_name<'a, T>(identifier: &str, choices: T, error: &mut Err
 
 /// If `result` is an `Err`, add the errors to `errors`.
 /// Otherwise, execute the closure.
-fn try<F: FnMut(T), T>(mut result: result::Result<T, Vec<Error>>, errors: &mut Vec<Error>, mut fn_using_result: F) {
+fn r#try<F: FnMut(T), T>(mut result: result::Result<T, Vec<Error>>, errors: &mut Vec<Error>, mut fn_using_result: F) {
     match result {
         Ok(value) => fn_using_result(value),
         Err(ref mut errs) => errors.append(errs),
@@ -28,15 +28,35 @@ use syn::{
     UnOp,
 };
 
-use ast::{
+use crate::ast::{
     Expression,
     Order,
     Query,
     first_token_span,
 };
-use error::{Error, Result, res};
+use crate::error::{Error, Result, res};
+use crate::state::is_decimal_field;
 use super::path_expr_to_identifier;
 
+/// Check that the rows are not sorted by a decimal field, since SQLite stores it as text.
+pub fn analyze_order_types(order: &[Order], table_name: &str, errors: &mut Vec<Error>) {
+    if !cfg!(feature = "rusqlite") {
+        return;
+    }
+    for order in order {
+        match *order {
+            Order::Ascending(ref ident) | Order::Descending(ref ident)
+                if is_decimal_field(table_name, &ident.to_string()) =>
+                errors.push(Error::new(
+                    &format!("cannot sort by the decimal field `{}` because SQLite stores the decimal numbers as text",
+                        ident),
+                    ident.span(),
+                )),
+            _ => (),
+        }
+    }
+}
+
 /// Convert an `Expression` to an `Order`.
 pub fn argument_to_order(arg: &Expression) -> Result<Order> {
     let mut errors = vec![];
@@ -0,0 +1,157 @@
+/*
+ * Copyright (c) 2017-2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+/// Analyzer for the subqueries used in the filter() method.
+
+use proc_macro2::Span;
+use syn::{Expr, Ident, Macro, parse2};
+use syn::spanned::Spanned;
+
+use crate::ast::{FilterExpression, FilterValue, Query, SubQuery, SubQueryOperator};
+use crate::error::{Error, Result, res};
+use crate::optimizer::optimize;
+use crate::parser::Parser;
+use super::{analyze_query, analyze_types};
+use super::method::analyze_methods;
+
+/// The data needed to typecheck a subquery.
+pub struct SubQueryCheck {
+    /// The table and the field compared to the column returned by the subquery.
+    pub field: Option<(String, Ident)>,
+    /// The column returned by the subquery.
+    pub column: Option<Ident>,
+    /// The table of the subquery.
+    pub table: String,
+}
+
+/// Check if the `expression` is a nested `sql!()` macro call.
+pub fn is_subquery(expression: &Expr) -> bool {
+    if let Expr::Macro(ref mac) = *expression {
+        return mac.mac.path.segments.last()
+            .map_or(false, |segment| segment.value().ident == "sql");
+    }
+    false
+}
+
+/// Convert a nested `sql!()` macro call to a `SubQuery`.
+/// The `operator` is used to check that the subquery returns a single column when needed.
+pub fn macro_to_subquery(mac: &Macro, operator: SubQueryOperator) -> Result<SubQuery> {
+    let span = mac.span();
+    let expr: Expr =
+        match parse2(mac.tts.clone()) {
+            Ok(expr) => expr,
+            Err(error) => return Err(vec![Error::new(&format!("cannot parse expression in sql!(): {}", error), span)]),
+        };
+    let parser = Parser::new();
+    let calls = parser.parse(&expr)?;
+    let mut query = analyze_query(&calls, true)?;
+    analyze_methods(&query)?;
+    optimize(&mut query);
+    analyze_types(&query)?;
+
+    let mut errors = vec![];
+    check_subquery(&query, operator, span, &mut errors);
+
+    res(SubQuery {
+        query: Box::new(query),
+    }, errors)
+}
+
+/// Check that the `query` can be used as a subquery with the `operator`.
+fn check_subquery(query: &Query, operator: SubQueryOperator, span: Span, errors: &mut Vec<Error>) {
+    let single_column =
+        match *query {
+            Query::Aggregate { ref aggregates, ref joins, .. } => {
+                check_no_joins(joins.is_empty(), span, errors);
+                aggregates.len() == 1
+            },
+            Query::Select { ref joins, ref only, .. } => {
+                check_no_joins(joins.is_empty(), span, errors);
+                only.is_some()
+            },
+            _ => {
+                errors.push(Error::new("only all(), filter(), get() and aggregate() queries can be used as subqueries",
+                    span));
+                return;
+            },
+        };
+
+    if let SubQueryOperator::Exists = operator {
+        return;
+    }
+
+    if !single_column {
+        let mut error = Error::new("this subquery must return a single column", span);
+        error.add_help("use only(field) or aggregate() with a single aggregate function");
+        errors.push(error);
+    }
+}
+
+/// Add an error if a subquery contains joins.
+fn check_no_joins(no_joins: bool, span: Span, errors: &mut Vec<Error>) {
+    if !no_joins {
+        errors.push(Error::new("cannot call the join() method in a subquery", span));
+    }
+}
+
+/// Get the subqueries (including the nested ones) to be able to typecheck them.
+pub fn get_subquery_checks(query: &Query) -> Vec<SubQueryCheck> {
+    let mut checks = vec![];
+    match *query {
+        Query::Aggregate { ref filter, .. } | Query::Delete { ref filter, .. } | Query::Select { ref filter, .. } |
+            Query::Update { ref filter, .. } => add_subquery_checks(filter, &mut checks),
+        Query::AlterTable { .. } | Query::CreateTable { .. } | Query::Drop { .. } | Query::Insert { .. } => (),
+    }
+    checks
+}
+
+fn add_subquery_checks(filter: &FilterExpression, checks: &mut Vec<SubQueryCheck>) {
+    match *filter {
+        FilterExpression::Filters(ref filters) => {
+            add_subquery_checks(&filters.operand1, checks);
+            add_subquery_checks(&filters.operand2, checks);
+        },
+        FilterExpression::NegFilter(ref filter) | FilterExpression::ParenFilter(ref filter) =>
+            add_subquery_checks(filter, checks),
+        FilterExpression::SubQuery(ref filter) => {
+            let query = &*filter.query.query;
+            let (table, column) =
+                match *query {
+                    Query::Aggregate { ref aggregates, ref table, .. } =>
+                        (table, aggregates.first().and_then(|aggregate| aggregate.field.clone())),
+                    Query::Select { ref only, ref table, .. } => (table, only.clone()),
+                    _ => unreachable!("subquery which is not an aggregate or a select"),
+                };
+            let field =
+                match filter.operand1 {
+                    Some(FilterValue::Identifier(ref table, ref identifier)) => Some((table.clone(), identifier.clone())),
+                    _ => None,
+                };
+            checks.push(SubQueryCheck {
+                column,
+                field,
+                table: table.clone(),
+            });
+            checks.extend(get_subquery_checks(query));
+        },
+        FilterExpression::Filter(_) | FilterExpression::FilterValue(_) | FilterExpression::NoFilters => (),
+    }
+}
@@ -27,10 +27,11 @@ use syn::{
     parse,
 };
 
-use ast::{
+use crate::ast::{
     Aggregate,
     AggregateFilterExpression,
     Assignment,
+    DateExpression,
     Expression,
     FilterExpression,
     FilterValue,
@@ -38,10 +39,13 @@ use ast::{
     MethodCall,
     Query,
 };
+use crate::state::version_field;
 
 /// A Rust expression to be send as a parameter to the SQL query function.
 #[derive(Clone, Debug)]
 pub struct Arg {
+    /// The field of the struct embedded in `field_name`, if any.
+    pub embedded_field: Option<Ident>,
     pub expression: Expression,
     pub field_name: Option<Ident>,
     pub field_name_prefix: Option<String>,
@@ -55,6 +59,7 @@ fn add(arguments: &mut Args, literals: &mut Args, field_name: Option<Ident>, fie
        expr: Expression)
 {
     add_expr(arguments, literals, Arg {
+        embedded_field: None,
         expression: expr,
         field_name_prefix,
         field_name,
@@ -77,6 +82,15 @@ fn add_expr(arguments: &mut Args, literals: &mut Args, arg: Arg) {
         literals.push(arg);
         return;
     }
+    if let Some(date) = DateExpression::from_expression(&arg.expression) {
+        // NOTE: a date expression is computed by the database, so only its interval amounts are sent
+        // as parameters. It is still type checked as a whole, like a literal.
+        for amount in date.amounts() {
+            add_with_method(arguments, literals, amount.clone());
+        }
+        literals.push(arg);
+        return;
+    }
     arguments.push(arg);
 }
 
@@ -97,6 +111,10 @@ fn add_filter_arguments(filter: FilterExpression, args: &mut Args, literals: &mu
         FilterExpression::ParenFilter(filter) => {
             add_filter_arguments(*filter, args, literals);
         },
+        FilterExpression::SubQuery(filter) => {
+            // NOTE: the arguments of the subquery are numbered after the arguments preceding it.
+            add_query_arguments(*filter.query.query, args, literals);
+        },
         FilterExpression::FilterValue(filter_value) => {
             add_filter_value_arguments(&filter_value.node, args, literals, None);
         },
@@ -138,6 +156,7 @@ fn add_limit_arguments(limit: Limit, arguments: &mut Args, literals: &mut Args)
             let expression = parse((quote! { #expression2 - #offset }).into())
                 .expect("Subtraction quoted expression");
             add_expr(arguments, literals, Arg {
+                embedded_field: None,
                 expression,
                 field_name: None,
                 field_name_prefix: None,
@@ -152,12 +171,23 @@ fn add_limit_arguments(limit: Limit, arguments: &mut Args, literals: &mut Args)
 fn add_with_method(args: &mut Args, literals: &mut Args, expr: Expression)
 {
     add_expr(args, literals, Arg {
+        embedded_field: None,
         expression: expr,
         field_name: None,
         field_name_prefix: None,
     });
 }
 
+/// Add the arguments of a method call, starting with the ones of the method calls it is chained on.
+fn add_method_call_arguments(call: &MethodCall, args: &mut Args, literals: &mut Args) {
+    if let Some(ref object_call) = call.object_call {
+        add_method_call_arguments(object_call, args, literals);
+    }
+    for arg in &call.arguments {
+        add_with_method(args, literals, arg.clone());
+    }
+}
+
 fn add_aggregate_filter_value_arguments(_aggregate: &Aggregate, args: &mut Args, literals: &mut Args,
                                         expression: Option<Expression>)
 {
@@ -170,6 +200,16 @@ fn add_filter_value_arguments(filter_value: &FilterValue, args: &mut Args, liter
                               expression: Option<Expression>)
 {
     match *filter_value {
+        FilterValue::EmbeddedField(ref table, ref identifier, ref subfield) => {
+            if let Some(expr) = expression {
+                add_expr(args, literals, Arg {
+                    embedded_field: Some(subfield.clone()),
+                    expression: expr,
+                    field_name: Some(identifier.clone()),
+                    field_name_prefix: Some(table.clone()),
+                });
+            }
+        },
         FilterValue::Identifier(ref table, ref identifier) => {
             // It is possible to have an identifier without expression, when the identifier is a
             // boolean field name, hence this condition.
@@ -177,9 +217,11 @@ fn add_filter_value_arguments(filter_value: &FilterValue, args: &mut Args, liter
                 add(args, literals, Some(identifier.clone()), Some(table.clone()), expr);
             }
         },
-        FilterValue::MethodCall(MethodCall { ref arguments, .. }) => {
-            for arg in arguments {
-                add_with_method(args, literals, arg.clone());
+        FilterValue::MethodCall(ref call) => {
+            add_method_call_arguments(call, args, literals);
+            // The value compared to the result of the method call comes after its arguments.
+            if let Some(expr) = expression {
+                add_with_method(args, literals, expr);
             }
         },
         FilterValue::None => unreachable!("FilterValue::None in add_filter_value_arguments()"),
@@ -195,29 +237,41 @@ fn add_filter_value_arguments(filter_value: &FilterValue, args: &mut Args, liter
 pub fn arguments(query: Query) -> (Args, Args) {
     let mut arguments = vec![];
     let mut literals = vec![];
+    add_query_arguments(query, &mut arguments, &mut literals);
+    (arguments, literals)
+}
 
+/// Add the arguments from the `query` in the same order as they appear in the SQL query.
+fn add_query_arguments(query: Query, arguments: &mut Args, literals: &mut Args) {
     match query {
         Query::Aggregate { aggregate_filter, filter, .. } => {
-            add_filter_arguments(filter, &mut arguments, &mut literals);
-            add_aggregate_filter_arguments(aggregate_filter, &mut arguments, &mut literals);
+            add_filter_arguments(filter, arguments, literals);
+            add_aggregate_filter_arguments(aggregate_filter, arguments, literals);
         },
+        Query::AlterTable { .. } => (), // No arguments.
         Query::CreateTable { .. } => (), // No arguments.
         Query::Delete { filter, .. } => {
-            add_filter_arguments(filter, &mut arguments, &mut literals);
+            add_filter_arguments(filter, arguments, literals);
         },
         Query::Drop { .. } => (), // No arguments.
         Query::Insert { assignments, .. } => {
-            add_assignments(assignments, &mut arguments, &mut literals);
+            add_assignments(assignments, arguments, literals);
         },
         Query::Select { filter, limit, ..} => {
-            add_filter_arguments(filter, &mut arguments, &mut literals);
-            add_limit_arguments(limit, &mut arguments, &mut literals);
+            add_filter_arguments(filter, arguments, literals);
+            add_limit_arguments(limit, arguments, literals);
         },
-        Query::Update { assignments, filter, .. } => {
-            add_assignments(assignments, &mut arguments, &mut literals);
-            add_filter_arguments(filter, &mut arguments, &mut literals);
+        Query::Update { assignments, filter, table, .. } => {
+            // NOTE: the current version is compared after the filter.
+            let version_field = version_field(&table);
+            let (version_assignments, assignments) = assignments.into_iter()
+                .partition(|assignment| match (&assignment.identifier, &version_field) {
+                    (&Some(ref ident), &Some(ref version_field)) => ident == version_field,
+                    _ => false,
+                });
+            add_assignments(assignments, arguments, literals);
+            add_filter_arguments(filter, arguments, literals);
+            add_assignments(version_assignments, arguments, literals);
         },
     }
-
-    (arguments, literals)
 }
@@ -25,19 +25,36 @@ use std::fmt::{Display, Error, Formatter};
 
 use proc_macro2::{Span, TokenStream};
 use quote::ToTokens;
-use syn::{Expr, Ident};
+use syn::{BinOp, Expr, Ident};
 
+use crate::state::version_field;
 pub type Expression = Expr;
 pub type Groups = Vec<Ident>;
 
 /// `Aggregate` for use in SQL Aggregate `Query`.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct Aggregate {
     pub field: Option<Ident>,
     pub function: String,
     pub has_name_in_query: bool,
     pub result_name: Option<Ident>,
-    pub sql_function: String,
+    /// The position of the aggregate function name.
+    pub span: Span,
+    /// The SQL function, or `None` for a user-defined aggregate (declared with #[sql_aggregate]).
+    pub sql_function: Option<String>,
+}
+
+impl Default for Aggregate {
+    fn default() -> Self {
+        Self {
+            field: None,
+            function: String::new(),
+            has_name_in_query: false,
+            result_name: None,
+            span: Span::call_site(),
+            sql_function: None,
+        }
+    }
 }
 
 /// `AggregateFilter` for SQL `Query` (HAVING clause).
@@ -87,6 +104,12 @@ pub struct Assignment {
     pub value: Expression,
 }
 
+/// Find the assignment to the `field` in `assignments`.
+pub fn find_assignment<'a>(assignments: &'a [Assignment], field: &str) -> Option<&'a Assignment> {
+    assignments.iter()
+        .find(|assignment| assignment.identifier.as_ref().map_or(false, |ident| ident == field))
+}
+
 /// `AssignmentOperator` for use in SQL Insert and Update `Query`.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum AssignmentOperator {
@@ -114,6 +137,83 @@ impl Display for AssignmentOperator {
     }
 }
 
+/// A date and time computed by the database, e.g. `tql::now() - tql::days(7)`.
+#[derive(Debug)]
+pub enum DateExpression {
+    /// Add or subtract an interval (e.g. `tql::days(7)`) from a date.
+    Interval {
+        amount: Expression,
+        date: Box<DateExpression>,
+        operator: IntervalOperator,
+        /// The interval function, e.g. `days`.
+        unit: Ident,
+    },
+    /// Comes from `tql::now()`.
+    Now(Ident),
+}
+
+impl DateExpression {
+    /// Convert an expression to a `DateExpression`.
+    /// Returns `None` if the expression is not computed from `tql::now()`.
+    pub fn from_expression(expr: &Expression) -> Option<Self> {
+        match *expr {
+            Expr::Binary(ref binary) => {
+                let operator =
+                    match binary.op {
+                        BinOp::Add(_) => IntervalOperator::Add,
+                        BinOp::Sub(_) => IntervalOperator::Sub,
+                        _ => return None,
+                    };
+                if let Expr::Call(ref call) = *binary.right {
+                    let unit = tql_function_name(&call.func)?;
+                    if INTERVAL_UNITS.contains(&unit.to_string().as_str()) && call.args.len() == 1 {
+                        return Some(DateExpression::Interval {
+                            amount: call.args[0].clone(),
+                            date: Box::new(DateExpression::from_expression(&binary.left)?),
+                            operator,
+                            unit,
+                        });
+                    }
+                }
+                None
+            },
+            Expr::Call(ref call) if call.args.is_empty() => {
+                let function = tql_function_name(&call.func)?;
+                if function == "now" {
+                    Some(DateExpression::Now(function))
+                }
+                else {
+                    None
+                }
+            },
+            Expr::Paren(ref paren) => DateExpression::from_expression(&paren.expr),
+            _ => None,
+        }
+    }
+
+    /// Get the interval amounts, in the order they appear in the query.
+    pub fn amounts(&self) -> Vec<&Expression> {
+        match *self {
+            DateExpression::Interval { ref amount, ref date, .. } => {
+                let mut amounts = date.amounts();
+                amounts.push(amount);
+                amounts
+            },
+            DateExpression::Now(_) => vec![],
+        }
+    }
+}
+
+/// The interval functions available in date expressions.
+pub const INTERVAL_UNITS: [&str; 6] = ["seconds", "minutes", "hours", "days", "months", "years"];
+
+/// The operator of an interval in a `DateExpression`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntervalOperator {
+    Add,
+    Sub,
+}
+
 /// `Filter` for SQL `Query` (WHERE clause).
 #[derive(Debug)]
 pub struct Filter {
@@ -125,7 +225,8 @@ pub struct Filter {
     pub operand2: Expression,
 }
 
-/// Either a single `Filter`, `Filters`, `NegFilter`, `NoFilters`, `ParenFilter` or a `FilterValue`.
+/// Either a single `Filter`, `Filters`, `NegFilter`, `NoFilters`, `ParenFilter`, `SubQuery` or a
+/// `FilterValue`.
 #[derive(Debug)]
 pub enum FilterExpression {
     Filter(Filter),
@@ -133,6 +234,7 @@ pub enum FilterExpression {
     NegFilter(Box<FilterExpression>),
     NoFilters,
     ParenFilter(Box<FilterExpression>),
+    SubQuery(SubQueryFilter),
     FilterValue(WithSpan<FilterValue>),
 }
 
@@ -153,10 +255,11 @@ pub struct Filters {
     pub operand2: Box<FilterExpression>,
 }
 
-/// Either an identifier or a method call.
+/// Either an identifier (possibly a field of an embedded struct) or a method call.
 #[derive(Debug)]
 pub enum FilterValue {
     None,
+    EmbeddedField(String, Ident, Ident),
     Identifier(String, Ident),
     MethodCall(MethodCall),
     PrimaryKey(String),
@@ -205,8 +308,13 @@ pub enum LogicalOperator {
 pub struct MethodCall {
     pub arguments: Vec<Expression>,
     pub method_name: Ident,
+    /// The method call this method is called on, for chained calls like `field.trim().len()`.
+    pub object_call: Option<Box<MethodCall>>,
+    /// The field at the start of the chain.
     pub object_name: Ident,
     pub position: Span,
+    /// The table of the field at the start of the chain.
+    pub table: String,
 }
 
 /// An SQL ORDER BY clause.
@@ -219,6 +327,34 @@ pub enum Order {
     NoOrder,
 }
 
+/// A nested `sql!()` query used as an operand in a filter.
+#[derive(Debug)]
+pub struct SubQuery {
+    pub query: Box<Query>,
+}
+
+/// A `Filter` where the right operand is a nested query.
+#[derive(Debug)]
+pub struct SubQueryFilter {
+    /// The filter value to be compared to the nested query (`None` for `EXISTS`).
+    pub operand1: Option<FilterValue>,
+    /// The `operator` used to compare `operand1` to the nested query.
+    pub operator: SubQueryOperator,
+    /// The nested query.
+    pub query: SubQuery,
+}
+
+/// Operator used in a `SubQueryFilter`.
+#[derive(Clone, Copy, Debug)]
+pub enum SubQueryOperator {
+    /// Comes from `exists(sql!(…))`.
+    Exists,
+    /// Comes from `field.in_(sql!(…))`.
+    In,
+    /// Comes from `field == sql!(…)`, `field < sql!(…)`, ….
+    Relational(RelationalOperator),
+}
+
 /// `RelationalOperator` to be used in a `Filter`.
 #[derive(Clone, Copy, Debug)]
 pub enum RelationalOperator {
@@ -239,17 +375,33 @@ pub enum Query {
         filter: FilterExpression,
         groups: Groups,
         joins: Vec<Join>,
+        /// Exclude the rows marked as deleted (comes from `#[sql(soft_delete)]`).
+        soft_delete: bool,
+        table: String,
+    },
+    /// An `ALTER TABLE` query (comes from `add_column()`, `drop_column()`, `rename_column()` or
+    /// `rename_to()`).
+    AlterTable {
+        alteration: TableAlteration,
         table: String,
     },
     CreateTable {
+        /// Do not fail if the table already exists (comes from `create_if_not_exists()`).
+        if_not_exists: bool,
         table: String,
     },
     Delete {
         filter: FilterExpression,
+        /// Mark the rows as deleted instead of deleting them (comes from `#[sql(soft_delete)]`).
+        soft_delete: bool,
         table: String,
         use_pk: bool,
     },
     Drop {
+        /// Also drop the objects depending on the table (comes from `drop_cascade()`).
+        cascade: bool,
+        /// Do not fail if the table does not exist (comes from `drop_if_exists()`).
+        if_exists: bool,
         table: String,
     },
     Insert {
@@ -261,13 +413,19 @@ pub enum Query {
         get: bool,
         joins: Vec<Join>,
         limit: Limit,
+        /// The only field to select (comes from `only(field)` in a subquery).
+        only: Option<Ident>,
         order: Vec<Order>,
+        /// Exclude the rows marked as deleted (comes from `#[sql(soft_delete)]`).
+        soft_delete: bool,
         table: String,
         use_pk: bool,
     },
     Update {
         assignments: Vec<Assignment>,
         filter: FilterExpression,
+        /// Only update the rows which are not marked as deleted (comes from `#[sql(soft_delete)]`).
+        soft_delete: bool,
         table: String,
         use_pk: bool,
     },
@@ -277,11 +435,31 @@ pub enum Query {
 pub enum QueryType {
     AggregateMulti,
     AggregateOne,
-    Create,
+    /// A query made of possibly many statements without arguments (e.g. the table rebuild done by
+    /// SQLite to drop a column or the creation of a table with its indexes).
+    Batch,
     Exec,
     InsertOne,
     SelectMulti,
     SelectOne,
+    /// An update which fails if no row has the expected version (comes from `#[sql(version)]`).
+    VersionedUpdate,
+}
+
+/// The change made to a table by a `Query::AlterTable`.
+#[derive(Debug)]
+pub enum TableAlteration {
+    /// Comes from `add_column(field)`.
+    AddColumn(Ident),
+    /// Comes from `drop_column(column)`.
+    DropColumn(Ident),
+    /// Comes from `rename_column(old, new)`.
+    RenameColumn {
+        new: Ident,
+        old: Ident,
+    },
+    /// Comes from `rename_to(table)`.
+    RenameTo(Ident),
 }
 
 /// An SQL field with its type.
@@ -313,7 +491,8 @@ pub fn query_type(query: &Query) -> QueryType {
             }
             typ
         },
-        Query::CreateTable { .. } => QueryType::Create,
+        Query::AlterTable { .. } | Query::CreateTable { .. } => QueryType::Batch,
+        Query::Update { ref table, .. } if version_field(table).is_some() => QueryType::VersionedUpdate,
         Query::Delete { .. } | Query::Drop { .. } | Query::Update { .. } => QueryType::Exec,
     }
 }
@@ -324,6 +503,20 @@ pub struct WithSpan<T> {
     pub span: Span,
 }
 
+/// Get the name of a function of the tql crate (e.g. `now` in `tql::now()`).
+/// The functions called without the `tql` path are user functions, so they are not considered.
+fn tql_function_name(func: &Expr) -> Option<Ident> {
+    if let Expr::Path(ref path) = *func {
+        let segments = &path.path.segments;
+        if path.qself.is_none() && segments.len() == 2 && segments[0].ident == "tql" &&
+            segments.iter().all(|segment| segment.arguments.is_empty())
+        {
+            return Some(segments[1].ident.clone());
+        }
+    }
+    None
+}
+
 /// Get the position of the first token of the expression.
 pub fn first_token_span(expr: &Expr) -> Span {
     let tokens: TokenStream = expr.into_token_stream().into();
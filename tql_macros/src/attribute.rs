@@ -27,15 +27,21 @@ use std::fmt::Write;
 use syn::{
     self,
     AngleBracketedGenericArguments,
+    Attribute,
     Field,
+    Lit,
+    Meta,
+    MetaNameValue,
+    NestedMeta,
     PathArguments,
     TypePath,
 };
 use syn::spanned::Spanned;
 
-use ast::WithSpan;
-use state::{BothTypes, SqlFields};
-use types::Type;
+use crate::ast::WithSpan;
+use crate::error::Error;
+use crate::state::{BothTypes, SqlFields};
+use crate::types::Type;
 
 /// Convert a type from the Rust AST to the SQL `Type`.
 //#[allow(cmp_owned)]
@@ -85,3 +91,93 @@ pub fn fields_vec_to_hashmap(fields: &[Field]) -> SqlFields {
     }
     sql_fields
 }
+
+/// The options specified in the `#[sql(…)]` attributes of a field.
+#[derive(Default)]
+pub struct FieldAttributes {
+    /// The field is set to the current time when a row is updated (and inserted).
+    pub auto_now: bool,
+    /// The field is set to the current time when a row is inserted.
+    pub auto_now_add: bool,
+    /// The field is a struct deriving `SqlEmbed` whose fields are stored in the table.
+    pub embed: bool,
+    /// An index is created on the column.
+    pub index: bool,
+    /// The field is the version of the row, incremented by each update (optimistic locking).
+    pub version: bool,
+}
+
+/// Get the options from the `#[sql(…)]` attributes of a field.
+pub fn parse_field_attributes(attributes: &[Attribute], errors: &mut Vec<Error>) -> FieldAttributes {
+    let mut field_attributes = FieldAttributes::default();
+    for attribute in attributes {
+        if !attribute.path.is_ident("sql") {
+            continue;
+        }
+        let arguments =
+            match attribute.parse_meta() {
+                Ok(Meta::List(list)) => list.nested,
+                _ => {
+                    errors.push(Error::new("expected #[sql(…)]", attribute.span()));
+                    continue;
+                },
+            };
+        for argument in arguments {
+            match argument {
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "auto_now" => field_attributes.auto_now = true,
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "auto_now_add" =>
+                    field_attributes.auto_now_add = true,
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "embed" => field_attributes.embed = true,
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "index" => field_attributes.index = true,
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "version" => field_attributes.version = true,
+                ref argument => errors.push(Error::new(
+                    "unknown argument, expected one of `auto_now`, `auto_now_add`, `embed`, `index` or `version`",
+                    argument.span())),
+            }
+        }
+    }
+    field_attributes
+}
+
+/// The options specified in the `#[sql(…)]` attributes of a table.
+#[derive(Default)]
+pub struct TableAttributes {
+    /// The path of the snapshot of the database schema the table is checked against (relative to
+    /// the crate root).
+    pub schema: Option<WithSpan<String>>,
+    /// The rows are marked as deleted with the `deleted_at` field instead of being deleted.
+    pub soft_delete: bool,
+}
+
+/// Get the options from the `#[sql(…)]` attributes of a table.
+pub fn parse_table_attributes(attributes: &[Attribute], errors: &mut Vec<Error>) -> TableAttributes {
+    let mut table_attributes = TableAttributes::default();
+    for attribute in attributes {
+        if !attribute.path.is_ident("sql") {
+            continue;
+        }
+        let arguments =
+            match attribute.parse_meta() {
+                Ok(Meta::List(list)) => list.nested,
+                _ => {
+                    errors.push(Error::new("expected #[sql(…)]", attribute.span()));
+                    continue;
+                },
+            };
+        for argument in arguments {
+            match argument {
+                NestedMeta::Meta(Meta::Word(ref ident)) if ident == "soft_delete" =>
+                    table_attributes.soft_delete = true,
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue { ref ident, lit: Lit::Str(ref path), .. }))
+                    if ident == "schema" =>
+                    table_attributes.schema = Some(WithSpan {
+                        node: path.value(),
+                        span: path.span(),
+                    }),
+                ref argument => errors.push(Error::new(
+                    "unknown argument, expected `schema = \"…\"` or `soft_delete`", argument.span())),
+            }
+        }
+    }
+    table_attributes
+}
@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Structs embedded in a table with the `#[derive(SqlEmbed)]` attribute.
+//!
+//! The fields of an embedded struct are stored in the table containing the `#[sql(embed)]` field,
+//! each in a column prefixed by the name of this field (e.g. `address_city`).
+
+use proc_macro2::{Span, TokenStream};
+use syn::{
+    Fields,
+    FieldsNamed,
+    Ident,
+    ItemStruct,
+};
+use syn::spanned::Spanned;
+
+use crate::attribute::{field_ty_to_type, parse_field_attributes};
+use crate::error::{Error, Result, res};
+use crate::gen::{check_column_type, embed_methods, macros_module};
+use crate::state::{EmbedField, embed_metadata};
+use crate::string::token_to_string;
+use crate::types::Type;
+use crate::{concat_token_stream, empty_token_stream};
+
+/// Implement `SqlEmbed` for a struct.
+pub fn embed_impl(item_struct: &ItemStruct) -> Result<TokenStream> {
+    let mut errors = vec![];
+    let embed_ident = &item_struct.ident;
+    let named =
+        match item_struct.fields {
+            Fields::Named(FieldsNamed { ref named , .. }) => named,
+            _ => return Err(vec![Error::new("Expected normal struct, found", embed_ident.span())]), // TODO: improve this message.
+        };
+    if !item_struct.generics.params.is_empty() {
+        errors.push(Error::new("generic structs cannot be embedded", item_struct.generics.span()));
+    }
+    let mut impls = empty_token_stream();
+    for field in named {
+        let field_ident = field.ident.as_ref().expect("field has name");
+        let attributes = parse_field_attributes(&field.attrs, &mut errors);
+        if attributes.embed {
+            errors.push(Error::new("nested embedded structs are not supported", field_ident.span()));
+        }
+        if attributes.auto_now || attributes.auto_now_add || attributes.version {
+            errors.push(Error::new("the fields of embedded structs cannot be set automatically",
+                field_ident.span()));
+        }
+        if attributes.index {
+            errors.push(Error::new("embedded fields cannot be indexed", field_ident.span()));
+        }
+        let typ = field_ty_to_type(&field.ty);
+        match typ.node {
+            Type::Custom(_) | Type::Serial =>
+                errors.push(Error::new("primary keys and foreign keys cannot be embedded", typ.span)),
+            _ => impls = concat_token_stream(impls, check_column_type(&field.ty, &typ, &mut errors)),
+        }
+    }
+
+    let impls = TokenStream::from(impls);
+    let methods = embed_methods(item_struct);
+    let code = quote! {
+        #impls
+        #methods
+    };
+    res(code, errors)
+}
+
+/// Create the macro calling back the `#[SqlTable]` attribute of a table embedding the struct with
+/// the fields of the struct (given by `embed_metadata()`), which are added after the fields of the
+/// other embedded structs when the callback is another `tql_<Struct>_embed!()` macro.
+pub fn embed_macro(item_struct: &ItemStruct) -> TokenStream {
+    let embed_ident = &item_struct.ident;
+    let fields: Vec<_> = item_struct.fields.iter()
+        .filter_map(|field| Some(EmbedField {
+            name: field.ident.as_ref()?.to_string(),
+            typ: token_to_string(&field.ty),
+        }))
+        .collect();
+    let metadata = embed_metadata(&fields);
+    let macro_name = Ident::new(&format!("tql_{}_embed", embed_ident), Span::call_site());
+    let macros_module = macros_module(&item_struct.vis, embed_ident, &["embed"]);
+    quote! {
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! #macro_name {
+            ($(@tql_embed $embed:ident $fields:tt)* [$($callback:tt)*] $($input:tt)*) => {
+                $($callback)*!($(@tql_embed $embed $fields)* @tql_embed #embed_ident #metadata $($input)*);
+            };
+        }
+
+        #macros_module
+    }
+}
@@ -0,0 +1,236 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Enums stored in a column with the `#[derive(SqlEnum)]` attribute.
+//!
+//! The derive implements `tql::SqlType` for the enum so that it can be used as a field type of a
+//! table. The storage is chosen with the `#[sql(storage = "…")]` attribute: the name of the
+//! variant (`text`, the default), its discriminant (`integer`) or a PostgreSQL enum type (`native`).
+
+use proc_macro2::{Span, TokenStream};
+use syn::{
+    Attribute,
+    Fields,
+    Ident,
+    ItemEnum,
+    Lit,
+    Meta,
+    NestedMeta,
+};
+use syn::spanned::Spanned;
+
+use crate::error::{Error, Result, res};
+use crate::gen::{macros_module, metadata_macro};
+use crate::plugin::string_literal;
+
+/// The way the enum values are stored in the database.
+#[derive(Clone, Copy)]
+enum Storage {
+    /// The discriminant of the variant, in an INTEGER column.
+    Integer,
+    /// The name of the variant, in a column of a PostgreSQL enum type.
+    Native,
+    /// The name of the variant, in a text column.
+    Text,
+}
+
+/// Implement `SqlType` for a fieldless enum.
+pub fn enum_impl(item_enum: &ItemEnum) -> Result<TokenStream> {
+    let mut errors = vec![];
+    let storage = parse_storage(&item_enum.attrs, &mut errors);
+    let enum_ident = &item_enum.ident;
+    if !item_enum.generics.params.is_empty() {
+        errors.push(Error::new("generic enums cannot be stored in a column", item_enum.generics.span()));
+    }
+    let mut variants = vec![];
+    for variant in &item_enum.variants {
+        match variant.fields {
+            Fields::Unit => variants.push(&variant.ident),
+            _ => errors.push(Error::new("only fieldless enums can be stored in a column", variant.span())),
+        }
+    }
+    let variant_paths: Vec<_> = variants.iter()
+        .map(|variant| quote! { #enum_ident::#variant })
+        .collect();
+    let variant_paths = &variant_paths;
+    let variant_names: Vec<_> = variants.iter()
+        .map(|variant| string_literal(&variant.to_string()))
+        .collect();
+    let variant_names = &variant_names;
+    let error = format!("invalid value `{{}}` for enum {}", enum_ident);
+
+    let code =
+        match storage {
+            Storage::Integer => {
+                let variant_values = variant_paths.iter()
+                    .map(|path| quote! { #path as i32 });
+                let variant_values = &variant_values.collect::<Vec<_>>();
+                quote! {
+                    impl ::tql::SqlType for #enum_ident {
+                        type Underlying = i32;
+
+                        fn to_sql_value(&self) -> i32 {
+                            match *self {
+                                #(#variant_paths => #variant_values,)*
+                            }
+                        }
+
+                        fn from_sql_value(value: i32)
+                            -> ::std::result::Result<Self, Box<::std::error::Error + Send + Sync>>
+                        {
+                            #(if value == #variant_values {
+                                return Ok(#variant_paths);
+                            })*
+                            Err(format!(#error, value).into())
+                        }
+                    }
+                }
+            },
+            Storage::Native => {
+                let enum_name = enum_ident.to_string();
+                let create_query = format!("CREATE TYPE {} AS ENUM ({})", enum_name,
+                    variants.iter()
+                        .map(|variant| format!("'{}'", variant))
+                        .collect::<Vec<_>>()
+                        .join(", "));
+                // NOTE: PostgreSQL has no CREATE TYPE IF NOT EXISTS, so the error is ignored instead.
+                let create_query_if_not_exists =
+                    format!("DO $$ BEGIN {}; EXCEPTION WHEN duplicate_object THEN NULL; END $$", create_query);
+                let drop_query = format!("DROP TYPE {}", enum_name);
+                let drop_query_cascade = format!("DROP TYPE {} CASCADE", enum_name);
+                let drop_query_if_exists = format!("DROP TYPE IF EXISTS {}", enum_name);
+                let create_macro_name = Ident::new(&format!("tql_{}_create_query", enum_ident), Span::call_site());
+                let drop_macro_name = Ident::new(&format!("tql_{}_drop_query", enum_ident), Span::call_site());
+                if cfg!(feature = "rusqlite") {
+                    errors.push(Error::new("native enums are only supported on PostgreSQL", enum_ident.span()));
+                }
+                // NOTE: the type has the metadata of a table without fields, so that sql!(Enum.create())
+                // can ask for it.
+                let metadata_macro = metadata_macro(enum_ident, "");
+                let macros_module =
+                    macros_module(&item_enum.vis, enum_ident, &["create_query", "drop_query", "metadata"]);
+                quote! {
+                    impl ::tql::EnumType for #enum_ident {
+                        const NAME: &'static str = #enum_name;
+                    }
+
+                    impl ::tql::SqlType for #enum_ident {
+                        type Underlying = ::tql::EnumValue<#enum_ident>;
+
+                        fn to_sql_value(&self) -> Self::Underlying {
+                            let name =
+                                match *self {
+                                    #(#variant_paths => #variant_names,)*
+                                };
+                            ::tql::EnumValue::new(name)
+                        }
+
+                        fn from_sql_value(value: Self::Underlying)
+                            -> ::std::result::Result<Self, Box<::std::error::Error + Send + Sync>>
+                        {
+                            match &value.name[..] {
+                                #(#variant_names => Ok(#variant_paths),)*
+                                _ => Err(format!(#error, value.name).into()),
+                            }
+                        }
+                    }
+
+                    #[macro_export]
+                    macro_rules! #create_macro_name {
+                        () => { #create_query };
+                        (if_not_exists) => { #create_query_if_not_exists };
+                    }
+
+                    #[macro_export]
+                    macro_rules! #drop_macro_name {
+                        () => { #drop_query };
+                        (cascade) => { #drop_query_cascade };
+                        (if_exists) => { #drop_query_if_exists };
+                    }
+
+                    #metadata_macro
+                    #macros_module
+                }
+            },
+            Storage::Text => quote! {
+                impl ::tql::SqlType for #enum_ident {
+                    type Underlying = String;
+
+                    fn to_sql_value(&self) -> String {
+                        let name =
+                            match *self {
+                                #(#variant_paths => #variant_names,)*
+                            };
+                        name.to_string()
+                    }
+
+                    fn from_sql_value(value: String)
+                        -> ::std::result::Result<Self, Box<::std::error::Error + Send + Sync>>
+                    {
+                        match &value[..] {
+                            #(#variant_names => Ok(#variant_paths),)*
+                            _ => Err(format!(#error, value).into()),
+                        }
+                    }
+                }
+            },
+        };
+    res(code, errors)
+}
+
+/// Get the storage from the `#[sql(storage = "…")]` attribute.
+fn parse_storage(attributes: &[Attribute], errors: &mut Vec<Error>) -> Storage {
+    let mut storage = Storage::Text;
+    for attribute in attributes {
+        if !attribute.path.is_ident("sql") {
+            continue;
+        }
+        let arguments =
+            match attribute.parse_meta() {
+                Ok(Meta::List(list)) => list.nested,
+                _ => {
+                    errors.push(Error::new("expected #[sql(storage = \"…\")]", attribute.span()));
+                    continue;
+                },
+            };
+        for argument in arguments {
+            match argument {
+                NestedMeta::Meta(Meta::NameValue(ref argument)) if argument.ident == "storage" => {
+                    match argument.lit {
+                        Lit::Str(ref value) =>
+                            match value.value().as_str() {
+                                "integer" => storage = Storage::Integer,
+                                "native" => storage = Storage::Native,
+                                "text" => storage = Storage::Text,
+                                value => errors.push(Error::new(
+                                    &format!("unknown storage `{}`, expected one of `integer`, `native` or `text`",
+                                        value),
+                                    argument.lit.span())),
+                            },
+                        ref lit => errors.push(Error::new("expected string literal", lit.span())),
+                    }
+                },
+                ref argument => errors.push(Error::new("unknown argument, expected `storage`", argument.span())),
+            }
+        }
+    }
+    storage
+}
@@ -0,0 +1,271 @@
+/*
+ * Copyright (c) 2017-2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! User-defined SQL functions declared with the `#[sql_function]` and `#[sql_aggregate]` attributes.
+//!
+//! The attribute replaces the function declaration by a macro named `tql_method_<name>` (or
+//! `tql_aggregate_<name>`) which is used by the query macros to generate the SQL code of the call
+//! (`sql` arm) and to type check it (`check` arm), and registers this macro so that the query
+//! macros know the function.
+
+use proc_macro2::{Span, TokenStream};
+use syn::{
+    FnArg,
+    ForeignItemFn,
+    Ident,
+    Lit,
+    LitStr,
+    MetaNameValue,
+    Type,
+    parse2,
+};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+
+use crate::error::{Error, Result, res};
+use crate::methods::{TemplatePart, template_parts};
+use crate::state::{add_user_function, is_user_function};
+
+/// The kind of user-defined function.
+#[derive(Clone, Copy)]
+pub enum FunctionKind {
+    /// An aggregate function, called in `aggregate()`.
+    Aggregate,
+    /// A method called on a field in `filter()`.
+    Method,
+}
+
+impl FunctionKind {
+    /// Get the name of the macro created for the user-defined `function`.
+    pub fn macro_name(self, function: &str) -> String {
+        let prefix =
+            match self {
+                FunctionKind::Aggregate => "aggregate",
+                FunctionKind::Method => "method",
+            };
+        format!("tql_{}_{}", prefix, function)
+    }
+
+    /// Check if the user-defined `function` was declared before the query.
+    pub fn is_declared(self, function: &str) -> bool {
+        is_user_function(&self.macro_name(function))
+    }
+}
+
+/// The SQL templates of a user-defined function.
+#[derive(Default)]
+struct Templates {
+    postgres: Option<LitStr>,
+    sqlite: Option<LitStr>,
+    template: Option<LitStr>,
+}
+
+impl Templates {
+    /// Get the template to use for the current backend.
+    fn backend_template(&self) -> Option<&LitStr> {
+        #[cfg(feature = "postgres")]
+        let backend_template = self.postgres.as_ref();
+        #[cfg(feature = "rusqlite")]
+        let backend_template = self.sqlite.as_ref();
+        #[cfg(not(any(feature = "rusqlite", feature = "postgres")))]
+        let backend_template = None;
+        backend_template.or(self.template.as_ref())
+    }
+}
+
+/// Convert the `#[sql_function]` or `#[sql_aggregate]` attribute and the function declaration to the
+/// macro used by the query macros.
+pub fn function_macro(attributes: TokenStream, item: TokenStream, kind: FunctionKind) -> Result<TokenStream> {
+    let mut errors = vec![];
+    let templates = parse_templates(attributes, &mut errors);
+    let function: ForeignItemFn =
+        match parse2(item) {
+            Ok(function) => function,
+            Err(error) => return Err(vec![Error::new(
+                &format!("cannot parse function declaration: {}", error), error.span())]),
+        };
+
+    let ident = &function.ident;
+    let macro_name = kind.macro_name(&ident.to_string());
+    add_user_function(macro_name.clone());
+    let argument_types = check_parameters(&function, kind, &mut errors);
+    let argument_count = argument_types.len().saturating_sub(1);
+    let sql =
+        match templates.backend_template() {
+            Some(template) => {
+                let parts = template_to_tokens(template, ident, argument_count, &mut errors);
+                quote! {
+                    concat!(#(#parts),*)
+                }
+            },
+            None => {
+                let message = format!("the function `{}` is not available on this backend", ident);
+                quote! {
+                    compile_error!(#message)
+                }
+            },
+        };
+
+    let parameters: Vec<_> =
+        match kind {
+            FunctionKind::Aggregate => vec![],
+            // NOTE: the field on which the method is called is the first parameter.
+            FunctionKind::Method => (0..argument_types.len()).map(metavar).collect(),
+        };
+    let sql_parameters =
+        match kind {
+            FunctionKind::Aggregate => vec![metavar(0)],
+            FunctionKind::Method => parameters.clone(),
+        };
+    let parameters = &parameters;
+    let macro_name = Ident::new(&macro_name, Span::call_site());
+    let attrs = &function.attrs;
+    let output = &function.decl.output;
+    let argument_types = &argument_types;
+    let placeholders = argument_types.iter().map(|_| quote! { _ });
+    let code = quote! {
+        #(#attrs)*
+        #[macro_export]
+        macro_rules! #macro_name {
+            (sql #(, $#sql_parameters:expr)*) => {
+                #sql
+            };
+            (check #(, $#parameters:expr)*) => {{
+                let #ident: fn(#(#argument_types),*) #output = |#(#placeholders),*| ::tql::function_result();
+                #ident(#($#parameters),*)
+            }};
+        }
+    };
+    res(code, errors)
+}
+
+/// Check the parameters of the function declaration and return their types.
+fn check_parameters(function: &ForeignItemFn, kind: FunctionKind, errors: &mut Vec<Error>) -> Vec<Type> {
+    let generics = &function.decl.generics;
+    if !generics.params.is_empty() || generics.where_clause.is_some() {
+        errors.push(Error::new("a user-defined function cannot be generic", generics.span()));
+    }
+    let inputs = &function.decl.inputs;
+    match kind {
+        FunctionKind::Aggregate => {
+            if !inputs.is_empty() {
+                let mut error = Error::new("an aggregate function declaration takes no parameters", inputs.span());
+                error.add_help("use $0 in the template to refer to the aggregated field");
+                errors.push(error);
+            }
+            vec![]
+        },
+        FunctionKind::Method => {
+            if inputs.is_empty() {
+                let mut error = Error::new("this function declaration takes no parameters",
+                    function.decl.paren_token.span);
+                error.add_help("the first parameter is the field on which the method is called");
+                errors.push(error);
+            }
+            inputs.iter()
+                .filter_map(|input| {
+                    match *input {
+                        FnArg::Captured(ref argument) => Some(argument.ty.clone()),
+                        _ => {
+                            errors.push(Error::new("expected a parameter with a type", input.span()));
+                            None
+                        },
+                    }
+                })
+                .collect()
+        },
+    }
+}
+
+/// Get the identifier of the metavariable for the argument at `index`.
+fn metavar(index: usize) -> Ident {
+    Ident::new(&format!("__tql_arg{}", index), Span::call_site())
+}
+
+/// Parse the templates from the attribute arguments.
+fn parse_templates(attributes: TokenStream, errors: &mut Vec<Error>) -> Templates {
+    let mut templates = Templates::default();
+    let span = attributes.span();
+    let arguments =
+        match Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(attributes) {
+            Ok(arguments) => arguments,
+            Err(error) => {
+                errors.push(Error::new(&format!("cannot parse attribute arguments: {}", error), error.span()));
+                return templates;
+            },
+        };
+    for argument in arguments {
+        let template =
+            match argument.lit {
+                Lit::Str(template) => template,
+                ref lit => {
+                    errors.push(Error::new("expected string literal", lit.span()));
+                    continue;
+                },
+            };
+        match argument.ident.to_string().as_str() {
+            "postgres" => templates.postgres = Some(template),
+            "sqlite" => templates.sqlite = Some(template),
+            "template" => templates.template = Some(template),
+            name => errors.push(Error::new(
+                &format!("unknown argument `{}`, expected one of `postgres`, `sqlite` or `template`", name),
+                argument.ident.span())),
+        }
+    }
+    if templates.postgres.is_none() && templates.sqlite.is_none() && templates.template.is_none() {
+        let mut error = Error::new("missing SQL template", span);
+        error.add_help("add a template argument: template = \"FUNCTION($0)\"");
+        errors.push(error);
+    }
+    templates
+}
+
+/// Convert the `template` to the arguments of `concat!()`, replacing the placeholders by the
+/// metavariables.
+fn template_to_tokens(template: &LitStr, function: &Ident, argument_count: usize, errors: &mut Vec<Error>)
+    -> Vec<TokenStream>
+{
+    template_parts(&template.value()).into_iter()
+        .map(|part| {
+            match part {
+                TemplatePart::Placeholder(placeholder) => {
+                    if placeholder > argument_count {
+                        errors.push(Error::new(
+                            &format!("the placeholder `${}` does not refer to a parameter of `{}`", placeholder,
+                                function),
+                            template.span()));
+                    }
+                    let metavar = metavar(placeholder);
+                    quote! {
+                        $#metavar
+                    }
+                },
+                TemplatePart::Text(text) => {
+                    let text = LitStr::new(&text, template.span());
+                    quote! {
+                        #text
+                    }
+                },
+            }
+        })
+        .collect()
+}
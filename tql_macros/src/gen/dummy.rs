@@ -23,7 +23,7 @@ use proc_macro2::TokenStream;
 use syn::{Expr, Ident};
 
 use super::BackendGen;
-use SqlQueryWithArgs;
+use crate::SqlQueryWithArgs;
 
 pub struct DummyBackend {}
 
@@ -26,9 +26,13 @@ mod postgres;
 #[cfg(feature = "rusqlite")]
 mod sqlite;
 
+use std::collections::BTreeMap;
+use std::path::Path as FilePath;
+
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use proc_macro2::TokenStream as Tokens;
+use quote::ToTokens;
 use rand::{self, Rng};
 use syn::{
     self,
@@ -38,6 +42,7 @@ use syn::{
     FieldsNamed,
     Ident,
     ItemStruct,
+    Visibility,
     parse,
 };
 #[cfg(feature="unstable")]
@@ -48,30 +53,42 @@ use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::token::Comma;
 
-use ast::{
+use crate::ast::{
     Aggregate,
     Join,
     TypedField,
+    WithSpan,
 };
-use attribute::{field_ty_to_type, fields_vec_to_hashmap};
-use error::{Error, Result, res};
-use plugin::{new_ident, string_literal};
+use crate::attribute::{field_ty_to_type, fields_vec_to_hashmap, parse_field_attributes, parse_table_attributes};
+use crate::error::{Error, Result, res};
+use crate::plugin::{new_ident, string_literal};
+use crate::schema::{ModelColumn, check_table_schema};
 #[cfg(feature = "postgres")]
 use self::postgres::create_backend;
 #[cfg(feature = "rusqlite")]
 use self::sqlite::create_backend;
 #[cfg(not(any(feature = "rusqlite", feature = "postgres")))]
 use self::dummy::create_backend;
-use sql::fields_to_sql;
-use state::SqlFields;
-use string::token_to_string;
-use types::{
+use crate::sql::{self, fields_to_sql};
+use crate::state::{
+    SqlFields,
+    TableInfo,
+    add_table,
+    embed_fields,
+    embedded_fields,
+    is_embed,
+    is_soft_delete,
+    related_tables,
+    table_metadata,
+};
+use crate::string::token_to_string;
+use crate::types::{
     Type,
     get_type_parameter,
     get_type_parameter_as_path,
     type_to_sql,
 };
-use {
+use crate::{
     Arguments,
     SqlQueryWithArgs,
     add_error,
@@ -84,17 +101,18 @@ use {
 pub fn table_methods(item_struct: &ItemStruct) -> Tokens {
     let table_ident = &item_struct.ident;
     if let Fields::Named(FieldsNamed { ref named , .. }) = item_struct.fields {
-        let field_idents = named.iter()
-            .map(|field| field.ty.clone());
+        let table = table_ident.to_string();
         let index = &mut 0;
-        let columns = field_idents.map(|typ| to_row_get(typ, false, index));
+        let columns = named.iter()
+            .map(|field| field_row_get(&table, field, false, index))
+            .collect::<Vec<_>>();
 
-        let field_idents = named.iter()
-            .map(|field| field.ty.clone());
         let index = &mut 0;
-        let related_columns = field_idents.map(|typ| to_row_get(typ, true, index));
+        let related_columns = named.iter()
+            .map(|field| field_row_get(&table, field, true, index))
+            .collect::<Vec<_>>();
 
-        let field_count = named.iter()
+        let field_count = flatten_fields(named, table_ident).iter()
             .filter(|field| {
                 let typ = token_to_string(&field.ty);
                 !typ.starts_with("ForeignKey")
@@ -114,6 +132,13 @@ pub fn table_methods(item_struct: &ItemStruct) -> Tokens {
         let row_type_ident = backend.row_type_ident(&table_ident);
         let delta_type = backend.delta_type();
         let row_ident = Ident::new("__tql_item_row", Span::call_site());
+        let primary_key = named.iter()
+            .find(|field| field_ty_to_type(&field.ty).node == Type::Serial)
+            .map(|field| field.ident.clone().expect("field has name").to_string())
+            .unwrap_or_default();
+        let schema = table_schema(&flatten_fields(named, table_ident), table_ident);
+        // NOTE: tql makemigrations runs the tests whose name contains __tql_snapshot_.
+        let snapshot_test = Ident::new(&format!("__tql_snapshot_{}", table_ident), Span::call_site());
 
         quote! {
             unsafe impl #trait_ident for #table_ident {
@@ -123,6 +148,14 @@ pub fn table_methods(item_struct: &ItemStruct) -> Tokens {
                     unimplemented!()
                 }
 
+                fn _tql_primary_key() -> &'static str {
+                    #primary_key
+                }
+
+                fn _tql_schema() -> ::tql::migration::TableSchema {
+                    #schema
+                }
+
                 #[allow(unused)]
                 fn from_row(#row_ident: &#row_type_ident) -> Self {
                     Self {
@@ -137,6 +170,25 @@ pub fn table_methods(item_struct: &ItemStruct) -> Tokens {
                     }
                 }
             }
+
+            impl #table_ident {
+                /// Compare the fields to the columns of the table in the `database`.
+                #[allow(dead_code)]
+                pub fn check_schema<D: ::tql::migration::Database>(database: &D)
+                    -> ::tql::migration::Result<Vec<::tql::migration::Mismatch>>
+                {
+                    let schema = ::tql::migration::Schema::new().table::<Self>();
+                    ::tql::check_all(database, &schema)
+                }
+            }
+
+            #[cfg(test)]
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            #[test]
+            fn #snapshot_test() {
+                ::tql::migration::write_snapshot::<#table_ident>();
+            }
         }
     }
     else {
@@ -144,6 +196,96 @@ pub fn table_methods(item_struct: &ItemStruct) -> Tokens {
     }
 }
 
+/// Create the schema of the table used by the migrations (the columns are the same as in the
+/// CREATE query).
+fn table_schema(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Tokens {
+    let table = table_ident.to_string();
+    let mut columns = vec![];
+    let mut indexes = vec![];
+    for field in named {
+        let name = field.ident.clone().expect("field has name").to_string();
+        let (typ, nullable) =
+            match field_ty_to_type(&field.ty).node {
+                Type::Nullable(typ) => (*typ, true),
+                typ => (typ, false),
+            };
+        let sql_type =
+            match typ {
+                Type::Custom(ref related_table_name) => {
+                    let related_table = new_ident(related_table_name);
+                    quote! {
+                        "INTEGER REFERENCES ", #related_table_name, "(",
+                            <#related_table as ::tql::SqlTable>::_tql_primary_key(), ")"
+                    }
+                },
+                ref typ => sql::type_to_sql(typ, true).into(),
+            };
+        let default =
+            match default_sql(field) {
+                Some(default) => quote! { Some(#default.to_string()) },
+                None => quote! { None },
+            };
+        columns.push(quote! {
+            ::tql::migration::Column {
+                name: #name.to_string(),
+                sql_type: [#sql_type].concat(),
+                nullable: #nullable,
+                default: #default,
+            }
+        });
+        if parse_field_attributes(&field.attrs, &mut vec![]).index {
+            indexes.push(quote! {
+                ::tql::migration::Index::on_column(#table, #name)
+            });
+        }
+    }
+    quote! {
+        ::tql::migration::TableSchema {
+            name: #table.to_string(),
+            columns: vec![#(#columns),*],
+            indexes: vec![#(#indexes),*],
+        }
+    }
+}
+
+/// Create the from_row() method for the embedded struct.
+pub fn embed_methods(item_struct: &ItemStruct) -> Tokens {
+    let embed_ident = &item_struct.ident;
+    if let Fields::Named(FieldsNamed { ref named , .. }) = item_struct.fields {
+        let index = &mut 0;
+        let columns = named.iter()
+            .map(|field| to_row_get(field.ty.clone(), true, index))
+            .collect::<Vec<_>>();
+        let field_idents = named.iter()
+            .map(|field| field.ident.clone().expect("field has name"));
+
+        let trait_ident = quote_spanned! { embed_ident.span() =>
+            ::tql::SqlEmbed
+        };
+        let backend = create_backend();
+        let row_type_ident = backend.row_type_ident(&embed_ident);
+        let delta_type = backend.delta_type();
+        let row_ident = Ident::new("__tql_item_row", Span::call_site());
+        let debug_impl = create_debug_impl(item_struct);
+
+        quote! {
+            #debug_impl
+
+            unsafe impl #trait_ident for #embed_ident {
+                #[allow(unused)]
+                fn from_row(#row_ident: &#row_type_ident, delta: #delta_type) -> Self {
+                    Self {
+                        #(#field_idents: #columns,)*
+                    }
+                }
+            }
+        }
+    }
+    else {
+        unreachable!("Check is done in embed_impl()")
+    }
+}
+
 /// Add the postgres::types::ToSql implementation on the struct.
 /// Its SQL representation is the same as the primary key SQL representation.
 pub fn tosql_impl(item_struct: &ItemStruct, primary_key_field: Option<String>) -> Tokens {
@@ -224,15 +366,78 @@ pub fn generate_errors(errors: Vec<Error>) -> TokenStream {
     }
 }
 
-/// Generate the Rust code from the SQL query.
+/// Generate the Rust code from the SQL query, with the variables binding the arguments on stable.
 pub(crate) fn gen_query(args: &SqlQueryWithArgs, connection_expr: Tokens) -> (TokenStream, Vec<Tokens>) {
     let struct_expr = create_struct(&args.table_name, &args.joins);
     let (aggregate_struct, aggregate_expr) = gen_aggregate_struct(&args.aggregates);
-    let (args_expr, metavars) = typecheck_arguments(args);
+    let (args_expr, bindings) = typecheck_arguments(args);
     let backend = create_backend();
     let tokens = backend.gen_query_expr(connection_expr, args, args_expr, struct_expr, aggregate_struct,
                                         aggregate_expr);
-    (tokens.into(), metavars)
+    let tokens = with_table_imports(args.table_path.as_ref(), &args.table_name, tokens);
+    (tokens.into(), bindings)
+}
+
+/// Wrap the `code` of a query on a path-qualified `table` (e.g. `models::Table`) in a block
+/// importing this table and its related tables, as well as their macros from the module created by
+/// the derive (see `macros_module()`), so that the query needs neither `use` nor `#[macro_use]`.
+pub fn with_table_imports(table_path: Option<&Path>, table: &Ident, code: Tokens) -> Tokens {
+    let table_path =
+        match table_path {
+            Some(table_path) => table_path,
+            None => return code,
+        };
+    let mut module = table_path.clone();
+    module.segments.pop();
+    let mut tables = vec![table.clone()];
+    // NOTE: the related tables are expected to be in the same module as the table.
+    tables.extend(related_tables(&table.to_string()).iter().map(|table| Ident::new(table, Span::call_site())));
+    let macro_imports = tables.iter()
+        .map(|table| {
+            let macros_module = Ident::new(&format!("__tql_{}", table), Span::call_site());
+            quote! {
+                #[allow(unused_imports)]
+                use #module #macros_module::*;
+            }
+        })
+        .collect::<Vec<_>>();
+    quote! {{
+        #[allow(unused_imports)]
+        use #module {#(#tables),*};
+        #(#macro_imports)*
+        #code
+    }}
+}
+
+/// Create the module re-exporting the `macros` of the `table` (`tql_<Table>_<macro>`), so that they
+/// can be imported by path like the table, even from the current crate where the exported macros
+/// can only be used by their name.
+pub fn macros_module(visibility: &Visibility, table: &Ident, macros: &[&str]) -> Tokens {
+    let module = Ident::new(&format!("__tql_{}", table), Span::call_site());
+    let macros = macros.iter()
+        .map(|suffix| Ident::new(&format!("tql_{}_{}", table, suffix), Span::call_site()));
+    quote! {
+        #[doc(hidden)]
+        #visibility mod #module {
+            #(pub use #macros;)*
+        }
+    }
+}
+
+/// Get the path of the macro `tql_<Struct>_<suffix>` of the struct at `path`: the macro of a
+/// path-qualified struct is used from the module created by `macros_module()`.
+pub fn struct_macro_path(path: &Path, suffix: &str) -> Tokens {
+    let name = &path.segments.last().expect("last segment of path").into_value().ident;
+    let macro_name = Ident::new(&format!("tql_{}_{}", name, suffix), name.span());
+    if path.segments.len() > 1 {
+        let mut module = path.clone();
+        module.segments.pop();
+        let macros_module = Ident::new(&format!("__tql_{}", name), name.span());
+        quote! { #module #macros_module::#macro_name }
+    }
+    else {
+        quote! { #macro_name }
+    }
 }
 
 /// Create the struct expression needed by the generated code.
@@ -283,17 +488,35 @@ fn gen_aggregate_struct(aggregates: &[Aggregate]) -> (Tokens, Tokens) {
 }
 
 
+/// The suffixes of the names of the macros generated for each table (`tql_<Table>_<suffix>`).
+const TABLE_MACROS: &[&str] = &[
+    "add_column_query",
+    "array_field",
+    "check_missing_fields",
+    "check_primary_key",
+    "check_related_pks",
+    "check_related_tables",
+    "create_query",
+    "deleted_filter",
+    "drop_column_query",
+    "drop_query",
+    "field_list",
+    "metadata",
+    "primary_key_field",
+    "related_deleted_filters",
+    "related_field_list",
+    "related_pks",
+    "related_tables",
+    "sql_value",
+];
+
 /// Get the fields from the struct (also returns the ToSql implementations to check that the types
-/// used for ForeignKey have a #[derive(SqlTable)]).
+/// used for ForeignKey have a #[derive(SqlTable)] and that the user-defined types implement SqlType).
 /// Also check if the field types from the struct are supported types.
 pub fn get_struct_fields(item_struct: &ItemStruct) -> (Result<SqlFields>, Option<String>, TokenStream) {
-    fn error(span: Span, typ: &str) -> Error {
-        Error::new_with_code(&format!("use of unsupported type name `{}`", typ),
-            span, "E0412")
-    }
-
     let mut primary_key_field = None;
     let position = item_struct.ident.span();
+    let table_name = item_struct.ident.to_string();
     let mut impls: TokenStream = quote! {}.into();
     let mut errors = vec![];
 
@@ -303,25 +526,85 @@ pub fn get_struct_fields(item_struct: &ItemStruct) -> (Result<SqlFields>, Option
             _ => return (Err(vec![Error::new("Expected normal struct, found", position)]), None, empty_token_stream()), // TODO: improve this message.
         };
     let mut primary_key_count = 0;
+    let mut table_embeds = BTreeMap::new();
+    let mut auto_now_fields = vec![];
+    let mut decimal_fields = vec![];
+    let mut required_fields = vec![];
+    let mut version_field = None;
+    let mut field_names = vec![];
+    let mut related_tables = vec![];
     for field in &fields {
         if let Some(ref field_ident) = field.ident {
             let field_type = &field.ty;
             let field_name = field_ident.to_string();
+            field_names.push(field_name.clone());
+            let attributes = parse_field_attributes(&field.attrs, &mut errors);
             let field = field_ty_to_type(&field.ty);
+            if attributes.auto_now || attributes.auto_now_add {
+                match field.node.current_time_sql() {
+                    Some(current_time) => {
+                        if attributes.auto_now {
+                            auto_now_fields.push((field_name.clone(), current_time.to_string()));
+                        }
+                    },
+                    None => errors.push(Error::new(
+                        "mismatched types: #[sql(auto_now)] and #[sql(auto_now_add)] require a date or time field",
+                        field.span)),
+                }
+            }
+            if attributes.version {
+                match field.node {
+                    Type::I16 | Type::I32 | Type::I64 => (),
+                    _ => errors.push(Error::new_with_code(
+                        "mismatched types: expected `i16`, `i32` or `i64` for a #[sql(version)] field",
+                        field.span, "E0308")),
+                }
+                if version_field.is_some() {
+                    errors.push(Error::new("a table can only have one #[sql(version)] field", field_ident.span()));
+                }
+                version_field = Some(field_name.clone());
+            }
+            if attributes.embed {
+                if attributes.index {
+                    errors.push(Error::new("embedded fields cannot be indexed", field_ident.span()));
+                }
+                match field.node {
+                    Type::UserDefined(ref typ) if is_embed(typ) => {
+                        let has_required_column = embed_fields(typ).into_iter()
+                            .flatten()
+                            .any(|embed_field| {
+                                let typ = syn::parse_str(&embed_field.typ).expect("embedded field type");
+                                !field_ty_to_type(&typ).node.is_nullable()
+                            });
+                        if has_required_column {
+                            required_fields.push(field_name.clone());
+                        }
+                        table_embeds.insert(field_name, typ.clone());
+                    },
+                    Type::UserDefined(ref typ) =>
+                        errors.push(Error::new(&format!(
+                            "cannot find the embedded struct `{}`: it must derive SqlEmbed", typ),
+                            field.span)),
+                    _ => errors.push(Error::new(
+                        "mismatched types: expected a struct deriving SqlEmbed (embedded fields cannot be optional)",
+                        field.span)),
+                }
+                continue;
+            }
+            if field.node.is_decimal() {
+                decimal_fields.push(field_name.clone());
+            }
+            let has_default = attributes.auto_now || attributes.auto_now_add || attributes.version;
+            if !field.node.is_nullable() && !has_default {
+                required_fields.push(field_name.clone());
+            }
             match field.node {
-                Type::Nullable(ref inner_type) => {
-                    if let Type::UnsupportedType(ref typ) = **inner_type {
-                        errors.push(error(field.span, typ));
-                    }
-                },
-                Type::UnsupportedType(ref typ) =>
-                    errors.push(error(field.span, typ)),
-                // NOTE: Other types are supported.
                 Type::Serial => {
                     primary_key_field = Some(field_name);
                     primary_key_count += 1;
                 },
                 Type::Custom(ref typ) => {
+                    related_tables.push(typ.clone());
                     let type_ident = new_ident(typ);
                     let struct_ident = new_ident(&format!("CheckForeignKey{}", rand_string()));
                     // TODO: replace with a trait bound on ForeignKey when it is stable.
@@ -351,10 +634,34 @@ pub fn get_struct_fields(item_struct: &ItemStruct) -> (Result<SqlFields>, Option
 
                     impls = concat_token_stream(impls, code);
                 },
-                _ => (),
+                _ => impls = concat_token_stream(impls, check_column_type(field_type, &field, &mut errors)),
             }
         }
     }
+    let table_attributes = parse_table_attributes(&item_struct.attrs, &mut errors);
+    let soft_delete =
+        if table_attributes.soft_delete {
+            soft_delete_time(&fields, position, &mut errors)
+        }
+        else {
+            None
+        };
+    add_table(table_name.clone(), TableInfo {
+        auto_now_fields,
+        decimal_fields,
+        embedded_fields: table_embeds,
+        fields: field_names,
+        related_tables,
+        required_fields,
+        soft_delete,
+        version_field,
+    });
+    if let Some(ref schema) = table_attributes.schema {
+        let columns = model_columns(&fields, &table_name);
+        if let Some(path) = check_table_schema(schema, &table_name, position, &columns, &mut errors) {
+            impls = concat_token_stream(impls, include_schema(&path));
+        }
+    }
 
     match primary_key_count {
         0 => errors.insert(0, Error::new_warning("No primary key found", position)),
@@ -366,6 +673,172 @@ pub fn get_struct_fields(item_struct: &ItemStruct) -> (Result<SqlFields>, Option
     (res(fields, errors), primary_key_field, impls)
 }
 
+/// Get the columns of the table checked against the schema, where each embedded field is replaced
+/// by the fields of its struct.
+fn model_columns(fields: &[Field], table: &str) -> Vec<ModelColumn> {
+    let mut columns = vec![];
+    for field in fields {
+        let ident = field.ident.clone().expect("field has name");
+        if let Some(embed_fields) = embedded_fields(table, &ident.to_string()) {
+            for embed_field in embed_fields {
+                let typ = syn::parse_str(&embed_field.typ).expect("embedded field type");
+                columns.push(ModelColumn {
+                    name: format!("{}_{}", ident, embed_field.name),
+                    span: ident.span(),
+                    typ: WithSpan {
+                        node: field_ty_to_type(&typ).node,
+                        span: ident.span(),
+                    },
+                });
+            }
+        }
+        else {
+            columns.push(ModelColumn {
+                name: ident.to_string(),
+                span: ident.span(),
+                typ: field_ty_to_type(&field.ty),
+            });
+        }
+    }
+    columns
+}
+
+/// Include the schema file in the crate so that it is recompiled when the schema changes.
+fn include_schema(path: &FilePath) -> TokenStream {
+    let path = path.to_string_lossy();
+    let const_ident = new_ident(&format!("_TQL_SCHEMA_{}", rand_string()));
+    let code = quote! {
+        #[allow(dead_code)]
+        const #const_ident: &'static str = include_str!(#path);
+    };
+    code.into()
+}
+
+/// Check that a soft-deleted table has a `deleted_at` field to store the date of deletion and get
+/// the SQL expression of the current time stored in this field.
+fn soft_delete_time(fields: &[Field], position: Span, errors: &mut Vec<Error>) -> Option<String> {
+    let deleted_at = fields.iter()
+        .find(|field| field.ident.as_ref().map_or(false, |ident| ident == "deleted_at"));
+    match deleted_at {
+        Some(field) => {
+            let typ = field_ty_to_type(&field.ty);
+            let current_time =
+                match typ.node {
+                    Type::Nullable(ref typ) =>
+                        match **typ {
+                            Type::LocalDateTime | Type::NaiveDateTime | Type::UtcDateTime => typ.current_time_sql(),
+                            _ => None,
+                        },
+                    _ => None,
+                };
+            if current_time.is_none() {
+                errors.push(Error::new_with_code(
+                    "mismatched types: expected `Option<DateTime<Utc>>` for the `deleted_at` field of a soft-deleted table",
+                    field.ty.span(), "E0308"));
+            }
+            // NOTE: the table is still soft-deleted when the type is wrong to avoid reporting errors
+            // in the queries using the soft delete methods.
+            current_time.or_else(|| Type::UtcDateTime.current_time_sql()).map(ToString::to_string)
+        },
+        None => {
+            errors.push(Error::new(
+                "#[sql(soft_delete)] requires a `deleted_at: Option<DateTime<Utc>>` field", position));
+            None
+        },
+    }
+}
+
+/// Check that the type of a field can be stored in a column (also returns the code checking that
+/// the user-defined types implement SqlType).
+pub fn check_column_type(field_type: &syn::Type, field: &WithSpan<Type>, errors: &mut Vec<Error>) -> TokenStream {
+    fn error(span: Span, typ: &str) -> Error {
+        Error::new_with_code(&format!("use of unsupported type name `{}`", typ),
+            span, "E0412")
+    }
+
+    fn array_error(span: Span) -> Error {
+        Error::new("array columns are only supported on PostgreSQL", span)
+    }
+
+    match field.node {
+        Type::Nullable(ref inner_type) => {
+            match **inner_type {
+                Type::Array(_) if cfg!(feature = "rusqlite") => errors.push(array_error(field.span)),
+                Type::UnsupportedType(ref typ) => errors.push(error(field.span, typ)),
+                Type::UserDefined(_) => {
+                    if let syn::Type::Path(TypePath { path: Path { ref segments, .. }, ..}) = *field_type {
+                        let segment = segments.first().expect("first segment").into_value();
+                        if let Some(path) = get_type_parameter_as_path(&segment.arguments) {
+                            return check_sql_type(path);
+                        }
+                    }
+                },
+                _ => (),
+            }
+        },
+        Type::Array(_) if cfg!(feature = "rusqlite") =>
+            errors.push(array_error(field.span)),
+        Type::UnsupportedType(ref typ) =>
+            errors.push(error(field.span, typ)),
+        Type::UserDefined(_) =>
+            return check_sql_type(field_type),
+        // NOTE: Other types are supported.
+        _ => (),
+    }
+    empty_token_stream()
+}
+
+/// Check that a user-defined type used as a field type implements `SqlType`.
+fn check_sql_type<T: ToTokens>(typ: &T) -> TokenStream {
+    let struct_ident = new_ident(&format!("CheckSqlType{}", rand_string()));
+    quote_spanned! { typ.span() =>
+        #[allow(dead_code)]
+        struct #struct_ident where #typ: ::tql::SqlType {
+            field: #typ,
+        }
+    }.into()
+}
+
+/// Replace the embedded fields by the fields of the embedded struct, prefixed by the name of the
+/// embedded field (e.g. `address_city`), since each of them is stored in its own column.
+fn flatten_fields(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Punctuated<Field, Comma> {
+    let table = table_ident.to_string();
+    let mut fields = Punctuated::new();
+    for field in named {
+        let ident = field.ident.clone().expect("field has name");
+        if let Some(embed_fields) = embedded_fields(&table, &ident.to_string()) {
+            for embed_field in embed_fields {
+                fields.push(Field {
+                    attrs: vec![],
+                    ident: Some(Ident::new(&format!("{}_{}", ident, embed_field.name), Span::call_site())),
+                    ty: syn::parse_str(&embed_field.typ).expect("embedded field type"),
+                    ..field.clone()
+                });
+            }
+        }
+        else {
+            fields.push(field.clone());
+        }
+    }
+    fields
+}
+
+/// Get the SQL expression of the default value of the field: the current time if the field is
+/// `#[sql(auto_now)]` or `#[sql(auto_now_add)]` and the first version if it is `#[sql(version)]`.
+fn default_sql(field: &Field) -> Option<&'static str> {
+    // NOTE: the errors in the attributes are reported by get_struct_fields().
+    let attributes = parse_field_attributes(&field.attrs, &mut vec![]);
+    if attributes.auto_now || attributes.auto_now_add {
+        field_ty_to_type(&field.ty).node.current_time_sql()
+    }
+    else if attributes.version {
+        Some("1")
+    }
+    else {
+        None
+    }
+}
+
 fn field_list_macro(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Tokens {
     let field_list = named.iter()
         .filter(|field| {
@@ -390,24 +863,154 @@ fn field_list_macro(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> To
     }
 }
 
-fn create_query_macro(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Tokens {
-    let mut fields_to_create = vec![];
-    for field in named {
-        fields_to_create.push(TypedField {
-            identifier: field.ident.clone().expect("field ident").to_string(),
-            typ: type_to_sql(&field_ty_to_type(&field.ty).node),
-        });
+/// Get the definition of the column of the `field`, and whether its SQL type is a user-defined
+/// type.
+fn column_definition(field: &Field) -> (TypedField, bool) {
+    let typ = field_ty_to_type(&field.ty).node;
+    let is_user_defined_type =
+        match typ {
+            Type::Nullable(ref inner_type) => {
+                if let Type::UserDefined(_) = **inner_type {
+                    true
+                }
+                else {
+                    false
+                }
+            },
+            Type::UserDefined(_) => true,
+            _ => false,
+        };
+    let sql_type = type_to_sql(&typ);
+    let sql_type =
+        match default_sql(field) {
+            Some(value) => {
+                let default = format!(" DEFAULT {}", value);
+                quote! {
+                    #sql_type, #default
+                }
+            },
+            None => sql_type,
+        };
+    let column = TypedField {
+        identifier: field.ident.clone().expect("field ident").to_string(),
+        typ: sql_type,
+    };
+    (column, is_user_defined_type)
+}
+
+/// Get the definitions of the `columns`, and whether one of them has a user-defined type.
+fn column_definitions(columns: &Punctuated<Field, Comma>) -> (Vec<TypedField>, bool) {
+    let mut definitions = vec![];
+    let mut has_user_defined_type = false;
+    for column in columns {
+        let (definition, is_user_defined_type) = column_definition(column);
+        definitions.push(definition);
+        has_user_defined_type = has_user_defined_type || is_user_defined_type;
+    }
+    (definitions, has_user_defined_type)
+}
+
+/// Convert the arguments of `concat!()` to the query string.
+fn concat_query(query: Tokens, has_user_defined_type: bool) -> Tokens {
+    if has_user_defined_type {
+        // The SQL types of the user-defined types are associated constants, so the query
+        // cannot be created with concat!().
+        quote! {
+            &[#query].concat()
+        }
+    }
+    else {
+        quote! {
+            concat!(#query)
+        }
     }
+}
+
+/// Get the names of the `columns` having an index (comes from `#[sql(index)]`).
+fn indexed_columns(columns: &Punctuated<Field, Comma>) -> Vec<String> {
+    columns.iter()
+        // NOTE: the errors in the attributes are reported by get_struct_fields().
+        .filter(|column| parse_field_attributes(&column.attrs, &mut vec![]).index)
+        .map(|column| column.ident.clone().expect("field ident").to_string())
+        .collect()
+}
+
+/// Create the macros generating the queries creating the table with its indexes and dropping it.
+fn create_query_macro(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Tokens {
+    let (fields_to_create, has_user_defined_type) = column_definitions(named);
     let table = table_ident.to_string();
     let fields = fields_to_sql(&fields_to_create);
-    let create_query = quote! {
-        concat!("CREATE TABLE ", #table, " (", #fields, ")")
+    let indexed_columns = indexed_columns(named);
+    let create_query = |if_not_exists| {
+        let create_table =
+            if if_not_exists {
+                "CREATE TABLE IF NOT EXISTS "
+            }
+            else {
+                "CREATE TABLE "
+            };
+        let create_indexes = sql::create_indexes_query(&table, &indexed_columns, if_not_exists);
+        concat_query(quote! { #create_table, #table, " (", #fields, ")", #create_indexes }, has_user_defined_type)
     };
+    let create_query_if_not_exists = create_query(true);
+    let create_query = create_query(false);
     let macro_name = Ident::new(&format!("tql_{}_create_query", table_ident), Span::call_site());
+    let drop_query = format!("DROP TABLE {}", table);
+    let drop_query_cascade = sql::drop_cascade_query("TABLE", &table);
+    let drop_query_if_exists = format!("DROP TABLE IF EXISTS {}", table);
+    let drop_macro_name = Ident::new(&format!("tql_{}_drop_query", table_ident), Span::call_site());
     quote! {
         #[macro_export]
         macro_rules! #macro_name {
             () => { #create_query };
+            (if_not_exists) => { #create_query_if_not_exists };
+        }
+
+        #[macro_export]
+        macro_rules! #drop_macro_name {
+            () => { #drop_query };
+            (cascade) => { #drop_query_cascade };
+            (if_exists) => { #drop_query_if_exists };
+        }
+    }
+}
+
+/// Create the macros generating the `ALTER TABLE` queries adding the columns of a field and
+/// dropping a column which is not stored by a field.
+fn alter_query_macros(named: &Punctuated<Field, Comma>, columns: &Punctuated<Field, Comma>, table_ident: &Ident)
+    -> Tokens
+{
+    let table = table_ident.to_string();
+    let (definitions, has_user_defined_type) = column_definitions(columns);
+    let indexed_columns = indexed_columns(columns);
+    let mut added_fields = vec![];
+    let mut add_column_queries = vec![];
+    for field in named {
+        // NOTE: an embedded field adds the columns of its struct.
+        let field_columns = flatten_fields(&Some(field.clone()).into_iter().collect(), table_ident);
+        let (added_columns, _) = column_definitions(&field_columns);
+        let nullable = field_columns.iter()
+            .all(|column| field_ty_to_type(&column.ty).node.is_nullable() && default_sql(column).is_none());
+        let query = sql::add_columns_query(&table, &added_columns, nullable, &definitions, &indexed_columns);
+        add_column_queries.push(concat_query(query, has_user_defined_type));
+        added_fields.push(field.ident.clone().expect("field ident"));
+    }
+    let drop_column_query = sql::drop_column_query(&table, &definitions, &indexed_columns);
+    let drop_column_query = concat_query(drop_column_query, has_user_defined_type);
+    let add_column_macro_name = Ident::new(&format!("tql_{}_add_column_query", table_ident), Span::call_site());
+    let drop_column_macro_name = Ident::new(&format!("tql_{}_drop_column_query", table_ident), Span::call_site());
+    quote! {
+        #[macro_export]
+        macro_rules! #add_column_macro_name {
+            #((#added_fields) => { #add_column_queries };)*
+            // NOTE: the check for the field name is done elsewhere, hence it is okay to return
+            // "" here.
+            ($tt:tt) => { "" };
+        }
+
+        #[macro_export]
+        macro_rules! #drop_column_macro_name {
+            ($column:ident) => { #drop_column_query };
         }
     }
 }
@@ -443,6 +1046,53 @@ fn related_pks_macro(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> T
     }
 }
 
+/// Create the macro giving the condition excluding the deleted rows of this table when it is joined
+/// (the empty string when the table has no `#[sql(soft_delete)]`) and the macro giving this
+/// condition for the table related to a field.
+fn deleted_filter_macros(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Tokens {
+    let filter =
+        if is_soft_delete(&table_ident.to_string()) {
+            format!(" AND {}.deleted_at IS NULL", table_ident)
+        }
+        else {
+            String::new()
+        };
+    let mut related_table_names = vec![];
+    let mut related_filter_macro_names = vec![];
+    for field in named {
+        let typ = token_to_string(&field.ty);
+        if let Some(ref ident) = field.ident {
+            if typ.starts_with("ForeignKey") {
+                if let syn::Type::Path(ref path) = field.ty {
+                    let element = path.path.segments.first().expect("first segment of path");
+                    let first_segment = element.value();
+                    if let Some(typ) = get_type_parameter(&first_segment.arguments) {
+                        related_table_names.push(ident);
+                        related_filter_macro_names.push(Ident::new(&format!("tql_{}_deleted_filter", typ),
+                            Span::call_site()));
+                    }
+                }
+            }
+        }
+    }
+    let macro_name = Ident::new(&format!("tql_{}_deleted_filter", table_ident), Span::call_site());
+    let related_macro_name = Ident::new(&format!("tql_{}_related_deleted_filters", table_ident), Span::call_site());
+    quote! {
+        #[macro_export]
+        macro_rules! #macro_name {
+            () => { #filter };
+        }
+
+        #[macro_export]
+        macro_rules! #related_macro_name {
+            #((#related_table_names) => { #related_filter_macro_names!() };)*
+            // NOTE: the check for the field name is done elsewhere, hence it is okay to return
+            // "" here.
+            ($tt:tt) => { "" };
+        }
+    }
+}
+
 fn pk_macro(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Tokens {
     let macro_name = Ident::new(&format!("tql_{}_primary_key_field", table_ident), Span::call_site());
     let mut primary_key = None;
@@ -479,7 +1129,8 @@ fn check_missing_fields_macro(named: &Punctuated<Field, Comma>, table_ident: &Id
     for field in named {
         let typ = token_to_string(&field.ty);
         if let Some(ref ident) = field.ident {
-            if !typ.starts_with("Option") && typ != "PrimaryKey" {
+            // The fields with a default value are not mandatory.
+            if !typ.starts_with("Option") && typ != "PrimaryKey" && default_sql(field).is_none() {
                 mandatory_fields.push(ident);
             }
         }
@@ -568,6 +1219,74 @@ expected type `ForeignKey<_>`
     }
 }
 
+/// Create the macro choosing between the SQL code for an array field and the SQL code for
+/// another field (e.g. `len()` is `CARDINALITY()` on an array and `LENGTH()` on a string).
+fn array_field_macro(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Tokens {
+    let mut array_fields = vec![];
+    for field in named {
+        if let Some(ref ident) = field.ident {
+            match field_ty_to_type(&field.ty).node {
+                Type::Array(_) => array_fields.push(ident),
+                Type::Nullable(ref typ) => {
+                    if let Type::Array(_) = **typ {
+                        array_fields.push(ident);
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+    let macro_name = Ident::new(&format!("tql_{}_array_field", table_ident), Span::call_site());
+    quote! {
+        #[macro_export]
+        macro_rules! #macro_name {
+            #((#array_fields, $array:expr, $other:expr) => { $array };)*
+            ($field:tt, $array:expr, $other:expr) => { $other };
+        }
+    }
+}
+
+/// Create the macro calling back a query macro with the information about the table (given by
+/// `table_metadata()`), which is added after the information about the other tables of the query
+/// when the callback is another `tql_<Table>_metadata!()` macro.
+pub fn metadata_macro(table_ident: &Ident, metadata: &str) -> Tokens {
+    let macro_name = Ident::new(&format!("tql_{}_metadata", table_ident), Span::call_site());
+    quote! {
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! #macro_name {
+            ($(@tql_metadata $table:ident $metadata:tt)* [$($callback:tt)*] $($input:tt)*) => {
+                $($callback)*!($(@tql_metadata $table $metadata)* @tql_metadata #table_ident #metadata $($input)*)
+            };
+        }
+    }
+}
+
+/// Create the macro converting the values bound to a field whose type is not supported by the
+/// database library.
+fn sql_value_macro(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Tokens {
+    let mut converted_fields = vec![];
+    let mut converted_types = vec![];
+    for field in named {
+        if let Some(ref ident) = field.ident {
+            if field_ty_to_type(&field.ty).node.needs_conversion() {
+                converted_fields.push(ident);
+                converted_types.push(&field.ty);
+            }
+        }
+    }
+    let macro_name = Ident::new(&format!("tql_{}_sql_value", table_ident), Span::call_site());
+    quote! {
+        #[macro_export]
+        macro_rules! #macro_name {
+            #((#converted_fields, $value:expr) => {
+                ::tql::SqlValue::<&#converted_types>(::std::borrow::Borrow::borrow(&$value))
+            };)*
+            ($field:tt, $value:expr) => { $value };
+        }
+    }
+}
+
 fn check_pk_macro(named: &Punctuated<Field, Comma>, table_ident: &Ident) -> Tokens {
     let mut primary_key_found = false;
     for field in named {
@@ -608,7 +1327,8 @@ pub fn table_macro(item_struct: &ItemStruct) -> Tokens {
         for field in named {
             let typ = token_to_string(&field.ty);
             if let Some(ref ident) = field.ident {
-                if !typ.starts_with("Option") && typ != "PrimaryKey" {
+                // The fields with a default value are not mandatory.
+                if !typ.starts_with("Option") && typ != "PrimaryKey" && default_sql(field).is_none() {
                     mandatory_fields.push(ident);
                 }
                 if typ.starts_with("ForeignKey") {
@@ -628,12 +1348,19 @@ pub fn table_macro(item_struct: &ItemStruct) -> Tokens {
 
         let related_field_list_macro_name = Ident::new(&format!("tql_{}_related_field_list", table_ident), Span::call_site());
         let check_missing_fields_macro = check_missing_fields_macro(named, table_ident);
-        let field_list_macro = field_list_macro(named, table_ident);
-        let create_query_macro = create_query_macro(named, table_ident);
+        let columns = &flatten_fields(named, table_ident);
+        let field_list_macro = field_list_macro(columns, table_ident);
+        let create_query_macro = create_query_macro(columns, table_ident);
+        let alter_query_macros = alter_query_macros(named, columns, table_ident);
         let pk_macro = pk_macro(named, table_ident);
+        let deleted_filter_macros = deleted_filter_macros(named, table_ident);
         let related_pks_macro = related_pks_macro(named, table_ident);
         let related_table_macro = related_table_macro(named, table_ident);
         let check_pk_macro = check_pk_macro(named, table_ident);
+        let sql_value_macro = sql_value_macro(columns, table_ident);
+        let array_field_macro = array_field_macro(columns, table_ident);
+        let metadata_macro = metadata_macro(table_ident, &table_metadata(&table_ident.to_string()));
+        let macros_module = macros_module(&item_struct.vis, table_ident, TABLE_MACROS);
         quote! {
             #[macro_export]
             macro_rules! #related_field_list_macro_name {
@@ -646,8 +1373,14 @@ pub fn table_macro(item_struct: &ItemStruct) -> Tokens {
             #check_missing_fields_macro
             #field_list_macro
             #create_query_macro
+            #alter_query_macros
             #related_pks_macro
             #pk_macro
+            #deleted_filter_macros
+            #sql_value_macro
+            #array_field_macro
+            #metadata_macro
+            #macros_module
         }
     }
     else {
@@ -655,8 +1388,33 @@ pub fn table_macro(item_struct: &ItemStruct) -> Tokens {
     }
 }
 
+/// Get the value of a field from the row (the embedded fields are read from multiple columns).
+fn field_row_get(table: &str, field: &Field, with_delta: bool, index: &mut usize) -> Tokens {
+    let field_name = field.ident.as_ref().expect("field has name").to_string();
+    if let Some(embed_fields) = embedded_fields(table, &field_name) {
+        let typ = &field.ty;
+        let backend = create_backend();
+        let index_lit = backend.int_literal(*index);
+        *index += embed_fields.len();
+        let delta =
+            if with_delta {
+                quote! { #index_lit + delta }
+            }
+            else {
+                quote! { #index_lit }
+            };
+        // NOTE: this use the Span call_site() to work-around a privacy issue:
+        // https://github.com/rust-lang/rust/issues/46635
+        return quote_spanned! { Span::call_site() =>
+            <#typ as ::tql::SqlEmbed>::from_row(__tql_item_row, #delta)
+        };
+    }
+    to_row_get(field.ty.clone(), with_delta, index)
+}
+
 fn to_row_get(typ: syn::Type, with_delta: bool, index: &mut usize) -> Tokens {
-    if let syn::Type::Path(path) = typ {
+    let needs_conversion = field_ty_to_type(&typ).node.needs_conversion();
+    if let syn::Type::Path(ref path) = typ {
         let segment = path.path.segments.first().expect("first segment").into_value();
         if segment.ident == "ForeignKey" {
             // NOTE: this use the Span call_site() to work-around a privacy issue:
@@ -678,6 +1436,11 @@ fn to_row_get(typ: syn::Type, with_delta: bool, index: &mut usize) -> Tokens {
         else {
             quote! { #index_lit }
         };
+    if needs_conversion {
+        return quote_spanned! { Span::call_site() =>
+            __tql_item_row.get::<_, ::tql::SqlValue<#typ>>(#index_lit).0
+        };
+    }
     // NOTE: this use the Span call_site() to work-around a privacy issue:
     // https://github.com/rust-lang/rust/issues/46635
     quote_spanned! { Span::call_site() =>
@@ -30,9 +30,9 @@ use syn::{
 };
 use syn::spanned::Spanned;
 
-use ast::QueryType;
+use crate::ast::QueryType;
 use super::BackendGen;
-use SqlQueryWithArgs;
+use crate::SqlQueryWithArgs;
 
 pub struct PostgresBackend {}
 
@@ -90,11 +90,13 @@ impl BackendGen for PostgresBackend {
                         })
                 }}
             },
-            QueryType::Create => {
-                quote! {
-                    #connection_expr.prepare(#sql_query)
-                        .and_then(|result| result.execute(&[]))
-                }
+            QueryType::Batch => {
+                // NOTE: a batch has no arguments, but the arguments expression checks that the
+                // fields exist.
+                quote! {{
+                    let _: [(); 0] = #args_expr;
+                    #connection_expr.batch_execute(#sql_query)
+                }}
             },
             QueryType::InsertOne => {
                 quote! {
@@ -138,6 +140,13 @@ impl BackendGen for PostgresBackend {
                         .and_then(|result| result.execute(&#args_expr))
                 }
             },
+            QueryType::VersionedUpdate => {
+                quote! {
+                    #connection_expr.prepare(#sql_query)
+                        .and_then(|result| result.execute(&#args_expr))
+                        .and_then(::tql::check_version)
+                }
+            },
         }
     }
 
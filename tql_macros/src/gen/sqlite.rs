@@ -30,9 +30,9 @@ use syn::{
 };
 use syn::spanned::Spanned;
 
-use ast::QueryType;
+use crate::ast::QueryType;
 use super::BackendGen;
-use SqlQueryWithArgs;
+use crate::SqlQueryWithArgs;
 
 pub struct SqliteBackend {}
 
@@ -91,11 +91,13 @@ impl BackendGen for SqliteBackend {
                         })
                 }}
             },
-            QueryType::Create => {
-                quote! {
-                    #connection_expr.prepare(#sql_query)
-                        .and_then(|mut result| result.execute(&[]))
-                }
+            QueryType::Batch => {
+                // NOTE: a batch has no arguments, but the arguments expression checks that the
+                // fields exist.
+                quote! {{
+                    let _: [(); 0] = #args_expr;
+                    #connection_expr.execute_batch(#sql_query)
+                }}
             },
             QueryType::InsertOne => {
                 quote! {
@@ -134,6 +136,13 @@ impl BackendGen for SqliteBackend {
                         .and_then(|mut result| result.execute(&#args_expr))
                 }
             },
+            QueryType::VersionedUpdate => {
+                quote! {
+                    #connection_expr.prepare(#sql_query)
+                        .and_then(|mut result| result.execute(&#args_expr))
+                        .and_then(::tql::check_version)
+                }
+            },
         }
     }
 
@@ -32,8 +32,6 @@
  * TODO: error for unsupported types in backends.
  * TODO: remove useless empty string ("") in generated code (concat!("", "")).
  * TODO: avoid using quote_spanned and respan when possible and document all of their usage.
- * TODO: allow using a model from another module without #[macro_use].
- * TODO: write multi-crate test.
  * TODO: show proper error instead of using expect() to parse the content of the macros.
  *
  * TODO: show a better error when using a type that is not a table (both in ForeignKey<_> and in
@@ -56,7 +54,6 @@
  * TODO: support primary key with multiple columns.
  * TODO: allow selecting only some fields.
  * TODO: join on non foreign key.
- * TODO: allow user-defined functions (maybe with partial query?) and types.
  * TODO: add table_name attribute to allow changing the table name.
  *
  * TODO: remove allow_failure for beta when this issue is fixed:
@@ -81,6 +78,8 @@
 #[cfg(all(feature = "rusqlite", feature = "postgres"))]
 compile_error!("Only one of the following features must be enabled: sqlite, pg");
 
+#[macro_use]
+extern crate lazy_static;
 extern crate proc_macro;
 extern crate proc_macro2;
 #[macro_use]
@@ -88,6 +87,7 @@ extern crate quote;
 extern crate rand;
 #[macro_use]
 extern crate syn;
+extern crate tql_snapshot;
 
 #[macro_use]
 mod hashmap;
@@ -95,14 +95,17 @@ mod analyzer;
 mod arguments;
 mod ast;
 mod attribute;
+mod embed;
+mod enumeration;
 mod error;
+mod function;
 mod gen;
 mod methods;
 mod optimizer;
 mod parser;
 mod plugin;
+mod schema;
 mod sql;
-mod stable;
 mod state;
 mod string;
 mod types;
@@ -114,62 +117,90 @@ use proc_macro::TokenStream;
 use proc_macro::{Group, TokenTree};
 use proc_macro2::{Spacing, Span};
 use proc_macro2::TokenStream as Tokens;
+use quote::ToTokens;
 
 use syn::{
     Expr,
     Ident,
     Item,
     ItemEnum,
+    ItemStruct,
+    LitStr,
+    TypePath,
     parse,
     parse2,
 };
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 
-use analyzer::{
+use crate::analyzer::{
+    SubQueryCheck,
     analyze,
     analyze_methods,
     analyze_types,
+    check_method_names,
     get_aggregate_calls,
+    get_alter_idents,
     get_insert_idents,
     get_limit_args,
     get_method_calls,
     get_sort_idents,
+    get_subquery_checks,
     get_values_idents,
 };
 #[cfg(feature = "unstable")]
-use analyzer::get_insert_position;
-use arguments::{Arg, Args, arguments};
-use ast::{
+use crate::analyzer::get_insert_position;
+use crate::arguments::{Arg, Args, arguments};
+use crate::attribute::{field_ty_to_type, parse_field_attributes};
+use crate::ast::{
     Aggregate,
+    DateExpression,
     Expression,
+    IntervalOperator,
     Join,
     MethodCall,
     Query,
     QueryType,
     query_type,
 };
-use error::{Error, Result};
+use crate::embed::{embed_impl, embed_macro};
+use crate::enumeration::enum_impl;
+use crate::error::{Error, Result};
+use crate::function::{FunctionKind, function_macro};
 #[cfg(not(feature = "unstable"))]
-use error::compiler_error;
-use gen::{
+use crate::error::compiler_error;
+use crate::gen::{
     gen_check_missing_fields,
     generate_errors,
     gen_query,
     get_struct_fields,
+    struct_macro_path,
     table_macro,
     table_methods,
     tosql_impl,
 };
-use optimizer::optimize;
-use parser::Parser;
-use stable::generate_macro_patterns;
+#[cfg(feature = "unstable")]
+use crate::gen::with_table_imports;
+use crate::optimizer::optimize;
+use crate::parser::Parser;
+use crate::state::{
+    EmbedField,
+    SqlMethods,
+    add_embed_metadata,
+    add_table_metadata,
+    aggregates_singleton,
+    clear_state,
+    embedded_fields,
+    is_table_known,
+    methods_singleton,
+};
+use crate::types::Type;
 
 struct SqlQueryWithArgs {
     aggregate_calls: Vec<(String, Expr)>,
     aggregates: Vec<Aggregate>,
     arguments: Args,
-    filter_method_calls: Vec<(MethodCall, Option<Expression>)>,
+    filter_method_calls: Vec<(String, MethodCall, Option<Expression>)>,
     idents: Vec<Ident>,
     #[cfg(feature = "unstable")]
     insert_call_span: Option<Span>,
@@ -179,8 +210,10 @@ struct SqlQueryWithArgs {
     literal_arguments: Args,
     query_type: QueryType,
     sql: Tokens,
-    stable_macro_query: Tokens,
+    subquery_checks: Vec<SubQueryCheck>,
     table_name: Ident,
+    table_path: Option<syn::Path>,
+    uses_table_struct: bool,
 }
 
 /// Expand the `sql!()` macro.
@@ -189,8 +222,9 @@ struct SqlQueryWithArgs {
 #[cfg(feature = "unstable")]
 #[proc_macro]
 pub fn sql(input: TokenStream) -> TokenStream {
+    let input = strip_table_metadata(input.into());
     let arguments: Arguments =
-        match parse(input) {
+        match parse2(input.clone()) {
             Ok(args) => args,
             Err(error) => return generate_errors(vec![Error::new(
                     &format!("cannot parse expression in sql!(): {}", error), Span::call_site())]),
@@ -211,10 +245,21 @@ pub fn sql(input: TokenStream) -> TokenStream {
                         &format!("this macro takes 1 parameter but {} parameters were supplied", arg_count),
                                                      Span::call_site(), "E0061")]),
         };
+    if let Some(callback) = metadata_callback(sql_expr, "sql", &input) {
+        return callback.into();
+    }
     let sql_expr = quote! { #sql_expr };
     let sql_result = to_sql_query(sql_expr.into());
     match sql_result {
-        Ok(sql_query_with_args) => gen_query(&sql_query_with_args, connection_ident).0,
+        Ok(sql_query_with_args) => {
+            let (code, bindings) = gen_query(&sql_query_with_args, connection_ident);
+            let code = proc_macro2::TokenStream::from(code);
+            let gen = quote! {{
+                #(#bindings)*
+                #code
+            }};
+            gen.into()
+        },
         Err(errors) => generate_errors(errors),
     }
 }
@@ -225,12 +270,112 @@ pub fn sql(input: TokenStream) -> TokenStream {
 #[cfg(feature = "unstable")]
 #[proc_macro]
 pub fn to_sql(input: TokenStream) -> TokenStream {
-    match to_sql_query(input.into()) {
-        Ok(args) => args.sql.into(),
+    let input = strip_table_metadata(input.into());
+    if let Ok(expr) = parse2::<Expr>(input.clone()) {
+        if let Some(callback) = metadata_callback(&expr, "to_sql", &input) {
+            return callback.into();
+        }
+    }
+    match to_sql_query(input) {
+        Ok(args) => with_table_imports(args.table_path.as_ref(), &args.table_name, args.sql).into(),
         Err(errors) => generate_errors(errors),
     }
 }
 
+/// Register the information about the tables given by the `tql_<Table>_metadata!()` macros at
+/// the start of the `input` of a query macro (`@tql_metadata Table "metadata"`, once per table) and
+/// return the rest of the input.
+fn strip_table_metadata(input: Tokens) -> Tokens {
+    clear_state();
+    strip_metadata(input, "tql_metadata", add_table_metadata)
+}
+
+/// Call `add_metadata()` with the name and the metadata of each `@<prefix> Name "metadata"` at the
+/// start of the `input` and return the rest of the input.
+fn strip_metadata(input: Tokens, prefix: &str, add_metadata: fn(&str, &str)) -> Tokens {
+    let tokens: Vec<_> = input.into_iter().collect();
+    let mut index = 0;
+    while let Some(metadata) = tokens.get(index..index + 4) {
+        let is_prefix =
+            match metadata[0] {
+                proc_macro2::TokenTree::Punct(ref punct) => punct.as_char() == '@' && metadata[1].to_string() == prefix,
+                _ => false,
+            };
+        if !is_prefix {
+            break;
+        }
+        match parse2::<LitStr>(Tokens::from_iter(metadata[3..].iter().cloned())) {
+            Ok(value) => add_metadata(&metadata[2].to_string(), &value.value()),
+            Err(_) => break,
+        }
+        index += 4;
+    }
+    Tokens::from_iter(tokens[index..].iter().cloned())
+}
+
+/// Get the code calling the query macro `callback` back with the `input` through the
+/// `tql_<Table>_metadata!()` macros of the tables of the query `sql_expr` (including the tables of
+/// its subqueries) when they are not known yet, i.e. on the first expansion of the query.
+/// This code also uses the tables as types, so that an unknown table is reported like an unknown
+/// type.
+/// There is no callback when the query has a syntax error or calls an unknown method: these errors
+/// are reported directly since they do not depend on the tables.
+fn metadata_callback(sql_expr: &Expr, callback: &str, input: &Tokens) -> Option<Tokens> {
+    let method_calls = Parser::new().parse(sql_expr).ok()?;
+    check_method_names(&method_calls).ok()?;
+    let mut tables: Vec<syn::Path> = vec![];
+    for path in query_tables(sql_expr) {
+        let is_new_table = {
+            let table = path.segments.last().expect("last segment of path").into_value().ident.to_string();
+            !is_table_known(&table) && !tables.iter()
+                .any(|other| other.segments.last().expect("last segment of path").into_value().ident == table)
+        };
+        if is_new_table {
+            tables.push(path);
+        }
+    }
+    let mut macros = tables.iter().map(|table| struct_macro_path(table, "metadata"));
+    let first_macro = macros.next()?;
+    let callback = Ident::new(callback, Span::call_site());
+    let tables = &tables;
+    Some(quote! {{
+        #(let _: ::std::marker::PhantomData<#tables>;)*
+        #first_macro!(#([#macros])* [#callback] #input)
+    }})
+}
+
+/// Get the paths of the tables of the query `expr` and of its subqueries (nested `sql!()` calls).
+fn query_tables(expr: &Expr) -> Vec<syn::Path> {
+    let mut tables: Vec<_> = query_table(expr).into_iter().cloned().collect();
+    add_subquery_tables(expr.into_token_stream(), &mut tables);
+    tables
+}
+
+/// Add the paths of the tables of the subqueries found in the `tokens` to `tables`.
+fn add_subquery_tables(tokens: Tokens, tables: &mut Vec<syn::Path>) {
+    let tokens: Vec<_> = tokens.into_iter().collect();
+    for (index, token) in tokens.iter().enumerate() {
+        if let proc_macro2::TokenTree::Group(ref group) = *token {
+            let is_subquery = index >= 2 && tokens[index - 2].to_string() == "sql" &&
+                tokens[index - 1].to_string() == "!";
+            match parse2::<Expr>(group.stream()) {
+                Ok(ref expr) if is_subquery => tables.extend(query_tables(expr)),
+                _ => add_subquery_tables(group.stream(), tables),
+            }
+        }
+    }
+}
+
+/// Get the path of the table of the query `expr`, i.e. the path at the start of the method calls.
+fn query_table(expr: &Expr) -> Option<&syn::Path> {
+    match *expr {
+        Expr::MethodCall(ref call) => query_table(&call.receiver),
+        Expr::Index(ref index) => query_table(&index.expr),
+        Expr::Path(ref path) => Some(&path.path),
+        _ => None,
+    }
+}
+
 /// Convert the Rust code to an SQL string with its type, arguments, joins, and aggregate fields.
 fn to_sql_query(input: proc_macro2::TokenStream) -> Result<SqlQueryWithArgs> {
     if input.is_empty() {
@@ -263,13 +408,20 @@ fn to_sql_query(input: proc_macro2::TokenStream) -> Result<SqlQueryWithArgs> {
             _ => vec![],
         };
     let query_type = query_type(&query);
+    // NOTE: create() and drop() can also be called on an enum with a native storage.
+    let uses_table_struct =
+        match query {
+            Query::CreateTable { .. } | Query::Drop { .. } => false,
+            _ => true,
+        };
     let mut idents = get_sort_idents(&query);
     idents.extend(get_values_idents(&query));
+    idents.extend(get_alter_idents(&query));
     let insert_idents = get_insert_idents(&query);
     let limit_exprs = get_limit_args(&query);
     let filter_method_calls = get_method_calls(&query);
     let aggregate_calls = get_aggregate_calls(&query);
-    let stable_macro_query = generate_macro_patterns(&query, &method_calls);
+    let subquery_checks = get_subquery_checks(&query);
     let (arguments, literal_arguments) = arguments(query);
     Ok(SqlQueryWithArgs {
         aggregates,
@@ -285,14 +437,16 @@ fn to_sql_query(input: proc_macro2::TokenStream) -> Result<SqlQueryWithArgs> {
         literal_arguments,
         query_type,
         sql,
-        stable_macro_query,
+        subquery_checks,
         table_name,
+        table_path: method_calls.path.clone(),
+        uses_table_struct,
     })
 }
 
 /// Expand the `#[SqlTable]` attribute.
 /// This attribute must be used on structs to tell tql that it represents an SQL table.
-#[proc_macro_derive(SqlTable)]
+#[proc_macro_derive(SqlTable, attributes(sql))]
 pub fn sql_table(input: TokenStream) -> TokenStream {
     let item: Item =
         match parse(input) {
@@ -303,29 +457,11 @@ pub fn sql_table(input: TokenStream) -> TokenStream {
 
     let gen =
         if let Item::Struct(item_struct) = item {
-            let (fields, primary_key, impls) = get_struct_fields(&item_struct);
-            let mut compiler_errors = quote! {};
-            let errors =
-                if let Err(errors) = fields {
-                    for error in errors {
-                        add_error(error, &mut compiler_errors);
-                    }
-                    compiler_errors
-                }
-                else {
-                    quote! {
-                    }
-                };
-            let code = tosql_impl(&item_struct, primary_key);
-            let methods = table_methods(&item_struct);
-            let table_macro = table_macro(&item_struct);
-            let code = quote! {
-                #errors
-                #methods
-                #code
-                #table_macro
-            };
-            concat_token_stream(code.into(), impls)
+            clear_state();
+            if let Some(callback) = embed_callback(&item_struct) {
+                return callback.into();
+            }
+            table_impl(&item_struct)
         }
         else {
             let mut compiler_errors = quote! {};
@@ -337,6 +473,169 @@ pub fn sql_table(input: TokenStream) -> TokenStream {
     gen
 }
 
+/// Expand the `#[SqlTable]` attribute called back by the `tql_<Struct>_embed!()` macros of the
+/// structs embedded in the table, with their fields at the start of the input
+/// (`@tql_embed Struct "fields"`, once per struct).
+#[doc(hidden)]
+#[proc_macro]
+pub fn sql_table_with_embeds(input: TokenStream) -> TokenStream {
+    clear_state();
+    let input = strip_metadata(input.into(), "tql_embed", add_embed_metadata);
+    match parse2(input) {
+        Ok(item_struct) => table_impl(&item_struct),
+        Err(error) => generate_errors(vec![Error::new(
+                &format!("cannot parse expression in SqlTable: {}", error), Span::call_site())]),
+    }
+}
+
+/// Get the code calling the `#[SqlTable]` attribute back through the `tql_<Struct>_embed!()`
+/// macros of the structs embedded in the table `item_struct`, so that it knows their fields.
+fn embed_callback(item_struct: &ItemStruct) -> Option<Tokens> {
+    let mut embeds = vec![];
+    for field in &item_struct.fields {
+        // NOTE: the errors in the attributes are reported by get_struct_fields().
+        if !parse_field_attributes(&field.attrs, &mut vec![]).embed {
+            continue;
+        }
+        if let syn::Type::Path(TypePath { ref path, qself: None }) = field.ty {
+            if let Type::UserDefined(_) = field_ty_to_type(&field.ty).node {
+                if !embeds.contains(path) {
+                    embeds.push(path.clone());
+                }
+            }
+        }
+    }
+    let mut macros = embeds.iter().map(|embed| struct_macro_path(embed, "embed"));
+    let first_macro = macros.next()?;
+    Some(quote! {
+        #first_macro!(#([#macros])* [::tql_macros::sql_table_with_embeds] #item_struct);
+    })
+}
+
+/// Generate the implementation of `SqlTable` and the macros of the table `item_struct`.
+fn table_impl(item_struct: &ItemStruct) -> TokenStream {
+    let (fields, primary_key, impls) = get_struct_fields(item_struct);
+    let mut compiler_errors = quote! {};
+    let errors =
+        if let Err(errors) = fields {
+            for error in errors {
+                add_error(error, &mut compiler_errors);
+            }
+            compiler_errors
+        }
+        else {
+            quote! {
+            }
+        };
+    let code = tosql_impl(item_struct, primary_key);
+    let methods = table_methods(item_struct);
+    let table_macro = table_macro(item_struct);
+    let code = quote! {
+        #errors
+        #methods
+        #code
+        #table_macro
+    };
+    concat_token_stream(code.into(), impls)
+}
+
+/// Expand the `#[derive(SqlEmbed)]` attribute.
+/// This attribute allows using a struct as the type of a `#[sql(embed)]` field of an SQL table.
+#[proc_macro_derive(SqlEmbed, attributes(sql))]
+pub fn sql_embed(input: TokenStream) -> TokenStream {
+    let item: Item =
+        match parse(input) {
+            Ok(item) => item,
+            Err(error) => return generate_errors(vec![Error::new(
+                    &format!("cannot parse expression in SqlEmbed: {}", error), Span::call_site())]),
+        };
+
+    // NOTE: the macro giving the fields to the tables is created even when the struct has errors, so
+    // that the tables embedding it do not report an error too.
+    let (result, embed_macro) =
+        if let Item::Struct(item_struct) = item {
+            (embed_impl(&item_struct), embed_macro(&item_struct))
+        }
+        else {
+            (Err(vec![Error::new("Expected struct but found", item.span())]), quote! {}) // TODO: improve this message.
+        };
+    match result {
+        Ok(code) => quote! {
+            #code
+            #embed_macro
+        }.into(),
+        Err(errors) => {
+            let mut compiler_errors = quote! {};
+            for error in errors {
+                add_error(error, &mut compiler_errors);
+            }
+            quote! {
+                #compiler_errors
+                #embed_macro
+            }.into()
+        },
+    }
+}
+
+/// Expand the `#[derive(SqlEnum)]` attribute.
+/// This attribute allows using a fieldless enum as the type of a field of an SQL table.
+#[proc_macro_derive(SqlEnum, attributes(sql))]
+pub fn sql_enum(input: TokenStream) -> TokenStream {
+    let item: Item =
+        match parse(input) {
+            Ok(item) => item,
+            Err(error) => return generate_errors(vec![Error::new(
+                    &format!("cannot parse expression in SqlEnum: {}", error), Span::call_site())]),
+        };
+
+    let result =
+        if let Item::Enum(item_enum) = item {
+            enum_impl(&item_enum)
+        }
+        else {
+            Err(vec![Error::new("Expected enum but found", item.span())]) // TODO: improve this message.
+        };
+    match result {
+        Ok(code) => code.into(),
+        Err(errors) => {
+            let mut compiler_errors = quote! {};
+            for error in errors {
+                add_error(error, &mut compiler_errors);
+            }
+            compiler_errors.into()
+        },
+    }
+}
+
+/// Expand the `#[sql_aggregate]` attribute.
+/// This attribute must be used on a function declaration to tell tql that it is an SQL aggregate
+/// function that can be used in the `aggregate()` method.
+#[proc_macro_attribute]
+pub fn sql_aggregate(attributes: TokenStream, item: TokenStream) -> TokenStream {
+    user_function(attributes, item, FunctionKind::Aggregate)
+}
+
+/// Expand the `#[sql_function]` attribute.
+/// This attribute must be used on a function declaration to tell tql that it is an SQL function
+/// that can be called as a method on a field in the `filter()` method.
+#[proc_macro_attribute]
+pub fn sql_function(attributes: TokenStream, item: TokenStream) -> TokenStream {
+    user_function(attributes, item, FunctionKind::Method)
+}
+
+fn user_function(attributes: TokenStream, item: TokenStream, kind: FunctionKind) -> TokenStream {
+    match function_macro(attributes.into(), item.into(), kind) {
+        Ok(code) => code.into(),
+        Err(errors) => {
+            let mut compiler_errors = quote! {};
+            for error in errors {
+                add_error(error, &mut compiler_errors);
+            }
+            compiler_errors.into()
+        },
+    }
+}
+
 #[cfg(feature = "unstable")]
 fn respan_tokens_with(tokens: Tokens, span: proc_macro::Span) -> Tokens {
     let tokens: proc_macro2::TokenStream = respan_with(tokens.into(), span).into();
@@ -361,18 +660,90 @@ fn respan_with(tokens: TokenStream, span: proc_macro::Span) -> TokenStream {
     FromIterator::from_iter(result.into_iter())
 }
 
+/// Get the expression type checking a date expression with the stubs of the tql crate.
+fn date_expression_typecheck(date: &DateExpression) -> Tokens {
+    match *date {
+        DateExpression::Interval { ref amount, ref date, operator, ref unit } => {
+            let date = date_expression_typecheck(date);
+            match operator {
+                IntervalOperator::Add => quote! { (#date + ::tql::#unit(#amount)) },
+                IntervalOperator::Sub => quote! { (#date - ::tql::#unit(#amount)) },
+            }
+        },
+        DateExpression::Now(ref now) => quote! { ::tql::#now() },
+    }
+}
+
+/// Get the expression type checking the value compared to a method call.
+fn comparison_operand_typecheck(expr: &Expression) -> Tokens {
+    match DateExpression::from_expression(expr) {
+        Some(date) => date_expression_typecheck(&date),
+        None => quote! { #expr },
+    }
+}
+
+/// Get the expression type checking a (possibly chained) method call on the field bound to the name of
+/// the field.
+/// The built-in methods are called on the `ToTqlType` stubs while the user-defined methods are called
+/// through the macro created by the #[sql_function] attribute.
+fn method_call_typecheck(call: &MethodCall, methods: &SqlMethods, table_ident: &Ident) -> Tokens {
+    let method = &call.method_name;
+    let arguments = &call.arguments;
+    let is_builtin = !is_user_method(method, methods);
+    let object =
+        match call.object_call {
+            Some(ref object_call) => {
+                let object = method_call_typecheck(object_call, methods, table_ident);
+                if is_builtin {
+                    let method_name = quote_spanned! { table_ident.span() =>
+                        to_tql_type
+                    };
+                    quote! {
+                        #object.#method_name()
+                    }
+                }
+                else {
+                    object
+                }
+            },
+            None => {
+                let field = &call.object_name;
+                quote! {
+                    #field
+                }
+            },
+        };
+    if is_builtin {
+        quote! {
+            #object.#method(#(#arguments),*)
+        }
+    }
+    else {
+        // NOTE: a user-defined method is type checked by the macro created by the #[sql_function]
+        // attribute.
+        let macro_name = Ident::new(&FunctionKind::Method.macro_name(&method.to_string()), method.span());
+        quote_spanned! { method.span() =>
+            #macro_name!(check, #object #(, #arguments)*)
+        }
+    }
+}
+
+/// Check if the `method` is a user-defined method.
+/// The unknown methods are not, so that they are called on the `ToTqlType` stubs, which reports them.
+fn is_user_method(method: &Ident, methods: &SqlMethods) -> bool {
+    let method = method.to_string();
+    !methods.contains_key(&method) && FunctionKind::Method.is_declared(&method)
+}
+
 /// Get the arguments to send to the `postgres::stmt::Statement::query` or
-/// `postgres::stmt::Statement::execute` method.
+/// `postgres::stmt::Statement::execute` method, with the variables binding them on stable.
 fn typecheck_arguments(args: &SqlQueryWithArgs) -> (Tokens, Vec<Tokens>) {
     let table_ident = &args.table_name;
     let mut arg_refs = vec![];
     let mut fns = vec![];
     let mut assigns = vec![];
     let mut typechecks = vec![];
-    #[cfg(not(feature = "unstable"))]
-    let mut metavars = vec![];
-    #[cfg(feature = "unstable")]
-    let metavars = vec![];
+    let mut bindings = vec![];
     let mut next_name = (0..).map(|counter|
         Ident::new(&format!("__tql_arg{}", counter), Span::call_site())
     );
@@ -380,9 +751,11 @@ fn typecheck_arguments(args: &SqlQueryWithArgs) -> (Tokens, Vec<Tokens>) {
     let ident = Ident::new("__tql_table", Span::call_site());
     {
         let mut add_arg = |arg: &Arg| {
+            let date_expression = DateExpression::from_expression(&arg.expression);
             let arg_name =
                 match arg.expression {
                     Expr::Lit(_) => None,
+                    _ if date_expression.is_some() => None,
                     _ => Some(next_name.next().expect("Next name")),
                 };
             if let Some(name) = arg.field_name.as_ref()
@@ -404,6 +777,19 @@ fn typecheck_arguments(args: &SqlQueryWithArgs) -> (Tokens, Vec<Tokens>) {
                 });
                 #[cfg(feature = "unstable")]
                 let expr = &arg.expression;
+                let ident = dummy_table_ident(arg.field_name_prefix.as_ref(), table_ident);
+                let name =
+                    match arg.embedded_field {
+                        Some(ref subfield) => quote! { #name.#subfield },
+                        None => quote! { #name },
+                    };
+                if let Some(ref date) = date_expression {
+                    let date = date_expression_typecheck(date);
+                    typechecks.push(quote_spanned! { arg.expression.span() =>
+                        ::tql::check_comparison(&#ident.#name, &#date);
+                    });
+                    return arg_name;
+                }
                 assigns.push(quote_spanned! { arg.expression.span() =>
                     #ident.#name = #convert_ident(&#expr.#to_owned_ident());
                 });
@@ -428,8 +814,11 @@ fn typecheck_arguments(args: &SqlQueryWithArgs) -> (Tokens, Vec<Tokens>) {
                     #[cfg(not(feature = "unstable"))]
                     {
                         if let Some(name) = _name {
-                            metavars.push(quote! { #name });
-                            arg_refs.push(quote! { &#name })
+                            let expr = &arg.expression;
+                            bindings.push(quote_spanned! { expr.span() =>
+                                let #name = &(#expr);
+                            });
+                            arg_refs.push(sql_value(arg, quote! { #name }, table_ident));
                         }
                         else {
                             let expr = &arg.expression;
@@ -439,7 +828,17 @@ fn typecheck_arguments(args: &SqlQueryWithArgs) -> (Tokens, Vec<Tokens>) {
                     #[cfg(feature = "unstable")]
                     {
                         let expr = &arg.expression;
-                        arg_refs.push(quote! { &(#expr) });
+                        match _name {
+                            // NOTE: an embedded struct is sent as one argument per field, so it is
+                            // bound to evaluate its expression only once.
+                            Some(name) if embedded_arg_fields(arg, table_ident).is_some() => {
+                                bindings.push(quote_spanned! { expr.span() =>
+                                    let #name = &(#expr);
+                                });
+                                arg_refs.push(sql_value(arg, quote! { #name }, table_ident));
+                            },
+                            _ => arg_refs.push(sql_value(arg, quote! { (#expr) }, table_ident)),
+                        }
                     }
                 },
             }
@@ -475,43 +874,128 @@ fn typecheck_arguments(args: &SqlQueryWithArgs) -> (Tokens, Vec<Tokens>) {
         typechecks.push(code);
     }
 
+    let tql_ident = quote_spanned! { Span::call_site() =>
+        ::tql
+    };
+    let methods = methods_singleton();
     for data in &args.filter_method_calls {
-        let call = &data.0;
+        let ident = dummy_table_ident(Some(&data.0), table_ident);
+        let call = &data.1;
         let field = &call.object_name;
-        let method = &call.method_name;
-        let arguments = &call.arguments;
-        let trait_ident = quote_spanned! { table_ident.span() =>
-            tql::ToTqlType;
-        };
-        let method_name = quote_spanned! { table_ident.span() =>
-            to_tql_type
-        };
+        let call_expr = method_call_typecheck(call, methods, table_ident);
+        // NOTE: the methods returning a string or a date (including the user-defined ones) are checked
+        // with check_comparison() so that their result can be compared with any compatible type (e.g. a
+        // String as well as a &str).
+        let returns_comparable = methods.get(&call.method_name.to_string())
+            .map(|overloads| overloads.iter().any(|method| match method.return_type {
+                Type::LocalDateTime | Type::NaiveDate | Type::NaiveDateTime | Type::String | Type::UtcDateTime =>
+                    true,
+                _ => false,
+            }))
+            .unwrap_or(true);
         let comparison_expr =
-            if let Some(ref expr) = data.1 {
+            match data.2 {
+                Some(ref expr) if returns_comparable => {
+                    let operand = comparison_operand_typecheck(expr);
+                    quote_spanned! { expr.span() =>
+                        #tql_ident::check_comparison(&#call_expr, &#operand);
+                    }
+                },
+                Some(ref expr) => {
+                    let operand = comparison_operand_typecheck(expr);
+                    quote! {
+                        let mut _data = #call_expr;
+                        _data = #operand;
+                    }
+                },
+                None => {
+                    quote_spanned! { call.position =>
+                        true == #call_expr;
+                    }
+                },
+            };
+        let mut first_call = call;
+        while let Some(ref object_call) = first_call.object_call {
+            first_call = object_call;
+        }
+        let uses_builtin_method = {
+            let mut method_call = Some(call);
+            let mut uses_builtin_method = false;
+            while let Some(current_call) = method_call {
+                uses_builtin_method |= !is_user_method(&current_call.method_name, methods);
+                method_call = current_call.object_call.as_ref().map(|call| &**call);
+            }
+            uses_builtin_method
+        };
+        let trait_import =
+            if uses_builtin_method {
+                quote_spanned! { table_ident.span() =>
+                    use tql::ToTqlType;
+                }
+            }
+            else {
+                quote! {}
+            };
+        let field_value =
+            if !is_user_method(&first_call.method_name, methods) {
+                let method_name = quote_spanned! { table_ident.span() =>
+                    to_tql_type
+                };
                 quote! {
-                    let mut _data = #field.#method(#(#arguments),*);
-                    _data = #expr;
+                    #ident.#field.#method_name()
                 }
             }
             else {
-                quote_spanned! { call.position =>
-                    true == #field.#method(#(#arguments),*);
+                quote! {
+                    #tql_ident::field_value(&#ident.#field)
                 }
             };
         typechecks.push(quote! {{
-            use #trait_ident;
-            let #field = #ident.#field.#method_name();
+            #trait_import
+            let #field = #field_value;
             #comparison_expr
         }});
     }
 
-    let tql_ident = quote_spanned! { Span::call_site() =>
-        ::tql
-    };
+    for check in &args.subquery_checks {
+        if let Some(ref column) = check.column {
+            let column_ident = dummy_table_ident(Some(&check.table), table_ident);
+            // NOTE: use the span of the column so that a type error points to it.
+            #[cfg(feature = "unstable")]
+            let column_ident = Ident::new(&column_ident.to_string(), column.span());
+            if let Some((ref table, ref field)) = check.field {
+                let field_ident = dummy_table_ident(Some(table), table_ident);
+                typechecks.push(quote_spanned! { column.span() =>
+                    #tql_ident::check_subquery_column(&#field_ident.#field, &#column_ident.#column);
+                });
+            }
+            else {
+                typechecks.push(quote_spanned! { column.span() =>
+                    let _ = &#column_ident.#column;
+                });
+            }
+        }
+    }
+
+    let aggregates = aggregates_singleton();
     for &(ref function, ref expr) in &args.aggregate_calls {
-        let function = Ident::new(function, Span::call_site());
+        let call =
+            if aggregates.contains_key(function) {
+                let function = Ident::new(function, Span::call_site());
+                quote! {
+                    #tql_ident::aggregates::#function()
+                }
+            }
+            else {
+                // NOTE: a user-defined aggregate is type checked by the macro created by the
+                // #[sql_aggregate] attribute.
+                let macro_name = Ident::new(&FunctionKind::Aggregate.macro_name(function), Span::call_site());
+                quote! {
+                    #macro_name!(check)
+                }
+            };
         typechecks.push(quote! {
-            let mut _data = #tql_ident::aggregates::#function();
+            let mut _data = #call;
             _data = #expr;
         });
     }
@@ -520,12 +1004,40 @@ fn typecheck_arguments(args: &SqlQueryWithArgs) -> (Tokens, Vec<Tokens>) {
         ::tql::SqlTable
     };
 
+    // The tables used in subqueries get their own dummy struct.
+    let mut other_tables = vec![];
+    {
+        let tables = args.arguments.iter().chain(args.literal_arguments.iter())
+            .filter_map(|arg| arg.field_name_prefix.as_ref())
+            .chain(args.filter_method_calls.iter().map(|data| &data.0))
+            .chain(args.subquery_checks.iter().map(|check| &check.table));
+        for table in tables {
+            if table_ident != table.as_str() && !other_tables.contains(table) {
+                other_tables.push(table.clone());
+            }
+        }
+    }
+    let other_dummies = other_tables.iter()
+        .map(|table| {
+            let ident = dummy_table_ident(Some(table), table_ident);
+            let table = Ident::new(table, table_ident.span());
+            quote_spanned! { table.span() =>
+                #[allow(unused_mut)]
+                let mut #ident = <#table as #trait_ident>::_tql_default();
+            }
+        });
+
+    if !args.uses_table_struct {
+        return (quote! { [] }, bindings);
+    }
+
     let tokens = quote_spanned! { table_ident.span() => {
         // Type check the arguments by creating a dummy struct.
         // TODO: check that this let is not in the generated binary.
         {
             let _tql_closure = || {
                 let mut #ident = <#table_ident as #trait_ident>::_tql_default();
+                #(#other_dummies)*
                 #({
                     #fns
                     #assigns
@@ -536,7 +1048,73 @@ fn typecheck_arguments(args: &SqlQueryWithArgs) -> (Tokens, Vec<Tokens>) {
 
         [#(#arg_refs),*]
     }};
-    (tokens, metavars)
+    (tokens, bindings)
+}
+
+/// Get the table and the name of the field assigned or compared to the argument `arg` (`None` if
+/// the argument is not the value of a field).
+fn arg_field(arg: &Arg, main_table: &Ident) -> Option<(String, String)> {
+    let field_name = arg.field_name.as_ref()?.to_string();
+    let index = field_name.find('.')
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let table = arg.field_name_prefix.clone().unwrap_or_else(|| main_table.to_string());
+    Some((table, field_name[index..].to_string()))
+}
+
+/// Get the fields of the struct given as the argument `arg` for an embedded field (`None` if the
+/// argument is not an embedded struct).
+fn embedded_arg_fields(arg: &Arg, main_table: &Ident) -> Option<Vec<EmbedField>> {
+    if arg.embedded_field.is_some() {
+        return None;
+    }
+    let (table, field_name) = arg_field(arg, main_table)?;
+    embedded_fields(&table, &field_name)
+}
+
+/// Get the reference to the value bound to the query for the argument `arg`.
+/// The value of a field is given to the table macro converting the types that are not supported by
+/// the database library.
+fn sql_value(arg: &Arg, value: Tokens, main_table: &Ident) -> Tokens {
+    match arg_field(arg, main_table) {
+        Some((table, field_name)) => {
+            let macro_name = Ident::new(&format!("tql_{}_sql_value", table), Span::call_site());
+            if let Some(ref subfield) = arg.embedded_field {
+                let column = Ident::new(&format!("{}_{}", field_name, subfield), Span::call_site());
+                return quote! {
+                    &#macro_name!(#column, #value)
+                };
+            }
+            // NOTE: an embedded struct is sent as one argument per field, in the order of its columns.
+            if let Some(embed_fields) = embedded_arg_fields(arg, main_table) {
+                let values = embed_fields.iter()
+                    .map(|embed_field| {
+                        let column = Ident::new(&format!("{}_{}", field_name, embed_field.name), Span::call_site());
+                        let subfield = Ident::new(&embed_field.name, Span::call_site());
+                        quote! {
+                            &#macro_name!(#column, (#value).#subfield)
+                        }
+                    });
+                return quote! {
+                    #(#values),*
+                };
+            }
+            let field = Ident::new(&field_name, Span::call_site());
+            quote! {
+                &#macro_name!(#field, #value)
+            }
+        },
+        None => quote! { &#value },
+    }
+}
+
+/// Get the identifier of the dummy struct used to typecheck the fields of `table`.
+fn dummy_table_ident(table: Option<&String>, main_table: &Ident) -> Ident {
+    match table {
+        Some(table) if main_table != table.as_str() =>
+            Ident::new(&format!("__tql_table_{}", table), Span::call_site()),
+        _ => Ident::new("__tql_table", Span::call_site()),
+    }
 }
 
 fn concat_token_stream(stream1: TokenStream, stream2: TokenStream) -> TokenStream {
@@ -622,7 +1200,17 @@ pub fn stable_to_sql(input: TokenStream) -> TokenStream {
         if let Expr::Field(ref field) = variant.as_ref().unwrap().1 {
             if let Expr::Tuple(ref tuple) = *field.base {
                 if let Expr::Macro(ref macr) = **tuple.elems.first().unwrap().value() {
-                    let tts: Vec<_> = macr.mac.tts.clone().into_iter().collect();
+                    let input = macr.mac.tts.clone();
+                    // NOTE: the query with the metadata is expanded inside the __tql_call_macro!()
+                    // of the query without it, so its macro needs another name to avoid ambiguity.
+                    let call_macro_name =
+                        if input.clone().into_iter().next().map_or(false, |token| token.to_string() == "@") {
+                            Ident::new("__tql_call_metadata_macro", Span::call_site())
+                        }
+                        else {
+                            Ident::new("__tql_call_macro", Span::call_site())
+                        };
+                    let tts: Vec<_> = strip_table_metadata(input).into_iter().collect();
                     let (sql_query, connection_expr) =
                         if let proc_macro2::TokenTree::Punct(ref op) = tts[1] { 
                             if op.as_char() == ',' && op.spacing() == Spacing::Alone {
@@ -641,33 +1229,33 @@ pub fn stable_to_sql(input: TokenStream) -> TokenStream {
                         };
                     let sql_query = sql_query.iter().cloned();
                     let sql_query = proc_macro2::TokenStream::from_iter(sql_query);
+                    if let Ok(expr) = parse2::<Expr>(sql_query.clone()) {
+                        let input = quote! { $connection, $($tt)* };
+                        if let Some(callback) = metadata_callback(&expr, "sql", &input) {
+                            let gen = quote! {
+                                macro_rules! __tql_call_macro {
+                                    ($connection:ident, $($tt:tt)*) => {
+                                        #callback
+                                    };
+                                }
+                            };
+                            return gen.into();
+                        }
+                    }
                     let sql_result = to_sql_query(sql_query);
-                    let (code, metavars, extract_macro) = match sql_result {
-                        Ok(sql_query_with_args) => {
-                            let (code, metavars) = gen_query(&sql_query_with_args, connection_expr);
-                            (code, metavars, sql_query_with_args.stable_macro_query)
-                        },
-                        Err(errors) => (generate_errors(errors), vec![], quote! {}),
+                    let (code, bindings) = match sql_result {
+                        Ok(sql_query_with_args) => gen_query(&sql_query_with_args, connection_expr),
+                        Err(errors) => (generate_errors(errors), vec![]),
                     };
                     let code = proc_macro2::TokenStream::from(code);
-                    let vars =
-                        match metavars.len() {
-                            0 => quote! {},
-                            1 => quote! {
-                                let #(#metavars),* = __tql_extract_exprs!($($tt)*);
-                            },
-                            _ => quote! {
-                                let (#(#metavars),*) = __tql_extract_exprs!($($tt)*);
-                            }
-                        };
 
+                    // NOTE: the arguments keep the spans of the input, so that they refer to the
+                    // variables of the caller and that the errors are shown at their position.
                     let gen = quote! {
-                        #extract_macro
-
-                        macro_rules! __tql_call_macro {
+                        macro_rules! #call_macro_name {
                             ($connection:ident, $($tt:tt)*) => {{
                                 let ref connection = $connection;
-                                #vars
+                                #(#bindings)*
                                 #code
                             }};
                         }
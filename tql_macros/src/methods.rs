@@ -21,17 +21,55 @@
 
 //! Methods definition for use in filters.
 
-use state::{SqlMethodTypes, aggregates_singleton, methods_singleton};
-use types::Type;
+use crate::state::{SqlAggregates, SqlMethodTypes, SqlMethods};
+use crate::types::Type;
+
+/// A piece of a method template.
+#[derive(Debug, PartialEq)]
+pub enum TemplatePart {
+    /// A `$n` placeholder.
+    Placeholder(usize),
+    Text(String),
+}
+
+/// Split a `template` into its text and its placeholders.
+pub fn template_parts(template: &str) -> Vec<TemplatePart> {
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(character) = chars.next() {
+        if character == '$' && chars.peek().map_or(false, |next| next.is_digit(10)) {
+            let mut number = String::new();
+            while let Some(&digit) = chars.peek() {
+                if !digit.is_digit(10) {
+                    break;
+                }
+                number.push(digit);
+                chars.next();
+            }
+            if !text.is_empty() {
+                parts.push(TemplatePart::Text(text));
+                text = String::new();
+            }
+            parts.push(TemplatePart::Placeholder(number.parse().expect("placeholder number")));
+        }
+        else {
+            text.push(character);
+        }
+    }
+    if !text.is_empty() {
+        parts.push(TemplatePart::Text(text));
+    }
+    parts
+}
 
 /// Add a new `method` on `object_type` of type `argument_types` -> `return_type`.
 /// The template is the resulting SQL with `$0` as a placeholder for `self` and `$1`, `$2`, … as
 /// placeholders for the arguments.
-pub fn add_method<'a, T: Into<Option<&'a str>>>(object_type: &Type, return_type: Type, argument_types: Vec<Type>,
-                                                method: &str, template: T)
+fn add_method<'a, T: Into<Option<&'a str>>>(methods: &mut SqlMethods, object_type: &Type, return_type: Type,
+                                            argument_types: Vec<Type>, method: &str, template: T)
 {
-    let methods = methods_singleton();
-    methods.insert(method.to_string(), SqlMethodTypes {
+    methods.entry(method.to_string()).or_insert_with(Vec::new).push(SqlMethodTypes {
         argument_types,
         object_type: object_type.clone(),
         return_type,
@@ -39,74 +77,178 @@ pub fn add_method<'a, T: Into<Option<&'a str>>>(object_type: &Type, return_type:
     });
 }
 
+/// Escape the LIKE wildcards (and the escape character \) in the SQL expression `expr`.
+/// The resulting pattern must be used with `ESCAPE '\'`.
+fn escape_like(expr: &str) -> String {
+    format!(r"REPLACE(REPLACE(REPLACE({}, '\', '\\'), '%', '\%'), '_', '\_')", expr)
+}
+
 /// Add a new aggregate `rust_function` mapping to `sql_function`.
-pub fn add_aggregate(rust_function: &str, sql_function: &str) {
-    let aggregates = aggregates_singleton();
+fn add_aggregate(aggregates: &mut SqlAggregates, rust_function: &str, sql_function: &str) {
     aggregates.insert(rust_function.to_string(), sql_function.to_string());
 }
 
-/// Add the default SQL aggregate functions.
-pub fn add_initial_aggregates() {
-    add_aggregate("avg", "AVG");
+/// Get the default SQL aggregate functions.
+pub fn initial_aggregates() -> SqlAggregates {
+    let mut aggregates = SqlAggregates::new();
+    add_aggregate(&mut aggregates, "avg", "AVG");
+    aggregates
+}
+
+/// Get the default SQL methods.
+pub fn initial_methods() -> SqlMethods {
+    let mut methods = SqlMethods::new();
+    add_initial_methods(&mut methods);
+    methods
 }
 
-/// Add the default SQL methods.
-pub fn add_initial_methods() {
+/// Add the default SQL methods to `methods`.
+fn add_initial_methods(methods: &mut SqlMethods) {
     // Date methods.
     let date_types = [Type::LocalDateTime, Type::NaiveDate, Type::NaiveDateTime, Type::UtcDateTime];
     for date_type in &date_types {
         #[cfg(feature = "postgres")]
-        add_method(date_type, Type::I32, vec![], "year", "EXTRACT(YEAR FROM $0)");
+        add_method(methods, date_type, Type::I32, vec![], "year", "EXTRACT(YEAR FROM $0)");
+        #[cfg(feature = "rusqlite")]
+        add_method(methods, date_type, Type::I32, vec![], "year", "CAST(STRFTIME('%Y', $0) AS INT)");
+
+        #[cfg(feature = "postgres")]
+        add_method(methods, date_type, Type::I32, vec![], "month", "EXTRACT(MONTH FROM $0)"); // TODO: use the U32 type.
+        // TODO: use the U32 type.
+        #[cfg(feature = "rusqlite")]
+        add_method(methods, date_type, Type::I32, vec![], "month", "CAST(STRFTIME('%m', $0) AS INT)");
+
+        #[cfg(feature = "postgres")]
+        add_method(methods, date_type, Type::I32, vec![], "day", "EXTRACT(DAY FROM $0)");
+        #[cfg(feature = "rusqlite")]
+        add_method(methods, date_type, Type::I32, vec![], "day", "CAST(STRFTIME('%d', $0) AS INT)");
+
+        // NOTE: the days of the week start at 0 for Monday.
+        #[cfg(feature = "postgres")]
+        add_method(methods, date_type, Type::I32, vec![], "weekday", "EXTRACT(ISODOW FROM $0) - 1");
         #[cfg(feature = "rusqlite")]
-        add_method(date_type, Type::I32, vec![], "year", "CAST(STRFTIME('%Y', $0) AS INT)");
+        add_method(methods, date_type, Type::I32, vec![], "weekday", "(CAST(STRFTIME('%w', $0) AS INT) + 6) % 7");
+    }
 
+    let date_time_types = [Type::LocalDateTime, Type::NaiveDateTime, Type::UtcDateTime];
+    for date_time_type in &date_time_types {
         #[cfg(feature = "postgres")]
-        add_method(date_type, Type::I32, vec![], "month", "EXTRACT(MONTH FROM $0)"); // TODO: use the U32 type.
+        add_method(methods, date_time_type, Type::NaiveDate, vec![], "date", "CAST($0 AS DATE)");
         #[cfg(feature = "rusqlite")]
-        add_method(date_type, Type::I32, vec![], "month", "CAST(STRFTIME('%m', $0) AS INT)"); // TODO: use the U32 type.
+        add_method(methods, date_time_type, Type::NaiveDate, vec![], "date", "DATE($0)");
 
         #[cfg(feature = "postgres")]
-        add_method(date_type, Type::I32, vec![], "day", "EXTRACT(DAY FROM $0)");
+        add_method(methods, date_time_type, date_time_type.clone(), vec![Type::String], "date_trunc",
+                   "DATE_TRUNC($1, $0)");
+        // NOTE: the result has the same format as the values written by rusqlite so that they can
+        // be compared as text: the date times with a time zone are stored in UTC with the `+00:00`
+        // suffix, which is kept.
         #[cfg(feature = "rusqlite")]
-        add_method(date_type, Type::I32, vec![], "day", "CAST(STRFTIME('%d', $0) AS INT)");
+        add_method(methods, date_time_type, date_time_type.clone(), vec![Type::String], "date_trunc",
+                   "STRFTIME(CASE $1 \
+                        WHEN 'year' THEN '%Y-01-01T00:00:00' \
+                        WHEN 'month' THEN '%Y-%m-01T00:00:00' \
+                        WHEN 'day' THEN '%Y-%m-%dT00:00:00' \
+                        WHEN 'hour' THEN '%Y-%m-%dT%H:00:00' \
+                        WHEN 'minute' THEN '%Y-%m-%dT%H:%M:00' \
+                        ELSE '%Y-%m-%dT%H:%M:%S' \
+                    END, $0) || CASE WHEN $0 LIKE '%+00:00' THEN '+00:00' ELSE '' END");
     }
 
     // Time methods.
     let time_types = [Type::LocalDateTime, Type::NaiveDateTime, Type::NaiveTime, Type::UtcDateTime];
     for time_type in &time_types {
         #[cfg(feature = "postgres")]
-        add_method(time_type, Type::I32, vec![], "hour", "EXTRACT(HOUR FROM $0)");
+        add_method(methods, time_type, Type::I32, vec![], "hour", "EXTRACT(HOUR FROM $0)");
         #[cfg(feature = "rusqlite")]
-        add_method(time_type, Type::I32, vec![], "hour", "CAST(STRFTIME('%H', $0) AS INT)");
+        add_method(methods, time_type, Type::I32, vec![], "hour", "CAST(STRFTIME('%H', $0) AS INT)");
 
         #[cfg(feature = "postgres")]
-        add_method(time_type, Type::I32, vec![], "minute", "EXTRACT(MINUTE FROM $0)");
+        add_method(methods, time_type, Type::I32, vec![], "minute", "EXTRACT(MINUTE FROM $0)");
         #[cfg(feature = "rusqlite")]
-        add_method(time_type, Type::I32, vec![], "minute", "CAST(STRFTIME('%M', $0) AS INT)");
+        add_method(methods, time_type, Type::I32, vec![], "minute", "CAST(STRFTIME('%M', $0) AS INT)");
 
         #[cfg(feature = "postgres")]
-        add_method(time_type, Type::I32, vec![], "second", "EXTRACT(SECOND FROM $0)");
+        add_method(methods, time_type, Type::I32, vec![], "second", "EXTRACT(SECOND FROM $0)");
         #[cfg(feature = "rusqlite")]
-        add_method(time_type, Type::I32, vec![], "second", "CAST(STRFTIME('%S', $0) AS INT)");
+        add_method(methods, time_type, Type::I32, vec![], "second", "CAST(STRFTIME('%S', $0) AS INT)");
     }
 
     // String methods.
-    add_method(&Type::String, Type::Bool, vec![Type::String], "contains", "$0 LIKE '%' || $1 || '%'");
-    add_method(&Type::String, Type::Bool, vec![Type::String], "ends_with", "$0 LIKE '%' || $1");
-    add_method(&Type::String, Type::Bool, vec![Type::String], "starts_with", "$0 LIKE $1 || '%'");
-    add_method(&Type::String, Type::I32, vec![], "len", "LENGTH($0)");
+    // NOTE: the argument of the LIKE methods is escaped so that they behave like their str
+    // counterparts.
+    let pattern = escape_like("$1");
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "contains",
+               &*format!(r"$0 LIKE '%' || {} || '%' ESCAPE '\'", pattern));
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "ends_with",
+               &*format!(r"$0 LIKE '%' || {} ESCAPE '\'", pattern));
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "starts_with",
+               &*format!(r"$0 LIKE {} || '%' ESCAPE '\'", pattern));
+    add_method(methods, &Type::String, Type::I32, vec![], "len", "LENGTH($0)");
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "eq_ignore_case", "LOWER($0) = LOWER($1)");
+    add_method(methods, &Type::String, Type::String, vec![Type::I32, Type::I32], "substring", "SUBSTR($0, $1, $2)");
+    add_method(methods, &Type::String, Type::String, vec![], "to_lowercase", "LOWER($0)");
+    add_method(methods, &Type::String, Type::String, vec![], "to_uppercase", "UPPER($0)");
+    add_method(methods, &Type::String, Type::String, vec![], "trim", "TRIM($0)");
+
+    #[cfg(feature = "postgres")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "icontains",
+               &*format!(r"$0 ILIKE '%' || {} || '%' ESCAPE '\'", pattern));
+    #[cfg(feature = "rusqlite")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "icontains",
+               &*format!(r"LOWER($0) LIKE '%' || LOWER({}) || '%' ESCAPE '\'", pattern));
+
+    #[cfg(feature = "postgres")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "iends_with",
+               &*format!(r"$0 ILIKE '%' || {} ESCAPE '\'", pattern));
+    #[cfg(feature = "rusqlite")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "iends_with",
+               &*format!(r"LOWER($0) LIKE '%' || LOWER({}) ESCAPE '\'", pattern));
+
+    #[cfg(feature = "postgres")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "istarts_with",
+               &*format!(r"$0 ILIKE {} || '%' ESCAPE '\'", pattern));
+    #[cfg(feature = "rusqlite")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "istarts_with",
+               &*format!(r"LOWER($0) LIKE LOWER({}) || '%' ESCAPE '\'", pattern));
+
+    // NOTE: on SQLite, the REGEXP function is registered by tql::sqlite::register_regexp().
+    #[cfg(feature = "postgres")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "regex", "$0 ~ $1");
+    #[cfg(feature = "rusqlite")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "regex", "$0 REGEXP $1");
+
+    #[cfg(feature = "postgres")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "iregex", "$0 ~* $1");
+    #[cfg(feature = "rusqlite")]
+    add_method(methods, &Type::String, Type::Bool, vec![Type::String], "iregex", "$0 REGEXP '(?i)' || $1");
+
+    // Array methods.
+    // NOTE: the array columns are only supported on PostgreSQL.
+    #[cfg(feature = "postgres")]
+    {
+        let array_type = Type::Array(Box::new(Type::Generic));
+        add_method(methods, &array_type, Type::Bool, vec![Type::Generic], "contains_element", "$1 = ANY($0)");
+        add_method(methods, &array_type, Type::Bool, vec![array_type.clone()], "overlaps", "$0 && $1");
+        // NOTE: CARDINALITY() returns 0 for an empty array while ARRAY_LENGTH() returns NULL.
+        add_method(methods, &array_type, Type::I32, vec![], "len", "CARDINALITY($0)");
+    }
 
+    // JSON methods.
     #[cfg(feature = "postgres")]
-    add_method(&Type::String, Type::Bool, vec![Type::String], "regex", "$0 LIKE $1");
+    add_method(methods, &Type::Json, Type::String, vec![Type::String], "get_str", "$0 ->> $1");
     #[cfg(feature = "rusqlite")]
-    add_method(&Type::String, Type::Bool, vec![Type::String], "regex", None);
+    add_method(methods, &Type::Json, Type::String, vec![Type::String], "get_str",
+               r#"JSON_EXTRACT($0, '$."' || $1 || '"')"#);
 
     #[cfg(feature = "postgres")]
-    add_method(&Type::String, Type::Bool, vec![Type::String], "iregex", "$0 ILIKE $1");
+    add_method(methods, &Type::Json, Type::Bool, vec![Type::String], "has_key", "$0 ? $1");
+    // NOTE: JSON_TYPE() returns the text 'null' for a key whose value is null and NULL for a missing key.
     #[cfg(feature = "rusqlite")]
-    add_method(&Type::String, Type::Bool, vec![Type::String], "iregex", "$0 LIKE $1");
+    add_method(methods, &Type::Json, Type::Bool, vec![Type::String], "has_key",
+               r#"JSON_TYPE($0, '$."' || $1 || '"') IS NOT NULL"#);
 
     // Option methods.
-    add_method(&Type::Nullable(Box::new(Type::Generic)), Type::Bool, vec![], "is_some", "$0 IS NOT NULL");
-    add_method(&Type::Nullable(Box::new(Type::Generic)), Type::Bool, vec![], "is_none", "$0 IS NULL");
+    add_method(methods, &Type::Nullable(Box::new(Type::Generic)), Type::Bool, vec![], "is_some", "$0 IS NOT NULL");
+    add_method(methods, &Type::Nullable(Box::new(Type::Generic)), Type::Bool, vec![], "is_none", "$0 IS NULL");
 }
@@ -30,9 +30,9 @@ use syn::{
     Lit,
 };
 
-use ast::{Expression, Limit, Query};
-use ast::Limit::{EndRange, Index, LimitOffset, Range, StartRange};
-use plugin::number_literal;
+use crate::ast::{Expression, Limit, Query};
+use crate::ast::Limit::{EndRange, Index, LimitOffset, Range, StartRange};
+use crate::plugin::number_literal;
 
 /// Check that all the expressions in `expression` are literal.
 fn all_integer_literal(expression: &Expression) -> bool {
@@ -69,6 +69,7 @@ fn evaluate(expression: &Expression) -> i64 {
 pub fn optimize(query: &mut Query) {
     match *query {
         Query::Aggregate { .. } => (), // TODO
+        Query::AlterTable { .. } => (), // Nothing to optimize.
         Query::CreateTable { .. } => (), // Nothing to optimize.
         Query::Delete { .. } => (), // TODO
         Query::Drop { .. } => (), // Nothing to optimize.
@@ -23,11 +23,12 @@ use proc_macro2::Span;
 use syn::{
     Expr,
     Ident,
+    Path,
 };
 use syn::spanned::Spanned;
 
-use ast::first_token_span;
-use error::{Error, Result, res};
+use crate::ast::first_token_span;
+use crate::error::{Error, Result, res};
 
 /// A method call.
 #[derive(Debug)]
@@ -43,6 +44,8 @@ pub struct MethodCalls {
     pub calls: Vec<MethodCall>,
     /// The identifier at the start of the calls chain.
     pub name: Option<Ident>,
+    /// The path of the table when its name is qualified (e.g. `models::Table`).
+    pub path: Option<Path>,
     pub position: Span,
 }
 
@@ -51,6 +54,7 @@ impl MethodCalls {
         Self {
             calls: vec![],
             name:  None,
+            path: None,
             // NOTE: we only want the position of the first token since this position is used in
             // errors for the table name.
             position: first_token_span(expr),
@@ -93,10 +97,10 @@ impl Parser {
                     });
                 },
                 Expr::Path(ref path) => {
-                    if path.path.segments.len() == 1 {
-                        calls.name = Some(path.path.segments.first()
-                            .expect("first segment in path").into_value()
-                            .ident.clone());
+                    calls.name = path.path.segments.last()
+                        .map(|segment| segment.into_value().ident.clone());
+                    if path.path.segments.len() > 1 {
+                        calls.path = Some(path.path.clone());
                     }
                 },
                 Expr::Index(ref index) => {
@@ -0,0 +1,151 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Compile-time verification of the tables against a snapshot of the database schema.
+//!
+//! The snapshot has the format created by `tql::migration::Schema::to_snapshot()` (and by
+//! `tql dumpschema`): a header followed by one line per table, column and index, whose parts are
+//! separated by tabulations.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use proc_macro2::Span;
+use tql_snapshot::{SnapshotColumn, normalize_type, parse_snapshot, type_and_constraint};
+
+use crate::ast::WithSpan;
+use crate::error::Error;
+use crate::sql::builtin_type_to_sql;
+use crate::types::Type;
+
+/// A column of the table struct, checked against the schema.
+pub struct ModelColumn {
+    pub name: String,
+    pub span: Span,
+    pub typ: WithSpan<Type>,
+}
+
+/// Check that the `columns` of the `table` have the same names, types and nullability as in the
+/// `schema` file.
+/// Return the path of the schema file if it could be read.
+pub fn check_table_schema(schema: &WithSpan<String>, table: &str, table_span: Span, columns: &[ModelColumn],
+                          errors: &mut Vec<Error>) -> Option<PathBuf>
+{
+    let directory = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    let path = PathBuf::from(directory).join(&schema.node);
+    let content = fs::read_to_string(&path).map_err(|error| error.to_string());
+    let tables =
+        match content.as_ref().map_err(Clone::clone).and_then(|content| parse_snapshot(content)) {
+            Ok(tables) => tables,
+            Err(error) => {
+                errors.push(Error::new(&format!("cannot read the schema `{}`: {}", schema.node, error), schema.span));
+                return None;
+            },
+        };
+    // NOTE: the names are compared case-insensitively since PostgreSQL converts the unquoted
+    // identifiers to lowercase.
+    let schema_table =
+        match tables.iter().find(|schema_table| schema_table.name.eq_ignore_ascii_case(table)) {
+            Some(schema_table) => schema_table,
+            None => {
+                errors.push(Error::new(&format!("table `{}` not found in the schema `{}`", table, schema.node),
+                    table_span));
+                return Some(path);
+            },
+        };
+    for column in columns {
+        match schema_table.columns.iter().find(|schema_column| schema_column.name.eq_ignore_ascii_case(&column.name))
+        {
+            Some(schema_column) => check_column(column, schema_column, &schema.node, errors),
+            None => errors.push(Error::new(&format!("column `{}` not found in the table `{}` of the schema `{}`",
+                column.name, schema_table.name, schema.node), column.span)),
+        }
+    }
+    for schema_column in &schema_table.columns {
+        if !columns.iter().any(|column| column.name.eq_ignore_ascii_case(&schema_column.name)) {
+            errors.push(Error::new(&format!("missing field for the column `{}` of the table `{}` in the schema `{}`",
+                schema_column.name, schema_table.name, schema.node), table_span));
+        }
+    }
+    Some(path)
+}
+
+/// Check that the type and the nullability of the `column` are the same as in the schema.
+fn check_column(column: &ModelColumn, schema_column: &SnapshotColumn, schema: &str, errors: &mut Vec<Error>) {
+    let (typ, nullable) =
+        match column.typ.node {
+            Type::Nullable(ref typ) => (&**typ, true),
+            ref typ => (typ, false),
+        };
+    let (schema_type, constraint) = type_and_constraint(schema_column.sql_type);
+    let expected_constraint =
+        match *typ {
+            Type::Custom(ref related_table) => {
+                let constraint = constraint.to_uppercase();
+                let references_table = constraint.starts_with("REFERENCES") &&
+                    constraint["REFERENCES".len()..].split('(').next().unwrap_or("").trim()
+                        .eq_ignore_ascii_case(related_table);
+                Some((format!("REFERENCES {}", related_table), references_table))
+            },
+            Type::Serial => Some(("PRIMARY KEY".to_string(), constraint.to_uppercase().starts_with("PRIMARY KEY"))),
+            _ => None,
+        };
+    if let Some((expected_constraint, false)) = expected_constraint {
+        errors.push(Error::new_with_code(&format!(
+            "mismatched types: expected `{}` for the column `{}`, found `{}` in the schema `{}`",
+            expected_constraint, column.name, schema_column.sql_type, schema), column.typ.span, "E0308"));
+        return;
+    }
+    let expected_type =
+        match *typ {
+            Type::Array(ref element_type) => builtin_type_to_sql(element_type).map(|typ| format!("{}[]", typ)),
+            Type::Custom(_) => Some("INTEGER".to_string()),
+            // NOTE: the SQL type of a user-defined type is only known at runtime.
+            ref typ => builtin_type_to_sql(typ).map(|typ| type_and_constraint(typ).0.to_string()),
+        };
+    if let Some(expected_type) = expected_type {
+        if normalize_type(&expected_type) != normalize_type(schema_type) {
+            errors.push(Error::new_with_code(&format!(
+                "mismatched types: expected `{}` for the column `{}`, found `{}` in the schema `{}`",
+                expected_type, column.name, schema_type, schema), column.typ.span, "E0308"));
+            return;
+        }
+    }
+    // NOTE: the nullability of the primary key is not checked since SQLite allows NULL in an
+    // INTEGER PRIMARY KEY column (it is then given a value).
+    if *typ != Type::Serial && nullable != schema_column.nullable {
+        let message =
+            if schema_column.nullable {
+                format!("mismatched types: the column `{}` is nullable in the schema `{}`, expected `Option<…>`",
+                    column.name, schema)
+            }
+            else {
+                format!(
+                    "mismatched types: the column `{}` is NOT NULL in the schema `{}`, expected a non-optional type",
+                    column.name, schema)
+            };
+        errors.push(Error::new_with_code(&message, column.typ.span, "E0308"));
+    }
+}
+
+
+
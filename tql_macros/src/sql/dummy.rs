@@ -23,8 +23,8 @@
 
 use proc_macro2::TokenStream ;
 
-use ast::Aggregate;
-use sql::{SqlBackend, ToSql};
+use crate::ast::{Aggregate, DateExpression, TypedField};
+use crate::sql::{SqlBackend, ToSql};
 
 pub struct DummySqlBackend {}
 
@@ -32,13 +32,33 @@ pub fn create_sql_backend() -> DummySqlBackend {
     DummySqlBackend { }
 }
 
-impl ToSql for Aggregate {
+impl Aggregate {
+    pub fn to_tokens(&self) -> TokenStream {
+        unreachable!("Enable one of the following features: sqlite, pg");
+    }
+}
+
+impl ToSql for DateExpression {
     fn to_sql(&self, _index: &mut usize) -> String {
         unreachable!("Enable one of the following features: sqlite, pg");
     }
 }
 
 impl SqlBackend for DummySqlBackend {
+    fn add_columns_query(&self, _table: &str, _added_columns: &[TypedField], _nullable: bool,
+        _columns: &[TypedField], _indexed_columns: &[String]) -> TokenStream
+    {
+        unreachable!("Enable one of the following features: sqlite, pg");
+    }
+
+    fn drop_cascade_query(&self, _object: &str, _name: &str) -> String {
+        unreachable!("Enable one of the following features: sqlite, pg");
+    }
+
+    fn drop_column_query(&self, _table: &str, _columns: &[TypedField], _indexed_columns: &[String]) -> TokenStream {
+        unreachable!("Enable one of the following features: sqlite, pg");
+    }
+
     fn insert_query(&self, _table: &str, _fields: &[String], _values: &[String]) -> TokenStream {
         unreachable!("Enable one of the following features: sqlite, pg");
     }
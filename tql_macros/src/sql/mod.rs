@@ -32,15 +32,16 @@ use std::iter;
 use std::str::from_utf8;
 
 use proc_macro2::{Span,TokenStream};
-use syn::{Expr, Ident, Lit};
+use syn::{self, Expr, Ident, Lit, Path};
 
-use ast::{
+use crate::ast::{
     Aggregate,
     AggregateFilter,
     AggregateFilters,
     AggregateFilterExpression,
     Assignment,
     AssignmentOperator,
+    DateExpression,
     Expression,
     Filter,
     FilterExpression,
@@ -53,9 +54,13 @@ use ast::{
     Order,
     Query,
     RelationalOperator,
+    SubQueryFilter,
+    SubQueryOperator,
+    TableAlteration,
     TypedField,
+    find_assignment,
 };
-use ast::Limit::{
+use crate::ast::Limit::{
     EndRange,
     Index,
     LimitOffset,
@@ -63,9 +68,18 @@ use ast::Limit::{
     Range,
     StartRange,
 };
-use plugin::string_literal;
-use state::methods_singleton;
-use types::Type;
+use crate::function::FunctionKind;
+use crate::methods::{TemplatePart, template_parts};
+use crate::plugin::string_literal;
+use crate::state::{
+    SqlMethodTypes,
+    auto_now_fields,
+    embedded_fields,
+    methods_singleton,
+    soft_delete_time,
+    version_field,
+};
+use crate::types::Type;
 
 #[cfg(not(any(feature = "rusqlite", feature = "postgres")))]
 use self::dummy::create_sql_backend;
@@ -75,6 +89,18 @@ use self::postgres::create_sql_backend;
 use self::sqlite::create_sql_backend;
 
 trait SqlBackend {
+    /// Convert to the arguments of `concat!()` the query adding the `added_columns` to the `table`
+    /// whose columns are `columns` (including the `indexed_columns`). The added columns are
+    /// `nullable` when they can be added to the existing rows without value (i.e. they accept NULL
+    /// and have no default value).
+    fn add_columns_query(&self, table: &str, added_columns: &[TypedField], nullable: bool, columns: &[TypedField],
+        indexed_columns: &[String]) -> TokenStream;
+    /// Get the query dropping the `object` (e.g. `TABLE`) called `name` as well as the objects
+    /// depending on it.
+    fn drop_cascade_query(&self, object: &str, name: &str) -> String;
+    /// Convert to the arguments of `concat!()` the query dropping the `$column` of the `table` whose
+    /// remaining columns are `columns` (including the `indexed_columns`).
+    fn drop_column_query(&self, table: &str, columns: &[TypedField], indexed_columns: &[String]) -> TokenStream;
     fn insert_query(&self, table: &str, fields: &[String], values: &[String]) -> TokenStream;
 }
 
@@ -83,43 +109,71 @@ pub trait ToSql {
     fn to_sql(&self, index: &mut usize) -> String;
 }
 
-impl ToSql for [Aggregate] {
-    fn to_sql(&self, index: &mut usize) -> String {
-        self.iter().map(|aggregate| aggregate.to_sql(index)).collect::<Vec<_>>().join(", ")
+impl Aggregate {
+    /// Convert the aggregate function call (without the backend-specific cast) to the arguments
+    /// of `concat!()`.
+    fn call_to_tokens(&self) -> TokenStream {
+        let field = self.field.clone().expect("Aggregate field").to_sql(&mut 1);
+        match self.sql_function {
+            Some(ref sql_function) => string_token(&format!("{}({})", sql_function, field)),
+            None => {
+                // NOTE: the SQL code of a user-defined aggregate is generated by the macro created by
+                // the #[sql_aggregate] attribute.
+                let macro_name = Ident::new(&FunctionKind::Aggregate.macro_name(&self.function), self.span);
+                quote_spanned! { self.span =>
+                    #macro_name!(sql, #field)
+                }
+            },
+        }
     }
 }
 
-impl ToSql for AggregateFilter {
-    fn to_sql(&self, index: &mut usize) -> String {
-        self.operand1.to_sql(index) + " " +
-            &self.operator.to_sql(index) + " " +
-            &self.operand2.to_sql(index)
+fn aggregates_to_tokens(aggregates: &[Aggregate]) -> TokenStream {
+    sep_by(aggregates.iter().map(|aggregate| aggregate.to_tokens()), ", ")
+}
+
+impl AggregateFilter {
+    fn to_tokens(&self, index: &mut usize) -> TokenStream {
+        let operand1 = self.operand1.to_tokens();
+        let operator = self.operator.to_sql(index);
+        let operand2 = self.operand2.to_sql(index);
+        quote! {
+            #operand1, " ", #operator, " ", #operand2
+        }
     }
 }
 
-impl ToSql for AggregateFilterExpression {
-    fn to_sql(&self, index: &mut usize) -> String {
+impl AggregateFilterExpression {
+    fn to_tokens(&self, index: &mut usize) -> TokenStream {
         match *self {
-            AggregateFilterExpression::Filter(ref filter) => filter.to_sql(index),
-            AggregateFilterExpression::Filters(ref filters) => filters.to_sql(index),
-            AggregateFilterExpression::NegFilter(ref filter) =>
-                "NOT ".to_string() +
-                &filter.to_sql(index),
-            AggregateFilterExpression::NoFilters => "".to_string(),
-            AggregateFilterExpression::ParenFilter(ref filter) =>
-                "(".to_string() +
-                &filter.to_sql(index) +
-                ")",
-            AggregateFilterExpression::FilterValue(ref filter_value) => filter_value.node.to_sql(index),
+            AggregateFilterExpression::Filter(ref filter) => filter.to_tokens(index),
+            AggregateFilterExpression::Filters(ref filters) => filters.to_tokens(index),
+            AggregateFilterExpression::NegFilter(ref filter) => {
+                let filter = filter.to_tokens(index);
+                quote! {
+                    "NOT ", #filter
+                }
+            },
+            AggregateFilterExpression::NoFilters => quote! { "" },
+            AggregateFilterExpression::ParenFilter(ref filter) => {
+                let filter = filter.to_tokens(index);
+                quote! {
+                    "(", #filter, ")"
+                }
+            },
+            AggregateFilterExpression::FilterValue(ref filter_value) => filter_value.node.to_tokens(),
         }
     }
 }
 
-impl ToSql for AggregateFilters {
-    fn to_sql(&self, index: &mut usize) -> String {
-        self.operand1.to_sql(index) + " " +
-            &self.operator.to_sql(index) + " " +
-            &self.operand2.to_sql(index)
+impl AggregateFilters {
+    fn to_tokens(&self, index: &mut usize) -> TokenStream {
+        let operand1 = self.operand1.to_tokens(index);
+        let operator = self.operator.to_sql(index);
+        let operand2 = self.operand2.to_tokens(index);
+        quote! {
+            #operand1, " ", #operator, " ", #operand2
+        }
     }
 }
 
@@ -137,10 +191,36 @@ impl ToSql for Assignment {
     }
 }
 
-impl ToSql for [Assignment] {
-    fn to_sql(&self, index: &mut usize) -> String {
-        self.iter().map(|assignment| assignment.to_sql(index)).collect::<Vec<_>>().join(", ")
+/// Convert the assignments of an update `Query` to SQL.
+/// An embedded field is assigned to each column of its struct.
+fn assignments_to_sql(assignments: &[Assignment], table: &str, index: &mut usize) -> String {
+    let mut sql = vec![];
+    let version_field = version_field(table);
+    for assignment in assignments {
+        let identifier = assignment.identifier.clone().expect("Assignment identifier").to_string();
+        if Some(&identifier) == version_field.as_ref() {
+            // NOTE: the assigned value is the current version, which is compared in the WHERE clause.
+            sql.push(format!("{} = {} + 1", identifier, identifier));
+            continue;
+        }
+        match embedded_fields(table, &identifier) {
+            Some(fields) =>
+                for field in fields {
+                    sql.push(format!("{}_{}{}{}", identifier, field.name, assignment.operator.node.to_sql(index),
+                        assignment.value.to_sql(index)));
+                },
+            None => sql.push(assignment.to_sql(index)),
+        }
+    }
+    // The auto_now fields are updated, unless they are assigned explicitly.
+    for (field, current_time) in auto_now_fields(table) {
+        let assigned = assignments.iter()
+            .any(|assignment| assignment.identifier.as_ref().map(|ident| *ident == field).unwrap_or(false));
+        if !assigned {
+            sql.push(format!("{} = {}", field, current_time));
+        }
     }
+    sql.join(", ")
 }
 
 impl ToSql for AssignmentOperator {
@@ -174,7 +254,8 @@ impl FilterExpression {
                     "(", #filter, ")"
                 }
             }
-            FilterExpression::FilterValue(ref filter_value) => filter_value.node.to_tokens(),
+            FilterExpression::SubQuery(ref filter) => filter.to_tokens(index),
+            FilterExpression::FilterValue(ref filter_value) => filter_value.node.to_tokens(index),
         }
     }
 }
@@ -220,6 +301,8 @@ impl Join {
         let related_table_macro_name =
             Ident::new(&format!("tql_{}_related_tables", self.base_table), Span::call_site());
         let related_pks_macro_name = Ident::new(&format!("tql_{}_related_pks", self.base_table), self.base_field.span());
+        let related_deleted_filters_macro_name =
+            Ident::new(&format!("tql_{}_related_deleted_filters", self.base_table), Span::call_site());
         let base_table = &self.base_table;
         let base_field = self.base_field.to_sql(&mut 1);
         let base_field_ident = &self.base_field;
@@ -228,11 +311,116 @@ impl Join {
         };
         quote! {
             " INNER JOIN ", #related_table_name, " ON ", #base_table, ".", #base_field, " = ",
-                    #related_table_name, ".", #related_pks_macro_name!(#base_field_ident)
+                    #related_table_name, ".", #related_pks_macro_name!(#base_field_ident),
+                    #related_deleted_filters_macro_name!(#base_field_ident)
         }
     }
 }
 
+/// Convert to the arguments of `concat!()` the query adding the `added_columns` of a field to the
+/// `table` whose columns are `columns`.
+pub fn add_columns_query(table: &str, added_columns: &[TypedField], nullable: bool, columns: &[TypedField],
+    indexed_columns: &[String]) -> TokenStream
+{
+    let backend = create_sql_backend();
+    backend.add_columns_query(table, added_columns, nullable, columns, indexed_columns)
+}
+
+/// Convert to the arguments of `concat!()` the `ALTER TABLE` statements adding the `columns` to
+/// the `table`, followed by the creation of their index if they are part of the `indexed_columns`.
+fn alter_add_columns(table: &str, columns: &[TypedField], indexed_columns: &[String]) -> TokenStream {
+    let alter_table = format!("ALTER TABLE {} ADD COLUMN ", table);
+    let statements = columns.iter()
+        .map(|column| {
+            let ident = &column.identifier;
+            let typ = &column.typ;
+            quote! {
+                #alter_table, #ident, " ", #typ
+            }
+        });
+    let statements = sep_by(statements, "; ");
+    let added_indexed_columns: Vec<_> = indexed_columns.iter()
+        .filter(|indexed_column| columns.iter().any(|column| &column.identifier == *indexed_column))
+        .cloned()
+        .collect();
+    let create_indexes = create_indexes_query(table, &added_indexed_columns, false);
+    quote! {
+        #statements, #create_indexes
+    }
+}
+
+/// Convert a `TableAlteration` of the `table` to SQL.
+fn alteration_to_tokens(alteration: &TableAlteration, table: &str) -> TokenStream {
+    let query =
+        match *alteration {
+            TableAlteration::AddColumn(ref field) => {
+                // NOTE: the column definitions are only known by the macro generated by
+                // #[derive(SqlTable)].
+                let macro_name = Ident::new(&format!("tql_{}_add_column_query", table), Span::call_site());
+                return quote_spanned! { Span::call_site() =>
+                    #macro_name!(#field)
+                };
+            },
+            TableAlteration::DropColumn(ref column) => {
+                let macro_name = Ident::new(&format!("tql_{}_drop_column_query", table), Span::call_site());
+                return quote_spanned! { Span::call_site() =>
+                    #macro_name!(#column)
+                };
+            },
+            TableAlteration::RenameColumn { ref new, ref old } => {
+                // NOTE: an embedded field is stored in the columns of its struct, so each of them
+                // is renamed.
+                let columns =
+                    match embedded_fields(table, &new.to_string()) {
+                        Some(embed_fields) => embed_fields.iter()
+                            .map(|field| (format!("{}_{}", old, field.name), format!("{}_{}", new, field.name)))
+                            .collect(),
+                        None => vec![(old.to_string(), new.to_string())],
+                    };
+                columns.iter()
+                    .map(|&(ref old, ref new)| format!("ALTER TABLE {} RENAME COLUMN {} TO {}", table, old, new))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            },
+            TableAlteration::RenameTo(ref new_table) => format!("ALTER TABLE {} RENAME TO {}", table, new_table),
+        };
+    quote! {
+        concat!(#query)
+    }
+}
+
+/// Get the statements creating the indexes on the `indexed_columns` of the `table`, each of them
+/// preceded by a separator.
+pub fn create_indexes_query(table: &str, indexed_columns: &[String], if_not_exists: bool) -> String {
+    let if_not_exists =
+        if if_not_exists {
+            "IF NOT EXISTS "
+        }
+        else {
+            ""
+        };
+    indexed_columns.iter()
+        .map(|column| format!("; CREATE INDEX {if_not_exists}{table}_{column}_idx ON {table} ({column})",
+                              column = column,
+                              if_not_exists = if_not_exists,
+                              table = table,
+                             ))
+        .collect()
+}
+
+/// Get the query dropping the `object` (e.g. `TYPE`) called `name` and the objects depending on it.
+pub fn drop_cascade_query(object: &str, name: &str) -> String {
+    let backend = create_sql_backend();
+    backend.drop_cascade_query(object, name)
+}
+
+/// Convert to the arguments of `concat!()` the query dropping a column of the `table` whose
+/// remaining columns are `columns`.
+pub fn drop_column_query(table: &str, columns: &[TypedField], indexed_columns: &[String]) -> TokenStream {
+    let backend = create_sql_backend();
+    backend.drop_column_query(table, columns, indexed_columns)
+}
+
 fn sep_by<I: Iterator<Item=TokenStream>>(elements: I, sep: &str) -> TokenStream {
     let mut elements: Vec<_> = elements.collect();
     if let Some(last_element) = elements.pop() {
@@ -284,9 +472,112 @@ impl ToSql for String {
 /// Convert a whole `Query` to SQL.
 impl Query {
     pub fn to_tokens(&self) -> TokenStream {
+        let index = &mut 1;
+        match *self {
+            Query::Aggregate { ref joins, .. } | Query::Select { ref joins, .. } => {
+                let check_joins = joins_to_check(&joins);
+                let query = self.select_to_tokens(index);
+                quote_spanned! { Span::call_site() => {
+                    #check_joins
+                    concat!(#query)
+                }}
+            },
+            Query::AlterTable { ref alteration, ref table } => alteration_to_tokens(alteration, table),
+            Query::CreateTable { if_not_exists, ref table } => {
+                let macro_name = Ident::new(&format!("tql_{}_create_query", table), Span::call_site());
+                let mode =
+                    if if_not_exists {
+                        quote! { if_not_exists }
+                    }
+                    else {
+                        quote! {}
+                    };
+                quote_spanned! { Span::call_site() =>
+                    #macro_name!(#mode)
+                }
+            },
+            Query::Delete { ref filter, soft_delete, ref table, use_pk: _use_pk } => {
+                let filter = filter_to_tokens(filter, soft_delete, table, index);
+                if soft_delete {
+                    // NOTE: the rows are marked as deleted instead of being deleted.
+                    let current_time = soft_delete_time(table).unwrap_or_default();
+                    quote! {
+                        concat!("UPDATE ", #table, " SET deleted_at = ", #current_time, #filter)
+                    }
+                }
+                else {
+                    quote! {
+                        concat!("DELETE FROM ", #table, #filter)
+                    }
+                }
+            },
+            Query::Drop { cascade, if_exists, ref table } => {
+                let macro_name = Ident::new(&format!("tql_{}_drop_query", table), Span::call_site());
+                let mode =
+                    if cascade {
+                        quote! { cascade }
+                    }
+                    else if if_exists {
+                        quote! { if_exists }
+                    }
+                    else {
+                        quote! {}
+                    };
+                quote_spanned! { Span::call_site() =>
+                    #macro_name!(#mode)
+                }
+            },
+            Query::Insert { ref assignments, ref table } => {
+                let mut fields = vec![];
+                let mut values = vec![];
+                for assign in assignments {
+                    let identifier = assign.identifier.clone().expect("Assignment identifier").to_sql(&mut 1);
+                    // NOTE: an embedded field is inserted in the columns of its struct.
+                    match embedded_fields(table, &identifier) {
+                        Some(embed_fields) =>
+                            for field in embed_fields {
+                                fields.push(format!("{}_{}", identifier, field.name));
+                                values.push(assign.value.to_sql(index));
+                            },
+                        None => {
+                            fields.push(identifier);
+                            values.push(assign.value.to_sql(index));
+                        },
+                    }
+                }
+                // Add the SQL code to get the inserted primary key.
+                // TODO: what to do when there is no primary key?
+                let backend = create_sql_backend();
+                backend.insert_query(table, &fields, &values)
+            },
+            Query::Update { ref assignments, ref filter, soft_delete, ref table, use_pk: _use_pk } => {
+                let sql_assignments = assignments_to_sql(assignments, table, index);
+                let filter_tokens = filter.to_tokens(index);
+                // NOTE: the row is only updated if it still has the version assigned in update().
+                let mut conditions = vec![];
+                if let Some(version_field) = version_field(table) {
+                    if let Some(assignment) = find_assignment(assignments, &version_field) {
+                        conditions.push(format!("{}.{} = {}", table, version_field, assignment.value.to_sql(index)));
+                    }
+                }
+                if soft_delete {
+                    conditions.push(format!("{}.deleted_at IS NULL", table));
+                }
+                let filter = where_clause_to_tokens(filter, filter_tokens, &conditions);
+                quote! {
+                    concat!("UPDATE ", #table, " SET ", #sql_assignments, #filter)
+                }
+            },
+        }
+    }
+
+    /// Convert an aggregate or a select `Query` to the arguments of `concat!()`.
+    /// The `index` is shared with the parent query when this query is a subquery.
+    fn select_to_tokens(&self, index: &mut usize) -> TokenStream {
         match *self {
-            Query::Aggregate { ref aggregates, ref aggregate_filter, ref filter, ref groups, ref joins, ref table } => {
-                let where_clause = filter_to_where_clause(filter);
+            Query::Aggregate { ref aggregates, ref aggregate_filter, ref filter, ref groups, ref joins, soft_delete,
+                ref table } =>
+            {
                 let group_clause =
                     if !groups.is_empty() {
                         " GROUP BY "
@@ -301,49 +592,19 @@ impl Query {
                     else {
                         " HAVING "
                     };
-                let aggregates = aggregates.to_sql(&mut 1);
-                let check_joins = joins_to_check(&joins);
+                let aggregates = aggregates_to_tokens(aggregates);
                 let joins = joins_to_tokens(&joins);
-                let index = &mut 1;
-                let filter = filter.to_tokens(index);
+                let filter = filter_to_tokens(filter, soft_delete, table, index);
                 let groups = groups.to_sql(&mut 1);
-                let aggregate_filter = aggregate_filter.to_sql(index);
-                quote! {{
-                    #check_joins
-                    concat!("SELECT ", #aggregates, " FROM ", #table, #joins, #where_clause, #filter, #group_clause,
-                            #groups, #having_clause, #aggregate_filter)
-                }}
-            },
-            Query::CreateTable { ref table } => {
-                let macro_name = Ident::new(&format!("tql_{}_create_query", table), Span::call_site());
-                quote_spanned! { Span::call_site() =>
-                    #macro_name!()
-                }
-            },
-            Query::Delete { ref filter, ref table, use_pk: _use_pk } => {
-                let where_clause = filter_to_where_clause(filter);
-                let filter = filter.to_tokens(&mut 1);
+                let aggregate_filter = aggregate_filter.to_tokens(index);
                 quote! {
-                    concat!("DELETE FROM ", #table, #where_clause, #filter)
+                    "SELECT ", #aggregates, " FROM ", #table, #joins, #filter, #group_clause,
+                        #groups, #having_clause, #aggregate_filter
                 }
             },
-            Query::Drop { ref table } => {
-                string_token(format!("DROP TABLE {table}", table = table).as_str())
-            },
-            Query::Insert { ref assignments, ref table } => {
-                let fields: Vec<_> = assignments.iter().map(|assign|
-                    assign.identifier.clone().expect("Assignment identifier").to_sql(&mut 1)).collect();
-                let index = &mut 1;
-                let values: Vec<_> = assignments.iter().map(|assign|
-                    assign.value.to_sql(index)
-                ).collect();
-                // Add the SQL code to get the inserted primary key.
-                // TODO: what to do when there is no primary key?
-                let backend = create_sql_backend();
-                backend.insert_query(table, &fields, &values)
-            },
-            Query::Select { ref filter, get: _get, ref joins, ref limit, ref order, ref table, use_pk: _use_pk } => {
-                let where_clause = filter_to_where_clause(filter);
+            Query::Select { ref filter, get: _get, ref joins, ref limit, ref only, ref order, soft_delete, ref table,
+                use_pk: _use_pk } =>
+            {
                 let order_clause =
                     if has_order_clauses(order) {
                         " ORDER BY "
@@ -351,36 +612,36 @@ impl Query {
                     else {
                         ""
                     };
-                let macro_name = Ident::new(format!("tql_{}_field_list", table).as_str(), Span::call_site());
-                let joined_fields = joined_fields(&joins, table);
-                let check_joins = joins_to_check(&joins);
+                let fields =
+                    if let Some(ref only) = *only {
+                        let field = only.to_sql(&mut 1);
+                        quote! {
+                            #table, ".", #field
+                        }
+                    }
+                    else {
+                        let macro_name = Ident::new(format!("tql_{}_field_list", table).as_str(), Span::call_site());
+                        let joined_fields = joined_fields(&joins, table);
+                        quote_spanned! { Span::call_site() =>
+                            #macro_name!() #joined_fields
+                        }
+                    };
                 let joins = joins_to_tokens(&joins);
-                let index = &mut 1;
-                let filter = filter.to_tokens(index);
+                let filter = filter_to_tokens(filter, soft_delete, table, index);
                 let order = order.to_sql(&mut 1);
-                let limit = limit.to_sql(&mut 1);
-                quote_spanned! { Span::call_site() => {
-                    #check_joins
-                    concat!("SELECT ", #macro_name!() #joined_fields, " FROM ", #table, #joins, #where_clause, #filter,
-                        #order_clause, #order, #limit)
-                }}
-            },
-            Query::Update { ref assignments, ref filter, ref table, use_pk: _use_pk } => {
-                let where_clause = filter_to_where_clause(filter);
-                let index = &mut 1;
-                let assignments = assignments.to_sql(index);
-                let filter = filter.to_tokens(index);
+                let limit = limit.to_sql(index);
                 quote! {
-                    concat!("UPDATE ", #table, " SET ", #assignments, #where_clause, #filter)
+                    "SELECT ", #fields, " FROM ", #table, #joins, #filter, #order_clause, #order, #limit
                 }
             },
+            _ => unreachable!("select_to_tokens() called on a query which is not an aggregate or a select"),
         }
     }
 }
 
 impl Filter {
     fn to_tokens(&self, index: &mut usize) -> TokenStream {
-        let operand1 = self.operand1.to_tokens();
+        let operand1 = self.operand1.to_tokens(index);
         let operator = self.operator.to_sql(index);
         let operand2 = self.operand2.to_sql(index);
         quote! {
@@ -389,6 +650,27 @@ impl Filter {
     }
 }
 
+impl SubQueryFilter {
+    fn to_tokens(&self, index: &mut usize) -> TokenStream {
+        let operand1 = self.operand1.as_ref().map(|operand1| operand1.to_tokens(index));
+        let query = self.query.query.select_to_tokens(index);
+        match self.operator {
+            SubQueryOperator::Exists => quote! {
+                "EXISTS (", #query, ")"
+            },
+            SubQueryOperator::In => quote! {
+                #operand1, " IN (", #query, ")"
+            },
+            SubQueryOperator::Relational(operator) => {
+                let operator = operator.to_sql(index);
+                quote! {
+                    #operand1, " ", #operator, " (", #query, ")"
+                }
+            },
+        }
+    }
+}
+
 impl Filters {
     fn to_tokens(&self, index: &mut usize) -> TokenStream {
         let operand1 = self.operand1.to_tokens(index);
@@ -401,31 +683,13 @@ impl Filters {
 }
 
 impl FilterValue {
-    fn to_tokens(&self) -> TokenStream {
+    fn to_tokens(&self, index: &mut usize) -> TokenStream {
         let sql =
             match *self {
                 FilterValue::Identifier(ref table, ref identifier) => format!("{}.{}", table, identifier.to_sql(&mut 1)),
-                FilterValue::MethodCall(MethodCall { ref arguments, ref object_name, ref method_name, ..  }) => {
-                    let methods = methods_singleton();
-                    if let Some(method) = methods.get(&method_name.to_string()) {
-                        // In the template, $0 represents the object identifier and $1, $2, ... the
-                        // arguments.
-                        let mut sql = method.template.as_ref().map(|string| string.as_str()).unwrap_or_default()
-                            // NOTE: it's safe to use unwrap_or_default() because we check if the method exists for the
-                            // backend in the method analyzer.
-                            .replace("$0", &object_name.to_string());
-                        let mut index = 1;
-                        for argument in arguments {
-                            sql = sql.replace(&format!("${}", index), &argument.to_sql(&mut 1));
-                            index += 1;
-                        }
-                        sql
-                    }
-                    else {
-                        // NOTE: type checking will disallow this code to be executed.
-                        String::new()
-                    }
-                },
+                FilterValue::EmbeddedField(ref table, ref field, ref subfield) =>
+                    format!("{}.{}_{}", table, field, subfield),
+                FilterValue::MethodCall(ref call) => return call.to_tokens(index),
                 FilterValue::None => unreachable!("FilterValue::None in FilterValue::to_sql()"),
                 FilterValue::PrimaryKey(ref table) => {
                     let macro_name = Ident::new(&format!("tql_{}_primary_key_field", table), Span::call_site());
@@ -472,6 +736,9 @@ impl ToSql for Expression {
                 }
             },
             _ => {
+                if let Some(date) = DateExpression::from_expression(self) {
+                    return date.to_sql(index);
+                }
                 let sql = format!("${}", index);
                 *index += 1;
                 sql
@@ -480,6 +747,123 @@ impl ToSql for Expression {
     }
 }
 
+/// Replace the placeholders of a method template in a single pass:
+/// `$0` is replaced by the object identifier and `$1`, `$2`, … by the `arguments`.
+impl MethodCall {
+    fn to_tokens(&self, index: &mut usize) -> TokenStream {
+        // NOTE: the object is converted first since its arguments come before the arguments of this
+        // method call.
+        let object =
+            match self.object_call {
+                Some(ref call) => call.to_tokens(index),
+                None => {
+                    let field = string_literal(&self.object_name.to_string());
+                    quote! {
+                        #field
+                    }
+                },
+            };
+        let arguments: Vec<_> = self.arguments.iter()
+            .map(|argument| argument.to_sql(index))
+            .collect();
+        let methods = methods_singleton();
+        if let Some(overloads) = methods.get(&self.method_name.to_string()) {
+            // NOTE: it's safe to use unwrap_or_default() because we check if the method exists for the
+            // backend in the method analyzer.
+            fn template(method: &SqlMethodTypes) -> &str {
+                method.template.as_ref().map(|string| string.as_str()).unwrap_or_default()
+            }
+            let array_method = overloads.iter().find(|method| method.object_type.is_array());
+            let other_method = overloads.iter().find(|method| !method.object_type.is_array());
+            match (array_method, other_method) {
+                (Some(array_method), Some(other_method)) if array_method.template != other_method.template => {
+                    let array_sql = render_template(template(array_method), &object, &arguments);
+                    let other_sql = render_template(template(other_method), &object, &arguments);
+                    match self.object_call {
+                        // NOTE: the type of the object is the return type of the previous method.
+                        Some(ref call) => {
+                            let returns_array = methods.get(&call.method_name.to_string())
+                                .and_then(|overloads| overloads.first())
+                                .map_or(false, |method| method.return_type.is_array());
+                            if returns_array {
+                                array_sql
+                            }
+                            else {
+                                other_sql
+                            }
+                        },
+                        // NOTE: the type of a field is only known by the macros generated for its table.
+                        None => {
+                            let macro_name = Ident::new(&format!("tql_{}_array_field", self.table),
+                                Span::call_site());
+                            let field = &self.object_name;
+                            quote! {
+                                #macro_name!(#field, concat!(#array_sql), concat!(#other_sql))
+                            }
+                        },
+                    }
+                },
+                _ => render_template(template(&overloads[0]), &object, &arguments),
+            }
+        }
+        else if FunctionKind::Method.is_declared(&self.method_name.to_string()) {
+            // NOTE: the SQL code of a user-defined method is generated by the macro created by the
+            // #[sql_function] attribute.
+            let method_name = &self.method_name;
+            let macro_name = Ident::new(&FunctionKind::Method.macro_name(&method_name.to_string()),
+                method_name.span());
+            // NOTE: the macro expects one expression per argument.
+            let object =
+                if self.object_call.is_some() {
+                    quote! {
+                        concat!(#object)
+                    }
+                }
+                else {
+                    object
+                };
+            quote_spanned! { method_name.span() =>
+                #macro_name!(sql, #object #(, #arguments)*)
+            }
+        }
+        else {
+            // NOTE: type checking will disallow this code to be executed.
+            quote! {
+                ""
+            }
+        }
+    }
+}
+
+fn render_template(template: &str, object: &TokenStream, arguments: &[String]) -> TokenStream {
+    let parts = template_parts(template).into_iter()
+        .map(|part| {
+            match part {
+                TemplatePart::Placeholder(0) => object.clone(),
+                TemplatePart::Placeholder(placeholder) => {
+                    let sql =
+                        match arguments.get(placeholder - 1) {
+                            Some(argument) => argument.clone(),
+                            None => format!("${}", placeholder),
+                        };
+                    let expr = string_literal(&sql);
+                    quote! {
+                        #expr
+                    }
+                },
+                TemplatePart::Text(text) => {
+                    let expr = string_literal(&text);
+                    quote! {
+                        #expr
+                    }
+                },
+            }
+        });
+    quote! {
+        #(#parts),*
+    }
+}
+
 // TODO: check if special characters (\n, \t, …) should be escaped.
 
 /// Escape the character '.
@@ -490,9 +874,20 @@ fn escape(string: String) -> String {
 pub fn type_to_sql(typ: &Type, nullable: bool) -> TokenStream {
     let sql_type =
         match *typ {
-            Type::Bool => "BOOLEAN",
-            Type::ByteString => "BYTEA",
-            Type::I8 | Type::Char => "CHARACTER(1)",
+            Type::Array(ref element_type) => {
+                // NOTE: the element type of an array cannot be nullable.
+                let element_type = type_to_sql(element_type, true);
+                let not_null =
+                    if nullable {
+                        quote! {}
+                    }
+                    else {
+                        quote! { , " NOT NULL" }
+                    };
+                return quote! {
+                    #element_type, "[]" #not_null
+                };
+            },
             Type::Custom(ref related_table_name) => {
                 let pk_macro_name = Ident::new(&format!("tql_{}_primary_key_field", related_table_name),
                     Span::call_site());
@@ -501,31 +896,31 @@ pub fn type_to_sql(typ: &Type, nullable: bool) -> TokenStream {
                 };
                 // NOTE: if the field type is not an SQL table, an error is thrown.
             },
-            Type::F32 => "REAL",
-            Type::F64 => "DOUBLE PRECISION",
-            Type::Generic => "", // TODO: document why this is empty.
-            Type::I16 => "SMALLINT",
-            Type::I32 => "INTEGER",
-            Type::I64 => "BIGINT",
-            Type::LocalDateTime => "TIMESTAMP WITH TIME ZONE",
-            Type::NaiveDate => "DATE",
-            Type::NaiveDateTime => "TIMESTAMP",
-            Type::NaiveTime => "TIME",
             Type::Nullable(ref typ) => {
                 let sql = type_to_sql(&*typ, true);
                 return quote! {
                     #sql
                 };
             },
-            #[cfg(feature = "rusqlite")]
-            Type::Serial => "INTEGER PRIMARY KEY",
-            #[cfg(feature = "postgres")]
-            Type::Serial => "SERIAL PRIMARY KEY",
-            #[cfg(not(any(feature = "rusqlite", feature = "postgres")))]
-            Type::Serial => unreachable!("Enable one of the following features: sqlite, pg"),
-            Type::String => "CHARACTER VARYING",
+            Type::UserDefined(ref typ) => {
+                // NOTE: the SQL type of a user-defined type is only known at runtime, so the
+                // CREATE query is not a string literal when a field uses such a type.
+                let typ: Path = syn::parse_str(typ).expect("user-defined type path");
+                let sql_type = quote! {
+                    <<#typ as ::tql::SqlType>::Underlying as ::tql::ColumnType>::SQL_TYPE
+                };
+                if nullable {
+                    return sql_type;
+                }
+                else {
+                    return quote! {
+                        #sql_type, " NOT NULL"
+                    };
+                }
+            },
+            Type::Generic => "", // TODO: document why this is empty.
             Type::UnsupportedType(_) => "", // TODO: should panic. TODO: document why.
-            Type::UtcDateTime => "TIMESTAMP WITH TIME ZONE",
+            ref typ => builtin_type_to_sql(typ).expect("builtin type"),
         };
 
     let expr = string_literal(sql_type);
@@ -541,6 +936,59 @@ pub fn type_to_sql(typ: &Type, nullable: bool) -> TokenStream {
     }
 }
 
+/// Get the SQL type of a builtin type, known at compile time (`None` for the arrays, the nullable
+/// types, the foreign keys and the user-defined types).
+pub fn builtin_type_to_sql(typ: &Type) -> Option<&'static str> {
+    let sql_type =
+        match *typ {
+            Type::Array(_) | Type::Custom(_) | Type::Generic | Type::Nullable(_) | Type::UnsupportedType(_) |
+                Type::UserDefined(_) => return None,
+            #[cfg(feature = "rusqlite")]
+            Type::BigDecimal | Type::Decimal => "TEXT",
+            #[cfg(not(feature = "rusqlite"))]
+            Type::BigDecimal | Type::Decimal => "NUMERIC",
+            Type::Bool => "BOOLEAN",
+            Type::ByteString => "BYTEA",
+            Type::I8 | Type::Char => "CHARACTER(1)",
+            Type::F32 => "REAL",
+            Type::F64 => "DOUBLE PRECISION",
+            Type::I16 => "SMALLINT",
+            Type::I32 => "INTEGER",
+            Type::I64 => "BIGINT",
+            #[cfg(feature = "rusqlite")]
+            Type::IpAddr => "TEXT",
+            #[cfg(not(feature = "rusqlite"))]
+            Type::IpAddr => "INET",
+            #[cfg(feature = "rusqlite")]
+            Type::Json => "TEXT",
+            #[cfg(not(feature = "rusqlite"))]
+            Type::Json => "JSONB",
+            Type::LocalDateTime => "TIMESTAMP WITH TIME ZONE",
+            Type::NaiveDate => "DATE",
+            Type::NaiveDateTime => "TIMESTAMP",
+            Type::NaiveTime => "TIME",
+            #[cfg(feature = "rusqlite")]
+            Type::Serial => "INTEGER PRIMARY KEY",
+            #[cfg(feature = "postgres")]
+            Type::Serial => "SERIAL PRIMARY KEY",
+            #[cfg(not(any(feature = "rusqlite", feature = "postgres")))]
+            Type::Serial => unreachable!("Enable one of the following features: sqlite, pg"),
+            Type::String => "CHARACTER VARYING",
+            // NOTE: the unsigned integers are stored in a wider signed column since the databases
+            // do not have unsigned integers. u64 is stored in a BIGINT and its values are range
+            // checked when converted.
+            Type::U8 => "SMALLINT",
+            Type::U16 => "INTEGER",
+            Type::U32 | Type::U64 => "BIGINT",
+            Type::UtcDateTime => "TIMESTAMP WITH TIME ZONE",
+            #[cfg(feature = "rusqlite")]
+            Type::Uuid => "BLOB",
+            #[cfg(not(feature = "rusqlite"))]
+            Type::Uuid => "UUID",
+        };
+    Some(sql_type)
+}
+
 impl ToSql for [Order] {
     fn to_sql(&self, index: &mut usize) -> String {
         self.iter().map(|order| order.to_sql(index)).collect::<Vec<_>>().join(", ")
@@ -619,11 +1067,42 @@ pub fn fields_to_sql(fields: &[TypedField]) -> TokenStream {
 /// Convert a `FilterExpression` to either " WHERE " or the empty string if there are no filters.
 fn filter_to_where_clause(filter: &FilterExpression) -> &str {
     match *filter {
-        FilterExpression::Filter(_) | FilterExpression::Filters(_) | FilterExpression::NegFilter(_) | FilterExpression::ParenFilter(_) | FilterExpression::FilterValue(_) => " WHERE ",
+        FilterExpression::Filter(_) | FilterExpression::Filters(_) | FilterExpression::NegFilter(_) | FilterExpression::ParenFilter(_) | FilterExpression::SubQuery(_) | FilterExpression::FilterValue(_) => " WHERE ",
         FilterExpression::NoFilters => "",
     }
 }
 
+/// Convert a `FilterExpression` to the WHERE clause, excluding the rows marked as deleted if
+/// `soft_delete` is true.
+fn filter_to_tokens(filter: &FilterExpression, soft_delete: bool, table: &str, index: &mut usize) -> TokenStream {
+    let filter_tokens = filter.to_tokens(index);
+    let mut conditions = vec![];
+    if soft_delete {
+        conditions.push(format!("{}.deleted_at IS NULL", table));
+    }
+    where_clause_to_tokens(filter, filter_tokens, &conditions)
+}
+
+/// Convert the tokens of a `FilterExpression` to the WHERE clause, adding the SQL `conditions`
+/// generated by tql.
+fn where_clause_to_tokens(filter: &FilterExpression, filter_tokens: TokenStream, conditions: &[String]) -> TokenStream {
+    if conditions.is_empty() {
+        let where_clause = filter_to_where_clause(filter);
+        return quote! {
+            #where_clause, #filter_tokens
+        };
+    }
+    let conditions = conditions.join(" AND ");
+    match *filter {
+        FilterExpression::NoFilters => quote! {
+            " WHERE ", #conditions
+        },
+        _ => quote! {
+            " WHERE (", #filter_tokens, ") AND ", #conditions
+        },
+    }
+}
+
 fn has_order_clauses(orders: &[Order]) -> bool {
     for order in orders {
         if let Order::NoOrder = *order {
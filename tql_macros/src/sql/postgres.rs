@@ -22,10 +22,10 @@
 //! The PostgreSQL code generator.
 
 use proc_macro2::{Span,TokenStream};
-use syn::Ident;
+use syn::{Expr, Ident};
 
-use ast::Aggregate;
-use sql::{SqlBackend, ToSql, string_token};
+use crate::ast::{Aggregate, DateExpression, IntervalOperator, TypedField};
+use crate::sql::{SqlBackend, ToSql, alter_add_columns, string_token};
 
 pub struct PostgresSqlBackend {}
 
@@ -33,15 +33,59 @@ pub fn create_sql_backend() -> PostgresSqlBackend {
     PostgresSqlBackend { }
 }
 
-impl ToSql for Aggregate {
-    fn to_sql(&self, index: &mut usize) -> String {
+impl Aggregate {
+    /// Convert the aggregate to the arguments of `concat!()`.
+    pub fn to_tokens(&self) -> TokenStream {
         // TODO: do not hard-code the type.
-        "CAST(".to_string() + &self.sql_function.to_sql(index) + "(" +
-            &self.field.clone().expect("Aggregate field").to_sql(index) + ") AS DOUBLE PRECISION)"
+        let call = self.call_to_tokens();
+        quote! {
+            "CAST(", #call, " AS DOUBLE PRECISION)"
+        }
+    }
+}
+
+impl ToSql for DateExpression {
+    fn to_sql(&self, index: &mut usize) -> String {
+        match *self {
+            DateExpression::Interval { ref amount, ref date, operator, ref unit } => {
+                let date = date.to_sql(index);
+                let operator =
+                    match operator {
+                        IntervalOperator::Add => "+",
+                        IntervalOperator::Sub => "-",
+                    };
+                let interval =
+                    if let Expr::Lit(_) = *amount {
+                        format!("INTERVAL '{} {}'", amount.to_sql(index), unit)
+                    }
+                    else {
+                        format!("CAST({} AS INTEGER) * INTERVAL '1 {}'", amount.to_sql(index), unit)
+                    };
+                format!("{} {} {}", date, operator, interval)
+            },
+            DateExpression::Now(_) => "NOW()".to_string(),
+        }
     }
 }
 
 impl SqlBackend for PostgresSqlBackend {
+    fn add_columns_query(&self, table: &str, added_columns: &[TypedField], _nullable: bool, _columns: &[TypedField],
+        indexed_columns: &[String]) -> TokenStream
+    {
+        alter_add_columns(table, added_columns, indexed_columns)
+    }
+
+    fn drop_cascade_query(&self, object: &str, name: &str) -> String {
+        format!("DROP {} {} CASCADE", object, name)
+    }
+
+    fn drop_column_query(&self, table: &str, _columns: &[TypedField], _indexed_columns: &[String]) -> TokenStream {
+        let alter_table = format!("ALTER TABLE {} DROP COLUMN ", table);
+        quote! {
+            #alter_table, stringify!($column)
+        }
+    }
+
     fn insert_query(&self, table: &str, fields: &[String], values: &[String]) -> TokenStream {
         let query_start =
             format!("INSERT INTO {table}({fields}) VALUES({values}) RETURNING ",
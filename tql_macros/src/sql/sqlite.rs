@@ -22,9 +22,14 @@
 //! The SQLite code generator.
 
 use proc_macro2::TokenStream;
+use syn::Expr;
 
-use ast::Aggregate;
-use sql::{SqlBackend, ToSql};
+use crate::ast::{Aggregate, DateExpression, IntervalOperator, TypedField};
+use crate::sql::{SqlBackend, ToSql, alter_add_columns, create_indexes_query, fields_to_sql};
+
+/// The format of the date times written by rusqlite, so that they can be compared as text with
+/// the stored values.
+pub const DATE_TIME_FORMAT: &str = "'%Y-%m-%dT%H:%M:%f'";
 
 pub struct SqliteSqlBackend {}
 
@@ -32,13 +37,103 @@ pub fn create_sql_backend() -> SqliteSqlBackend {
     SqliteSqlBackend { }
 }
 
-impl ToSql for Aggregate {
+impl Aggregate {
+    /// Convert the aggregate to the arguments of `concat!()`.
+    pub fn to_tokens(&self) -> TokenStream {
+        self.call_to_tokens()
+    }
+}
+
+impl ToSql for DateExpression {
     fn to_sql(&self, index: &mut usize) -> String {
-        self.sql_function.to_sql(index) + "(" + &self.field.clone().expect("Aggregate field").to_sql(index) + ")"
+        match *self {
+            DateExpression::Interval { ref amount, ref date, operator, ref unit } => {
+                let date = date.to_sql(index);
+                let sign =
+                    match operator {
+                        IntervalOperator::Add => "+",
+                        IntervalOperator::Sub => "-",
+                    };
+                let modifier =
+                    if let Expr::Lit(_) = *amount {
+                        format!("'{}{} {}'", sign, amount.to_sql(index), unit)
+                    }
+                    else {
+                        format!("({}{}) || ' {}'", sign, amount.to_sql(index), unit)
+                    };
+                format!("STRFTIME({}, {}, {})", DATE_TIME_FORMAT, date, modifier)
+            },
+            DateExpression::Now(_) => format!("STRFTIME({}, 'now')", DATE_TIME_FORMAT),
+        }
+    }
+}
+
+/// Convert to the arguments of `concat!()` the queries recreating the `table` with the `columns`
+/// and the indexes on the `indexed_columns`, and copying the `copied_columns` from the old table,
+/// since SQLite cannot drop a column or add a column without default value.
+fn rebuild_table_query(table: &str, columns: &[TypedField], indexed_columns: &[String], copied_columns: &[&str])
+    -> TokenStream
+{
+    let new_table = format!("tql_new_{}", table);
+    let fields = fields_to_sql(columns);
+    let copy_rows =
+        if copied_columns.is_empty() {
+            String::new()
+        }
+        else {
+            let copied_columns = copied_columns.join(", ");
+            format!("INSERT INTO {new_table} ({columns}) SELECT {columns} FROM {table}; ",
+                    columns = copied_columns,
+                    new_table = new_table,
+                    table = table,
+                   )
+        };
+    // NOTE: the indexes are dropped with the old table, so they are created again.
+    let replace_table = format!("{copy_rows}DROP TABLE {table}; ALTER TABLE {new_table} RENAME TO {table}{indexes}",
+                                copy_rows = copy_rows,
+                                indexes = create_indexes_query(table, indexed_columns, false),
+                                new_table = new_table,
+                                table = table,
+                               );
+    // NOTE: the new table is dropped first in case a previous rebuild failed (e.g. because the
+    // existing rows have no value for an added column).
+    let drop_new_table = format!("DROP TABLE IF EXISTS {new_table}; CREATE TABLE {new_table} (", new_table = new_table);
+    quote! {
+        #drop_new_table, #fields, "); ", #replace_table
     }
 }
 
 impl SqlBackend for SqliteSqlBackend {
+    fn add_columns_query(&self, table: &str, added_columns: &[TypedField], nullable: bool, columns: &[TypedField],
+        indexed_columns: &[String]) -> TokenStream
+    {
+        if nullable {
+            alter_add_columns(table, added_columns, indexed_columns)
+        }
+        else {
+            // NOTE: the existing rows get the default value of the added columns (the analyzer rejects
+            // the fields with a column which cannot be NULL and has no default value).
+            let copied_columns: Vec<_> = columns.iter()
+                .filter(|column| !added_columns.iter().any(|added| added.identifier == column.identifier))
+                .map(|column| column.identifier.as_str())
+                .collect();
+            rebuild_table_query(table, columns, indexed_columns, &copied_columns)
+        }
+    }
+
+    fn drop_cascade_query(&self, object: &str, name: &str) -> String {
+        // NOTE: SQLite does not support CASCADE, so drop_cascade() is rejected by the analyzer and this
+        // query is never executed.
+        format!("DROP {} {}", object, name)
+    }
+
+    fn drop_column_query(&self, table: &str, columns: &[TypedField], indexed_columns: &[String]) -> TokenStream {
+        let copied_columns: Vec<_> = columns.iter()
+            .map(|column| column.identifier.as_str())
+            .collect();
+        rebuild_table_query(table, columns, indexed_columns, &copied_columns)
+    }
+
     fn insert_query(&self, table: &str, fields: &[String], values: &[String]) -> TokenStream {
         let query =
             format!("INSERT INTO {table}({fields}) VALUES({values})",
@@ -19,26 +19,43 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
-//! Global mutable states handling.
+//! Global states handling.
 //!
-//! There are four global states:
+//! There are five global states:
 //!
 //! The aggregates global state contains the existing aggregate functions.
 //!
-//! The methods global state contains the existing aggregate functions.
+//! The methods global state contains the existing methods.
+//!
+//! These two states are immutable and initialized on first use.
+//!
+//! The embeds state contains the fields of the structs deriving `SqlEmbed`.
+//!
+//! The tables state contains the information about the tables needed to generate the queries
+//! (e.g. their embedded fields).
+//!
+//! These two states only contain what is known by the macro being expanded: they are cleared at the
+//! start of each expansion (see `clear_state()`) and filled by the derive or from the input given
+//! by the `tql_<Struct>_embed!()` and `tql_<Table>_metadata!()` macros calling the macro back (see
+//! `add_embed_metadata()` and `add_table_metadata()`). They are local to the thread expanding the
+//! macro, so that an expansion depends neither on the order of the expansions nor on the other
+//! crates compiled by the same process.
+//!
+//! The user functions state contains the macros created by the `#[sql_function]` and
+//! `#[sql_aggregate]` attributes. It is filled in the order of the expansions, hence the functions
+//! must be declared before the queries using them.
 
-use std::collections::BTreeMap;
-use std::collections::HashMap;
-use std::mem;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 
 use syn::{self, Ident};
 
-use ast::WithSpan;
-use methods::{add_initial_aggregates, add_initial_methods};
-use types::Type;
+use crate::ast::WithSpan;
+use crate::methods::{initial_aggregates, initial_methods};
+use crate::types::Type;
 
 /// A collection of tql aggregate functions.
-pub type SqlAggregates = HashMap<String, String>;
+pub type SqlAggregates = BTreeMap<String, String>;
 
 #[derive(Debug)]
 pub struct BothTypes {
@@ -49,8 +66,47 @@ pub struct BothTypes {
 /// A collection of fields from an `SqlTable`.
 pub type SqlFields = BTreeMap<Ident, BothTypes>;
 
-/// A collection mapping method names to methods.
-pub type SqlMethods = HashMap<String, SqlMethodTypes>;
+/// A collection mapping method names to methods (one per object type when the method is
+/// overloaded).
+pub type SqlMethods = BTreeMap<String, Vec<SqlMethodTypes>>;
+
+/// A field of a struct deriving `SqlEmbed`.
+// NOTE: the type is stored as a string because the tokens cannot outlive the macro expansion.
+#[derive(Clone)]
+pub struct EmbedField {
+    pub name: String,
+    pub typ: String,
+}
+
+/// A collection mapping the names of the structs deriving `SqlEmbed` (without their path) to their
+/// fields.
+pub type SqlEmbeds = BTreeMap<String, Vec<EmbedField>>;
+
+/// The information about a table deriving `SqlTable` needed to generate the queries.
+#[derive(Clone, Default)]
+pub struct TableInfo {
+    /// The fields set to the current time when a row is updated (`#[sql(auto_now)]`), with the SQL
+    /// expression of the current time.
+    pub auto_now_fields: Vec<(String, String)>,
+    /// The decimal fields, which cannot be ordered by SQLite since it stores them as text.
+    pub decimal_fields: Vec<String>,
+    /// The embedded fields, with the name of their struct.
+    pub embedded_fields: BTreeMap<String, String>,
+    /// The names of the fields, in declaration order.
+    pub fields: Vec<String>,
+    /// The tables referenced by the `ForeignKey` fields.
+    pub related_tables: Vec<String>,
+    /// The fields with a column which cannot be `NULL` and has no default value.
+    pub required_fields: Vec<String>,
+    /// The rows are marked as deleted by setting the `deleted_at` field to this SQL expression of the
+    /// current time (`#[sql(soft_delete)]`).
+    pub soft_delete: Option<String>,
+    /// The field incremented by each update to detect concurrent updates (`#[sql(version)]`).
+    pub version_field: Option<String>,
+}
+
+/// A collection mapping the table names to their information.
+pub type SqlTables = BTreeMap<String, TableInfo>;
 
 /// Tql method return type, argument types and template.
 pub struct SqlMethodTypes {
@@ -60,32 +116,250 @@ pub struct SqlMethodTypes {
     pub template: Option<String>,
 }
 
+lazy_static! {
+    static ref AGGREGATES: SqlAggregates = initial_aggregates();
+    static ref METHODS: SqlMethods = initial_methods();
+}
+
+thread_local! {
+    static EMBEDS: RefCell<SqlEmbeds> = RefCell::new(SqlEmbeds::new());
+    static TABLES: RefCell<SqlTables> = RefCell::new(SqlTables::new());
+    static USER_FUNCTIONS: RefCell<BTreeSet<String>> = RefCell::new(BTreeSet::new());
+}
+
 /// Returns the global aggregate state.
-pub fn aggregates_singleton() -> &'static mut SqlAggregates {
-    // FIXME: make this thread safe.
-    static mut HASH_MAP: *mut SqlAggregates = 0 as *mut SqlAggregates;
-
-    let map: SqlAggregates = HashMap::new();
-    unsafe {
-        if HASH_MAP == 0 as *mut SqlAggregates {
-            HASH_MAP = mem::transmute(Box::new(map));
-            add_initial_aggregates();
+pub fn aggregates_singleton() -> &'static SqlAggregates {
+    &AGGREGATES
+}
+
+/// Returns the global methods state.
+pub fn methods_singleton() -> &'static SqlMethods {
+    &METHODS
+}
+
+/// Clear the embeds and tables states filled by the previous expansion.
+pub fn clear_state() {
+    EMBEDS.with(|embeds| embeds.borrow_mut().clear());
+    TABLES.with(|tables| tables.borrow_mut().clear());
+}
+
+/// Get the name of the struct `embed`, which may be qualified by its path.
+fn embed_name(embed: &str) -> &str {
+    embed.rsplit("::").next().unwrap_or(embed)
+}
+
+/// Call `f` with the information about the `table` (`None` if the table is unknown).
+fn with_table<F: FnOnce(&TableInfo) -> T, T>(table: &str, f: F) -> Option<T> {
+    TABLES.with(|tables| tables.borrow().get(table).map(f))
+}
+
+/// Add the `fields` of the struct `embed` deriving `SqlEmbed`.
+pub fn add_embed(embed: &str, fields: Vec<EmbedField>) {
+    EMBEDS.with(|embeds| embeds.borrow_mut().insert(embed_name(embed).to_string(), fields));
+}
+
+/// Get the fields of the struct `embed` deriving `SqlEmbed` (`None` if the struct is unknown).
+pub fn embed_fields(embed: &str) -> Option<Vec<EmbedField>> {
+    EMBEDS.with(|embeds| embeds.borrow().get(embed_name(embed)).cloned())
+}
+
+/// Check if the struct `embed` derives `SqlEmbed`.
+pub fn is_embed(embed: &str) -> bool {
+    EMBEDS.with(|embeds| embeds.borrow().contains_key(embed_name(embed)))
+}
+
+/// Get the fields of the struct deriving `SqlEmbed` as text, to be exported by the
+/// `tql_<Struct>_embed!()` macro.
+///
+/// There is one field per line, with its name and its type separated by a tab.
+pub fn embed_metadata(fields: &[EmbedField]) -> String {
+    fields.iter()
+        .map(|field| format!("{}\t{}", field.name, field.typ))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Add the fields of the struct `embed` from the text produced by `embed_metadata()`.
+pub fn add_embed_metadata(embed: &str, metadata: &str) {
+    let fields = metadata.lines()
+        .filter_map(|line| {
+            let mut values = line.splitn(2, '\t');
+            Some(EmbedField {
+                name: values.next()?.to_string(),
+                typ: values.next()?.to_string(),
+            })
+        })
+        .collect();
+    add_embed(embed, fields);
+}
+
+/// Add the information about the `table` deriving `SqlTable`.
+pub fn add_table(table: String, info: TableInfo) {
+    TABLES.with(|tables| tables.borrow_mut().insert(table, info));
+}
+
+/// Check if the information about the `table` is known.
+pub fn is_table_known(table: &str) -> bool {
+    TABLES.with(|tables| tables.borrow().contains_key(table))
+}
+
+/// Check if the column of the `field` of the `table` cannot be `NULL` and has no default value (or
+/// one of its columns if it is embedded).
+pub fn is_required_field(table: &str, field: &str) -> bool {
+    with_table(table, |table| table.required_fields.iter().any(|required_field| required_field == field))
+        .unwrap_or(false)
+}
+
+/// Get the fields of the `table` set to the current time when a row is updated, with the SQL
+/// expression of the current time.
+pub fn auto_now_fields(table: &str) -> Vec<(String, String)> {
+    with_table(table, |table| table.auto_now_fields.clone())
+        .unwrap_or_default()
+}
+
+/// Check if the rows of the `table` are marked as deleted instead of being deleted.
+pub fn is_soft_delete(table: &str) -> bool {
+    soft_delete_time(table).is_some()
+}
+
+/// Get the SQL expression of the current time set in the `deleted_at` field of the `table` to mark
+/// a row as deleted (`None` if the rows are deleted).
+pub fn soft_delete_time(table: &str) -> Option<String> {
+    with_table(table, |table| table.soft_delete.clone())?
+}
+
+/// Check if the `field` of the `table` is a decimal number.
+pub fn is_decimal_field(table: &str, field: &str) -> bool {
+    with_table(table, |table| table.decimal_fields.iter().any(|decimal_field| decimal_field == field))
+        .unwrap_or(false)
+}
+
+/// Get the fields of the struct embedded in the `field` of `table` (`None` if the field is not
+/// embedded).
+pub fn embedded_fields(table: &str, field: &str) -> Option<Vec<EmbedField>> {
+    let embed = with_table(table, |table| table.embedded_fields.get(field).cloned())??;
+    embed_fields(&embed)
+}
+
+/// Get the columns of the `table`, where each embedded field is replaced by the columns of its
+/// struct (`None` if the table is unknown).
+pub fn table_columns(table: &str) -> Option<Vec<String>> {
+    let fields = with_table(table, |table| table.fields.clone())?;
+    let columns = fields.into_iter()
+        .flat_map(|field| match embedded_fields(table, &field) {
+            Some(embed_fields) => embed_fields.iter()
+                .map(|embed_field| format!("{}_{}", field, embed_field.name))
+                .collect(),
+            None => vec![field],
+        })
+        .collect();
+    Some(columns)
+}
+
+/// Get the tables referenced by the `ForeignKey` fields of the `table`.
+pub fn related_tables(table: &str) -> Vec<String> {
+    with_table(table, |table| table.related_tables.clone())
+        .unwrap_or_default()
+}
+
+/// Get the field of the `table` holding the version of the rows (`None` if the table has no
+/// `#[sql(version)]` field).
+pub fn version_field(table: &str) -> Option<String> {
+    with_table(table, |table| table.version_field.clone())?
+}
+
+/// Get the information about the `table` and its embedded structs as text, to be exported by the
+/// `tql_<Table>_metadata!()` macro.
+///
+/// There is one item per line, with its values separated by tabs:
+///
+/// ```text
+/// field	<name>
+/// embed	<field>	<struct>
+/// embed_field	<struct>	<name>	<type>
+/// related	<table>
+/// required	<field>
+/// auto_now	<field>	<SQL expression>
+/// decimal	<field>
+/// soft_delete	<SQL expression>
+/// version	<field>
+/// ```
+pub fn table_metadata(table: &str) -> String {
+    let info =
+        match with_table(table, TableInfo::clone) {
+            Some(info) => info,
+            None => return String::new(),
+        };
+    let mut lines = vec![];
+    for field in &info.fields {
+        lines.push(format!("field\t{}", field));
+    }
+    for (field, embed) in &info.embedded_fields {
+        lines.push(format!("embed\t{}\t{}", field, embed));
+        for embed_field in embed_fields(embed).into_iter().flatten() {
+            lines.push(format!("embed_field\t{}\t{}\t{}", embed, embed_field.name, embed_field.typ));
         }
-        &mut *HASH_MAP
     }
+    for related_table in &info.related_tables {
+        lines.push(format!("related\t{}", related_table));
+    }
+    for field in &info.required_fields {
+        lines.push(format!("required\t{}", field));
+    }
+    for &(ref field, ref current_time) in &info.auto_now_fields {
+        lines.push(format!("auto_now\t{}\t{}", field, current_time));
+    }
+    for field in &info.decimal_fields {
+        lines.push(format!("decimal\t{}", field));
+    }
+    if let Some(ref current_time) = info.soft_delete {
+        lines.push(format!("soft_delete\t{}", current_time));
+    }
+    if let Some(ref field) = info.version_field {
+        lines.push(format!("version\t{}", field));
+    }
+    lines.join("\n")
 }
 
-/// Returns the global methods state.
-pub fn methods_singleton() -> &'static mut SqlMethods {
-    // FIXME: make this thread safe.
-    static mut HASH_MAP: *mut SqlMethods = 0 as *mut SqlMethods;
-
-    let map: SqlMethods = HashMap::new();
-    unsafe {
-        if HASH_MAP == 0 as *mut SqlMethods {
-            HASH_MAP = mem::transmute(Box::new(map));
-            add_initial_methods();
+/// Add the information about the `table` from the text produced by `table_metadata()`.
+pub fn add_table_metadata(table: &str, metadata: &str) {
+    let mut info = TableInfo::default();
+    let mut embed_fields: BTreeMap<String, Vec<EmbedField>> = BTreeMap::new();
+    for line in metadata.lines() {
+        let values: Vec<_> = line.split('\t').collect();
+        match values.as_slice() {
+            ["field", field] => info.fields.push(field.to_string()),
+            ["embed", field, embed] => {
+                info.embedded_fields.insert(field.to_string(), embed.to_string());
+                embed_fields.entry(embed.to_string()).or_insert_with(Vec::new);
+            },
+            ["embed_field", embed, name, typ] =>
+                embed_fields.entry(embed.to_string()).or_insert_with(Vec::new).push(EmbedField {
+                    name: name.to_string(),
+                    typ: typ.to_string(),
+                }),
+            ["related", table] => info.related_tables.push(table.to_string()),
+            ["required", field] => info.required_fields.push(field.to_string()),
+            ["auto_now", field, current_time] =>
+                info.auto_now_fields.push((field.to_string(), current_time.to_string())),
+            ["decimal", field] => info.decimal_fields.push(field.to_string()),
+            ["soft_delete", current_time] => info.soft_delete = Some(current_time.to_string()),
+            ["version", field] => info.version_field = Some(field.to_string()),
+            _ => (),
         }
-        &mut *HASH_MAP
     }
+    for (embed, fields) in embed_fields {
+        add_embed(&embed, fields);
+    }
+    add_table(table.to_string(), info);
+}
+
+/// Add the macro `macro_name` created for a user-defined function.
+pub fn add_user_function(macro_name: String) {
+    USER_FUNCTIONS.with(|functions| functions.borrow_mut().insert(macro_name));
+}
+
+/// Check if the macro `macro_name` was created for a user-defined function.
+pub fn is_user_function(macro_name: &str) -> bool {
+    USER_FUNCTIONS.with(|functions| functions.borrow().contains(macro_name))
 }
@@ -88,6 +88,16 @@ fn levenshtein_distance(string1: &str, string2: &str) -> usize {
     d[string1.len()][string2.len()]
 }
 
+/// Returns "" if count equals 1, "s" otherwise.
+pub fn plural<'a>(count: usize) -> &'a str {
+    if count == 1 {
+        ""
+    }
+    else {
+        "s"
+    }
+}
+
 /// Returns " was" if count equals 1, "s were" otherwise.
 pub fn plural_verb<'a>(count: usize) -> &'a str {
     if count == 1 {
@@ -36,16 +36,19 @@ use syn::{
     TypePath,
 };
 
-use ast::Expression;
-use sql;
+use crate::ast::Expression;
+use crate::sql;
 
 /// A field type.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Type {
+    Array(Box<Type>),
+    BigDecimal,
     Bool,
     ByteString,
     Char,
     Custom(String),
+    Decimal,
     F32,
     F64,
     Generic,
@@ -53,6 +56,8 @@ pub enum Type {
     I16,
     I32,
     I64,
+    IpAddr,
+    Json,
     LocalDateTime,
     NaiveDate,
     NaiveDateTime,
@@ -60,18 +65,94 @@ pub enum Type {
     Nullable(Box<Type>),
     Serial,
     String,
+    U8,
+    U16,
+    U32,
+    U64,
     UnsupportedType(String),
+    UserDefined(String),
     UtcDateTime,
+    Uuid,
+}
+
+impl Type {
+    /// Check if the values of this type are converted by `tql::SqlValue` because the database
+    /// library does not support them (or does not map them to the right column type).
+    pub fn needs_conversion(&self) -> bool {
+        match *self {
+            Type::BigDecimal | Type::Decimal | Type::IpAddr | Type::Json | Type::U8 | Type::U16 | Type::U32 |
+                Type::U64 | Type::UserDefined(_) | Type::Uuid => true,
+            Type::Nullable(ref typ) => typ.needs_conversion(),
+            _ => false,
+        }
+    }
+
+    /// Check if this type is a decimal number (or a nullable decimal number).
+    pub fn is_decimal(&self) -> bool {
+        match *self {
+            Type::BigDecimal | Type::Decimal => true,
+            Type::Nullable(ref typ) => typ.is_decimal(),
+            _ => false,
+        }
+    }
+
+    /// Check if the values of this type can be `NULL`.
+    pub fn is_nullable(&self) -> bool {
+        match *self {
+            Type::Nullable(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Check if this type is an array (or a nullable array).
+    pub fn is_array(&self) -> bool {
+        match *self {
+            Type::Array(_) => true,
+            Type::Nullable(ref typ) => typ.is_array(),
+            _ => false,
+        }
+    }
+
+    /// Get the SQL expression of the current date or time for this type (`None` if this type is not
+    /// a date or a time).
+    pub fn current_time_sql(&self) -> Option<&'static str> {
+        match *self {
+            #[cfg(not(feature = "rusqlite"))]
+            Type::LocalDateTime | Type::NaiveDateTime | Type::UtcDateTime => Some("CURRENT_TIMESTAMP"),
+            // NOTE: SQLite compares the dates as text, so they are written in the format of rusqlite
+            // instead of the one of CURRENT_TIMESTAMP: the date times with a time zone are stored in UTC.
+            #[cfg(feature = "rusqlite")]
+            Type::LocalDateTime | Type::UtcDateTime => Some("(STRFTIME('%Y-%m-%dT%H:%M:%f+00:00', 'now'))"),
+            #[cfg(feature = "rusqlite")]
+            Type::NaiveDateTime => Some("(STRFTIME('%Y-%m-%dT%H:%M:%f', 'now'))"),
+            Type::NaiveDate => Some("CURRENT_DATE"),
+            Type::NaiveTime => Some("CURRENT_TIME"),
+            Type::Nullable(ref typ) => typ.current_time_sql(),
+            _ => None,
+        }
+    }
+
+    /// Check if this type can be the element type of an array column.
+    fn is_array_element(&self) -> bool {
+        match *self {
+            Type::Bool | Type::F32 | Type::F64 | Type::I16 | Type::I32 | Type::I64 | Type::LocalDateTime |
+                Type::NaiveDate | Type::NaiveDateTime | Type::NaiveTime | Type::String | Type::UtcDateTime => true,
+            _ => false,
+        }
+    }
 }
 
 impl Display for Type {
     /// Get a string representation of the SQL `Type` for display in error messages.
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let typ = match *self {
+            Type::Array(ref typ) => "Vec<".to_string() + &typ.to_string() + ">",
+            Type::BigDecimal => "bigdecimal::BigDecimal".to_string(),
             Type::Bool => "bool".to_string(),
             Type::ByteString => "Vec<u8>".to_string(),
             Type::Char => "char".to_string(),
             Type::Custom(ref typ) => typ.clone(),
+            Type::Decimal => "rust_decimal::Decimal".to_string(),
             Type::F32 => "f32".to_string(),
             Type::F64 => "f64".to_string(),
             Type::Generic => "".to_string(),
@@ -79,6 +160,8 @@ impl Display for Type {
             Type::I16 => "i16".to_string(),
             Type::I32 => "i32".to_string(),
             Type::I64 => "i64".to_string(),
+            Type::IpAddr => "std::net::IpAddr".to_string(),
+            Type::Json => "serde_json::Value".to_string(),
             Type::LocalDateTime => "chrono::datetime::DateTime<chrono::offset::Local>".to_string(),
             Type::NaiveDate => "chrono::naive::NaiveDate".to_string(),
             Type::NaiveDateTime => "chrono::naive::NaiveDateTime".to_string(),
@@ -86,8 +169,14 @@ impl Display for Type {
             Type::Nullable(ref typ) => "Option<".to_string() + &typ.to_string() + ">",
             Type::Serial => "i32".to_string(),
             Type::String => "String".to_string(),
+            Type::U8 => "u8".to_string(),
+            Type::U16 => "u16".to_string(),
+            Type::U32 => "u32".to_string(),
+            Type::U64 => "u64".to_string(),
             Type::UnsupportedType(_) => "".to_string(),
+            Type::UserDefined(ref typ) => typ.clone(),
             Type::UtcDateTime => "chrono::datetime::DateTime<chrono::offset::Utc>".to_string(),
+            Type::Uuid => "uuid::Uuid".to_string(),
         };
         write!(f, "{}", typ)
     }
@@ -107,6 +196,10 @@ impl PartialEq<Expression> for Type {
                 Type::Nullable(ref typ) => typ,
                 ref typ => typ,
             };
+        if let Type::UserDefined(_) = *typ {
+            // The literals compared to a user-defined type are type checked by rustc.
+            return true;
+        }
         match *expression {
             Expr::Lit(ExprLit { lit: Lit::Bool(_), .. }) => *typ == Type::Bool,
             Expr::Lit(ExprLit { lit: Lit::Byte(_), .. }) => false,
@@ -117,7 +210,11 @@ impl PartialEq<Expression> for Type {
                     // TODO: check if right suffix.
                     FloatSuffix::F32 => *typ == Type::F32,
                     FloatSuffix::F64 => *typ == Type::F64,
-                    FloatSuffix::None => *typ == Type::F32 || *typ == Type::F64,
+                    FloatSuffix::None =>
+                        *typ == Type::F32 ||
+                        *typ == Type::F64 ||
+                        *typ == Type::Decimal ||
+                        *typ == Type::BigDecimal,
                 },
             Expr::Lit(ExprLit { lit: Lit::Int(ref int), .. }) =>
                 match int.suffix() {
@@ -126,14 +223,22 @@ impl PartialEq<Expression> for Type {
                     IntSuffix::I16 => *typ == Type::I16,
                     IntSuffix::I32 => *typ == Type::I32 || *typ == Type::Serial,
                     IntSuffix::I64 => *typ == Type::I64,
-                    IntSuffix::U8 | IntSuffix::U16 | IntSuffix::U32 | IntSuffix::U64 | IntSuffix::U128 |
-                        IntSuffix::Usize | IntSuffix::I128 => false,
+                    IntSuffix::U8 => *typ == Type::U8,
+                    IntSuffix::U16 => *typ == Type::U16,
+                    IntSuffix::U32 => *typ == Type::U32,
+                    IntSuffix::U64 => *typ == Type::U64,
+                    IntSuffix::U128 | IntSuffix::Usize | IntSuffix::I128 => false,
                     IntSuffix::None =>
                         *typ == Type::I8 ||
                         *typ == Type::I16 ||
                         *typ == Type::I32 ||
                         *typ == Type::I64 ||
-                        *typ == Type::Serial,
+                        *typ == Type::Serial ||
+                        *typ == Type::U8 ||
+                        *typ == Type::U16 ||
+                        *typ == Type::U32 ||
+                        *typ == Type::U64 ||
+                        *typ == Type::Decimal || *typ == Type::BigDecimal,
                 },
             Expr::Lit(ExprLit { lit: Lit::Str(_), .. }) => *typ == Type::String,
             _ => true, // Returns true, because the type checking for non-literal is done later.
@@ -143,13 +248,13 @@ impl PartialEq<Expression> for Type {
 
 impl<'a> From<&'a Path> for Type {
     /// Convert a `Path` to a `Type`.
-    fn from(&Path { ref segments, .. }: &Path) -> Type {
-        let unsupported = Type::UnsupportedType("".to_string());
+    fn from(&Path { ref leading_colon, ref segments }: &Path) -> Type {
         if segments.len() == 1 {
             let element = segments.first().expect("first segment of path");
             let first_segment = element.value();
             let ident = first_segment.ident.to_string();
             match &ident[..] {
+                "BigDecimal" => Type::BigDecimal,
                 "bool" => Type::Bool,
                 "char" => Type::Char,
                 "DateTime" => match get_type_parameter(&first_segment.arguments) {
@@ -160,12 +265,15 @@ impl<'a> From<&'a Path> for Type {
                     },
                     None => Type::UnsupportedType("DateTime".to_string()),
                 },
+                "Decimal" => Type::Decimal,
                 "f32" => Type::F32,
                 "f64" => Type::F64,
                 "i8" => Type::I8,
                 "i16" => Type::I16,
                 "i32" => Type::I32,
                 "i64" => Type::I64,
+                "IpAddr" => Type::IpAddr,
+                "Json" => Type::Json,
                 "ForeignKey" => match get_type_parameter(&first_segment.arguments) {
                     Some(ty) => Type::Custom(ty),
                     None => Type::UnsupportedType("ForeignKey".to_string()),
@@ -194,18 +302,44 @@ impl<'a> From<&'a Path> for Type {
                 "String" => {
                     Type::String
                 },
-                "Vec" => match get_type_parameter(&first_segment.arguments) {
-                    Some(ty) => match ty.as_ref() {
-                        "u8" => Type::ByteString,
-                        parameter_type => Type::UnsupportedType("Vec<".to_string() + parameter_type + ">"),
+                "u8" => Type::U8,
+                "u16" => Type::U16,
+                "u32" => Type::U32,
+                "u64" => Type::U64,
+                "Uuid" => Type::Uuid,
+                "Vec" => match get_type_parameter_as_path(&first_segment.arguments) {
+                    Some(path) => {
+                        let typ = From::from(path);
+                        match typ {
+                            Type::U8 => Type::ByteString,
+                            ref typ if typ.is_array_element() => Type::Array(Box::new(typ.clone())),
+                            Type::UnsupportedType(typ) => Type::UnsupportedType("Vec<".to_string() + &typ + ">"),
+                            typ => Type::UnsupportedType("Vec<".to_string() + &typ.to_string() + ">"),
+                        }
                     },
                     None => Type::UnsupportedType("Vec".to_string()),
                 },
+                typ if first_segment.arguments.is_empty() => Type::UserDefined(typ.to_string()),
                 typ => Type::UnsupportedType(typ.to_string()), // TODO: show the generic types as well.
             }
         }
         else {
-            unsupported
+            let path = segments.iter()
+                .map(|segment| segment.ident.to_string())
+                .collect::<Vec<_>>()
+                .join("::");
+            match &path[..] {
+                "bigdecimal::BigDecimal" => Type::BigDecimal,
+                "rust_decimal::Decimal" => Type::Decimal,
+                "serde_json::Value" | "tql::Json" => Type::Json,
+                "net::IpAddr" | "std::net::IpAddr" => Type::IpAddr,
+                "uuid::Uuid" => Type::Uuid,
+                _ if segments.iter().all(|segment| segment.arguments.is_empty()) => {
+                    let prefix = leading_colon.map(|_| "::").unwrap_or("");
+                    Type::UserDefined(prefix.to_string() + &path)
+                },
+                _ => Type::UnsupportedType(path),
+            }
         }
     }
 }
@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) 2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! The snapshot of the database schema and the comparison of the SQL types, shared by the
+//! migrations of tql and the compile-time check of #[sql(schema = "…")] in tql_macros.
+
+/// The first line of a snapshot.
+pub const SNAPSHOT_HEADER: &str = "tql schema 1";
+
+/// A table of a snapshot.
+pub struct SnapshotTable<'a> {
+    pub name: &'a str,
+    pub columns: Vec<SnapshotColumn<'a>>,
+    pub indexes: Vec<SnapshotIndex<'a>>,
+}
+
+/// A column of a snapshot.
+pub struct SnapshotColumn<'a> {
+    pub name: &'a str,
+    /// The SQL type of the column, with its `PRIMARY KEY` or `REFERENCES` constraint.
+    pub sql_type: &'a str,
+    pub nullable: bool,
+    /// The SQL expression of the default value (not checked by #[sql(schema = "…")]).
+    pub default: Option<&'a str>,
+}
+
+/// An index of a snapshot.
+// NOTE: the indexes are not checked by #[sql(schema = "…")].
+pub struct SnapshotIndex<'a> {
+    pub name: &'a str,
+    pub columns: Vec<&'a str>,
+}
+
+/// Parse a snapshot: a header followed by one line per table, column and index, whose parts are
+/// separated by tabulations.
+pub fn parse_snapshot<'a>(snapshot: &'a str) -> Result<Vec<SnapshotTable<'a>>, String> {
+    let mut lines = snapshot.lines().enumerate();
+    match lines.next() {
+        Some((_, SNAPSHOT_HEADER)) => (),
+        _ => return Err(format!("expected `{}` on the first line", SNAPSHOT_HEADER)),
+    }
+    let mut tables: Vec<SnapshotTable> = vec![];
+    for (index, line) in lines {
+        let invalid_line = || format!("unexpected line {}: {}", index + 1, line);
+        let parts: Vec<_> = line.split('\t').collect();
+        match (parts[0], parts.len()) {
+            ("", 1) => (),
+            ("table", 2) => tables.push(SnapshotTable {
+                name: parts[1],
+                columns: vec![],
+                indexes: vec![],
+            }),
+            ("column", 4) | ("column", 5) => {
+                let nullable =
+                    match parts[3] {
+                        "NULL" => true,
+                        "NOT NULL" => false,
+                        _ => return Err(invalid_line()),
+                    };
+                let table = tables.last_mut().ok_or_else(invalid_line)?;
+                table.columns.push(SnapshotColumn {
+                    name: parts[1],
+                    sql_type: parts[2],
+                    nullable,
+                    default: parts.get(4).cloned(),
+                });
+            },
+            ("index", 3) => {
+                let table = tables.last_mut().ok_or_else(invalid_line)?;
+                table.indexes.push(SnapshotIndex {
+                    name: parts[1],
+                    columns: parts[2].split(',').collect(),
+                });
+            },
+            _ => return Err(invalid_line()),
+        }
+    }
+    Ok(tables)
+}
+
+/// Split the SQL type in the type itself and its `PRIMARY KEY` or `REFERENCES` constraint.
+pub fn type_and_constraint(sql_type: &str) -> (&str, &str) {
+    let upper_sql_type = sql_type.to_ascii_uppercase();
+    let index = [" PRIMARY KEY", " REFERENCES"].iter()
+        .filter_map(|constraint| upper_sql_type.find(constraint))
+        .min()
+        .unwrap_or(sql_type.len());
+    let (typ, constraint) = sql_type.split_at(index);
+    (typ.trim(), constraint.trim())
+}
+
+/// Convert an SQL type to the name used by the `CREATE TABLE` queries of tql, so that the aliases
+/// (e.g. `INT4` and `INTEGER`) and the types read by the same Rust type (e.g. `TEXT` and
+/// `VARCHAR(255)`) are considered equal.
+pub fn normalize_type(typ: &str) -> String {
+    let typ = typ.trim().to_uppercase();
+    let (typ, array) =
+        if typ.ends_with("[]") {
+            (typ[..typ.len() - 2].trim(), "[]")
+        }
+        else {
+            (typ.as_str(), "")
+        };
+    let (name, modifier) =
+        match typ.find('(') {
+            Some(index) => (typ[..index].trim(), &typ[index..]),
+            None => (typ, ""),
+        };
+    let name =
+        match name {
+            "BIGSERIAL" | "INT8" | "SERIAL8" => "BIGINT",
+            "BOOL" => "BOOLEAN",
+            "BPCHAR" | "CHAR" => "CHARACTER",
+            "DECIMAL" => "NUMERIC",
+            "FLOAT4" => "REAL",
+            "FLOAT8" => "DOUBLE PRECISION",
+            "INT" | "INT4" | "SERIAL" | "SERIAL4" => "INTEGER",
+            "INT2" | "SERIAL2" | "SMALLSERIAL" => "SMALLINT",
+            "JSON" => "JSONB",
+            "TEXT" | "VARCHAR" => "CHARACTER VARYING",
+            "TIMESTAMP WITHOUT TIME ZONE" => "TIMESTAMP",
+            "TIMESTAMPTZ" => "TIMESTAMP WITH TIME ZONE",
+            name => name,
+        };
+    let modifier =
+        match name {
+            // NOTE: the length of a string or the precision of a number does not change the Rust
+            // type the column is read into.
+            "CHARACTER VARYING" | "NUMERIC" => "",
+            "CHARACTER" if modifier.is_empty() => "(1)",
+            _ => modifier,
+        };
+    format!("{}{}{}", name, modifier, array)
+}